@@ -0,0 +1,46 @@
+//! Wall-clock time of commands that should return almost instantly
+
+use std::{path::Path, process::Command};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+fn grm(home: &Path, args: &[&str]) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_grm"));
+    command
+        .args(args)
+        .env("HOME", home)
+        .env("GRM_ROOT", home.join("grm"))
+        .env("GIT_CONFIG_NOSYSTEM", "1")
+        .env_remove("XDG_CONFIG_HOME")
+        .env_remove("XDG_CACHE_HOME")
+        .env_remove("XDG_STATE_HOME");
+    command
+}
+
+fn run(command: &mut Command) {
+    let output = command.output().unwrap();
+    assert!(output.status.success(), "{output:?}");
+}
+
+fn startup(c: &mut Criterion) {
+    let home = std::env::temp_dir().join(format!("grm-bench-{}", std::process::id()));
+    for name in ["a/one", "a/two", "b/three"] {
+        let path = home.join("grm/example.com").join(name);
+        std::fs::create_dir_all(&path).unwrap();
+        run(Command::new("git")
+            .args(["init", "--quiet"])
+            .current_dir(&path));
+    }
+    run(&mut grm(&home, &["completion-data", "--refresh"]));
+
+    c.bench_function("help", |b| b.iter(|| run(&mut grm(&home, &["--help"]))));
+    c.bench_function("root", |b| b.iter(|| run(&mut grm(&home, &["root"]))));
+    c.bench_function("list --cached", |b| {
+        b.iter(|| run(&mut grm(&home, &["list", "--cached"])))
+    });
+
+    std::fs::remove_dir_all(&home).unwrap();
+}
+
+criterion_group!(benches, startup);
+criterion_main!(benches);
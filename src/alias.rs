@@ -0,0 +1,119 @@
+use anyhow::{bail, ensure, Result};
+
+use crate::{setup, startup::LazyConfig};
+
+/// Aliases are stored as `grm.alias.<name>` in the global config
+fn key(name: &str) -> String {
+    format!("{}.alias.{name}", env!("CARGO_PKG_NAME"))
+}
+
+pub fn list(config: &git2::Config) -> Result<Vec<(String, String)>> {
+    let prefix = key("");
+    let mut aliases = Vec::new();
+    config
+        .entries(Some(&format!("^{}", regex::escape(&prefix))))?
+        .for_each(|entry| {
+            if let (Some(name), Some(value)) = (entry.name(), entry.value()) {
+                aliases.push((name[prefix.len()..].to_string(), value.to_string()));
+            }
+        })?;
+    Ok(aliases)
+}
+
+pub fn set(name: &str, value: &str) -> Result<()> {
+    split(value)?;
+    setup::global_config()?.set_str(&key(name), value)?;
+    Ok(())
+}
+
+/// Returns `false` if the alias is not defined
+pub fn remove(name: &str) -> Result<bool> {
+    setup::remove_global(&key(name))
+}
+
+/// Replace the subcommand in `args` with its definition if it is an alias
+///
+/// Built-in commands cannot be overridden, and aliases can refer to other aliases.
+pub fn expand(
+    config: &LazyConfig,
+    command: &clap::Command,
+    mut args: Vec<String>,
+) -> Result<Vec<String>> {
+    let mut seen = Vec::new();
+    loop {
+        let Some(i) = subcommand_index(command, &args) else {
+            return Ok(args);
+        };
+        let name = &args[i];
+        if command.find_subcommand(name).is_some() {
+            return Ok(args);
+        }
+        let Ok(value) = config.get()?.get_string(&key(name)) else {
+            return Ok(args);
+        };
+        ensure!(!seen.contains(name), "alias `{name}` expands to itself");
+        seen.push(name.clone());
+        args.splice(i..=i, split(&value)?);
+    }
+}
+
+/// Position of the first argument that is neither a global option nor its value
+fn subcommand_index(command: &clap::Command, args: &[String]) -> Option<usize> {
+    let mut i = 1;
+    while i < args.len() {
+        let arg = &args[i];
+        if arg == "--" {
+            return None;
+        }
+        if let Some(long) = arg.strip_prefix("--") {
+            let takes_value = command
+                .get_arguments()
+                .any(|a| a.get_long() == Some(long) && a.get_action().takes_values());
+            i += if takes_value { 2 } else { 1 };
+        } else if arg.starts_with('-') {
+            i += 1;
+        } else {
+            return Some(i);
+        }
+    }
+    None
+}
+
+/// Split a command line into words, honoring single and double quotes
+fn split(s: &str) -> Result<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word = None::<String>;
+    let mut quote = None;
+    for c in s.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => word.get_or_insert_with(String::new).push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                word.get_or_insert_with(String::new);
+            }
+            (None, c) if c.is_whitespace() => words.extend(word.take()),
+            (None, c) => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if quote.is_some() {
+        bail!("unterminated quote in `{s}`");
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+#[cfg(test)]
+mod test_split {
+    use super::*;
+
+    #[test]
+    fn words() {
+        assert_eq!(split("update  --prune").unwrap(), ["update", "--prune"]);
+        assert_eq!(
+            split(r#"grep -F "fn main" ''"#).unwrap(),
+            ["grep", "-F", "fn main", ""]
+        );
+        assert!(split("grep 'a").is_err());
+    }
+}
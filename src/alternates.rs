@@ -0,0 +1,68 @@
+//! Objects borrowed from other repositories through `objects/info/alternates`, as
+//! `git clone --reference` sets up
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use git2::Repository;
+
+use crate::git;
+
+fn alternates_file(repo: &Repository) -> PathBuf {
+    repo.path().join("objects").join("info").join("alternates")
+}
+
+fn objects_dir(path: &Path) -> Option<PathBuf> {
+    let repo = Repository::open(path).ok()?;
+    repo.path().join("objects").canonicalize().ok()
+}
+
+/// Object directories the repository at `path` borrows from
+pub fn borrowed(path: &Path) -> Vec<PathBuf> {
+    let Ok(repo) = Repository::open(path) else {
+        return Vec::new();
+    };
+    let Ok(content) = std::fs::read_to_string(alternates_file(&repo)) else {
+        return Vec::new();
+    };
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+        // relative paths are relative to the objects directory
+        .filter_map(|line| {
+            repo.path()
+                .join("objects")
+                .join(line.trim())
+                .canonicalize()
+                .ok()
+        })
+        .collect()
+}
+
+/// Repositories among `repos` borrowing objects from the one at `path`
+pub fn dependents(path: &Path, repos: &[PathBuf]) -> Vec<PathBuf> {
+    let Some(objects) = objects_dir(path) else {
+        return Vec::new();
+    };
+    repos
+        .iter()
+        .filter(|repo| *repo != path && borrowed(repo).contains(&objects))
+        .cloned()
+        .collect()
+}
+
+/// Copy the borrowed objects into the repository at `path` and stop borrowing them
+pub fn dissociate(path: &Path) -> Result<()> {
+    git::run_quiet(git::command(path).args(["repack", "-a", "-d", "-q"]))?;
+    let repo = Repository::open(path)?;
+    std::fs::remove_file(alternates_file(&repo))?;
+    Ok(())
+}
+
+/// A repository among `repos` with the same name as `path`, such as the original of a fork
+pub fn find_same_name<'a>(path: &Path, repos: &'a [PathBuf]) -> Option<&'a PathBuf> {
+    let name = path.file_name()?;
+    repos
+        .iter()
+        .find(|repo| *repo != path && repo.file_name() == Some(name))
+}
@@ -0,0 +1,239 @@
+#[cfg(feature = "archive")]
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+#[cfg(feature = "archive")]
+use anyhow::{ensure, Context};
+#[cfg(feature = "archive")]
+use git2::{build::CheckoutBuilder, Repository, ResetType, Status, StatusOptions};
+
+#[cfg(feature = "archive")]
+use crate::git;
+use crate::paths;
+
+/// Ref holding the staged changes as a commit while the repository is archived
+#[cfg(feature = "archive")]
+const INDEX_REF: &str = concat!("refs/", env!("CARGO_PKG_NAME"), "/index");
+
+/// A repository packed into a git bundle and a tarball of files the bundle cannot hold
+pub struct Archive {
+    bundle: PathBuf,
+    #[cfg_attr(not(feature = "archive"), allow(dead_code))]
+    files: PathBuf,
+}
+
+impl Archive {
+    /// `name` is the path of the repository relative to the root directory
+    pub fn new(archive_dir: &Path, name: &Path) -> Self {
+        let base = archive_dir.join(name).display().to_string();
+        Self {
+            bundle: PathBuf::from(format!("{base}.bundle")),
+            files: PathBuf::from(format!("{base}.tar.zst")),
+        }
+    }
+
+    pub fn exists(&self) -> bool {
+        self.bundle.exists()
+    }
+}
+
+#[cfg(feature = "archive")]
+impl Archive {
+    /// Pack the repository at `path`
+    ///
+    /// Untracked and modified files are stored in the tarball together with `.git/config` and
+    /// `.git/HEAD`. Ignored files are dropped unless `include_ignored` is set. Staged changes are
+    /// bundled as a commit under [`INDEX_REF`].
+    pub fn create(&self, path: &Path, include_ignored: bool) -> Result<()> {
+        let repo = Repository::open(path)?;
+        let workdir = repo.workdir().with_context(|| t!(BareRepository))?;
+        if let Some(parent) = self.bundle.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let staged = stage_index(&repo)?;
+        let bundled = git::run(
+            git::command(path)
+                .args(["bundle", "create"])
+                .arg(&self.bundle)
+                .arg("--all"),
+        );
+        if let Some(mut staged) = staged {
+            staged.delete()?;
+        }
+        bundled?;
+
+        let mut builder = tar::Builder::new(zstd::Encoder::new(File::create(&self.files)?, 0)?);
+        builder.append_path_with_name(repo.path().join("config"), ".git/config")?;
+        builder.append_path_with_name(repo.path().join("HEAD"), ".git/HEAD")?;
+
+        let mut opts = StatusOptions::new();
+        opts.include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .include_ignored(include_ignored)
+            .recurse_ignored_dirs(include_ignored);
+        for entry in repo.statuses(Some(&mut opts))?.iter() {
+            if entry
+                .status()
+                .intersects(Status::WT_DELETED | Status::INDEX_DELETED)
+            {
+                continue;
+            }
+            let Some(name) = entry.path() else {
+                continue;
+            };
+            let file = workdir.join(name);
+            if file.is_file() || file.is_symlink() {
+                builder.append_path_with_name(&file, name)?;
+            }
+        }
+        builder.into_inner()?.finish()?;
+        Ok(())
+    }
+
+    /// Bring the repository back to `path` and delete the archive
+    pub fn restore(&self, path: &Path) -> Result<()> {
+        ensure!(!path.exists(), t!(AlreadyExists, path.display()));
+        Repository::init(path)?;
+        git::run(
+            git::command(path)
+                .args(["fetch", "--quiet", "--update-head-ok"])
+                .arg(&self.bundle)
+                .arg("+refs/*:refs/*"),
+        )?;
+
+        tar::Archive::new(zstd::Decoder::new(File::open(&self.files)?)?).unpack(path)?;
+
+        // the tarball brings back HEAD and modified files, so only fill in the rest
+        let repo = Repository::open(path)?;
+        let head = repo.head()?.peel_to_commit()?;
+        repo.reset(head.as_object(), ResetType::Mixed, None)?;
+        repo.checkout_head(Some(CheckoutBuilder::new().recreate_missing(true)))?;
+        if let Ok(mut staged) = repo.find_reference(INDEX_REF) {
+            let tree = staged.peel_to_tree()?;
+            let mut index = repo.index()?;
+            index.read_tree(&tree)?;
+            index.write()?;
+            staged.delete()?;
+        }
+
+        std::fs::remove_file(&self.bundle)?;
+        std::fs::remove_file(&self.files)?;
+        Ok(())
+    }
+}
+
+/// Record the index as a commit on top of HEAD under [`INDEX_REF`] if it differs from HEAD
+#[cfg(feature = "archive")]
+fn stage_index(repo: &Repository) -> Result<Option<git2::Reference<'_>>> {
+    let tree = repo
+        .index()?
+        .write_tree()
+        .with_context(|| t!(UnresolvedConflicts))?;
+    let head = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+    if head.as_ref().is_some_and(|head| head.tree_id() == tree) {
+        return Ok(None);
+    }
+    let signature = git2::Signature::now(
+        env!("CARGO_PKG_NAME"),
+        concat!(env!("CARGO_PKG_NAME"), "@localhost"),
+    )?;
+    let commit = repo.commit(
+        None,
+        &signature,
+        &signature,
+        "staged changes",
+        &repo.find_tree(tree)?,
+        &head.iter().collect::<Vec<_>>(),
+    )?;
+    Ok(Some(repo.reference(INDEX_REF, commit, true, "archive")?))
+}
+
+#[cfg(not(feature = "archive"))]
+impl Archive {
+    pub fn create(&self, _path: &Path, _include_ignored: bool) -> Result<()> {
+        anyhow::bail!("build with the `archive` feature to archive repositories")
+    }
+
+    pub fn restore(&self, _path: &Path) -> Result<()> {
+        anyhow::bail!("build with the `archive` feature to restore archives")
+    }
+}
+
+/// Read `grm.archiveDir`, falling back to the data directory
+pub fn get_archive_dir(config: &git2::Config) -> Result<PathBuf> {
+    config
+        .get_path(concat!(env!("CARGO_PKG_NAME"), ".archiveDir"))
+        .or_else(|_| Ok(paths::data_dir()?.join("archive")))
+}
+
+#[cfg(all(test, feature = "archive"))]
+mod test_archive {
+    use super::*;
+
+    /// Index entries, then status and content of each file in the worktree
+    fn snapshot(path: &Path) -> Vec<String> {
+        let repo = Repository::open(path).unwrap();
+        let mut lines: Vec<String> = repo
+            .index()
+            .unwrap()
+            .iter()
+            .map(|entry| format!("{} {}", String::from_utf8_lossy(&entry.path), entry.id))
+            .collect();
+        let mut opts = StatusOptions::new();
+        opts.include_untracked(true).recurse_untracked_dirs(true);
+        for entry in repo.statuses(Some(&mut opts)).unwrap().iter() {
+            let name = entry.path().unwrap();
+            let content = std::fs::read_to_string(path.join(name)).unwrap_or_default();
+            lines.push(format!("{name} {:?} {content:?}", entry.status()));
+        }
+        lines
+    }
+
+    #[test]
+    fn round_trip() {
+        let temp = std::env::temp_dir().join(format!("grm-archive-{}", std::process::id()));
+        _ = std::fs::remove_dir_all(&temp);
+        let path = temp.join("repo");
+        let repo = Repository::init(&path).unwrap();
+        for name in ["staged", "modified", "both"] {
+            std::fs::write(path.join(name), "committed\n").unwrap();
+        }
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["*"], git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        index.write().unwrap();
+        let signature = git2::Signature::now("grm", "grm@example.test").unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])
+            .unwrap();
+
+        std::fs::write(path.join("staged"), "staged\n").unwrap();
+        std::fs::write(path.join("both"), "staged\n").unwrap();
+        std::fs::write(path.join("added"), "added\n").unwrap();
+        for name in ["staged", "both", "added"] {
+            index.add_path(Path::new(name)).unwrap();
+        }
+        index.write().unwrap();
+        std::fs::write(path.join("both"), "modified after staging\n").unwrap();
+        std::fs::write(path.join("modified"), "modified\n").unwrap();
+        std::fs::write(path.join("untracked"), "untracked\n").unwrap();
+        let before = snapshot(&path);
+
+        let archive = Archive::new(&temp.join("archive"), Path::new("repo"));
+        archive.create(&path, false).unwrap();
+        std::fs::remove_dir_all(&path).unwrap();
+        archive.restore(&path).unwrap();
+
+        assert_eq!(snapshot(&path), before);
+        assert!(!archive.exists());
+        assert!(Repository::open(&path)
+            .unwrap()
+            .find_reference(INDEX_REF)
+            .is_err());
+
+        _ = std::fs::remove_dir_all(&temp);
+    }
+}
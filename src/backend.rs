@@ -0,0 +1,117 @@
+use std::{cell::OnceCell, path::Path};
+
+use anyhow::{bail, Result};
+use url::Url;
+
+use crate::{cert::Verification, proxy::Proxy};
+
+#[cfg(feature = "backend-gix")]
+mod gitoxide;
+#[cfg(feature = "backend-git2")]
+mod libgit2;
+#[cfg(feature = "backend-git")]
+mod system;
+
+/// Options shared by every clone backend
+pub struct CloneOptions<'a> {
+    #[cfg_attr(not(feature = "backend-git2"), allow(dead_code))]
+    pub config: &'a git2::Config,
+    #[cfg_attr(not(feature = "backend-git2"), allow(dead_code))]
+    pub username: &'a str,
+    /// Fetch depth, 0 means to pull everything
+    pub depth: i32,
+    /// Date to fetch the history after, not supported by the `git2` backend
+    pub shallow_since: Option<&'a str>,
+    /// Refs whose history is not fetched, not supported by the `git2` backend
+    pub shallow_exclude: &'a [String],
+    pub proxy: Proxy,
+    /// Private key to use instead of the SSH agent
+    pub ssh_key: Option<&'a Path>,
+    /// Name of the remote to clone from
+    pub remote_name: &'a str,
+    /// Populate the working tree after cloning
+    pub checkout: bool,
+    /// Token sent as the password of HTTPS requests
+    pub token: &'a Token<'a>,
+    pub verification: Verification,
+    /// Repository to borrow objects from, only supported by the `git` backend
+    pub reference: Option<&'a Path>,
+    /// Copy the borrowed objects instead of keeping to borrow them
+    pub dissociate: bool,
+    /// Headers printed by `grm.<host>.headerCommand`, sent in addition to the ones of
+    /// `http.<url>.extraHeader`
+    pub headers: Vec<String>,
+}
+
+/// A token looked up on first use, so that the vault is only unlocked for clones that need it
+pub struct Token<'a> {
+    lookup: &'a dyn Fn() -> Option<String>,
+    token: OnceCell<Option<String>>,
+}
+
+impl<'a> Token<'a> {
+    pub fn new(lookup: &'a dyn Fn() -> Option<String>) -> Self {
+        Self {
+            lookup,
+            token: OnceCell::new(),
+        }
+    }
+
+    pub fn get(&self) -> Option<&str> {
+        self.token.get_or_init(self.lookup).as_deref()
+    }
+
+    /// Whether a token was found when it was asked for
+    pub fn was_used(&self) -> bool {
+        self.token.get().is_some_and(Option::is_some)
+    }
+}
+
+pub trait CloneBackend {
+    fn clone_repo(&self, url: &Url, path: &Path, opts: &CloneOptions) -> Result<()>;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Backend {
+    /// libgit2 via the git2 crate
+    Git2,
+    /// The `git` command found in PATH
+    Git,
+    /// gitoxide
+    Gix,
+}
+
+impl Backend {
+    /// Read `grm.backend`, falling back to libgit2, or to `git` in builds without libgit2
+    pub fn from_config(config: &git2::Config) -> Result<Self> {
+        let Ok(name) = config.get_string(concat!(env!("CARGO_PKG_NAME"), ".backend")) else {
+            return Ok(
+                if cfg!(feature = "backend-git2") || !cfg!(feature = "backend-git") {
+                    Self::Git2
+                } else {
+                    Self::Git
+                },
+            );
+        };
+        <Self as clap::ValueEnum>::from_str(&name, true)
+            .map_err(|_| anyhow::anyhow!("unknown backend `{name}`"))
+    }
+
+    pub fn build(self) -> Result<Box<dyn CloneBackend>> {
+        match self {
+            #[cfg(feature = "backend-git2")]
+            Self::Git2 => Ok(Box::new(libgit2::Libgit2)),
+            #[cfg(feature = "backend-git")]
+            Self::Git => Ok(Box::new(system::SystemGit)),
+            #[cfg(feature = "backend-gix")]
+            Self::Gix => Ok(Box::new(gitoxide::Gitoxide)),
+            #[allow(unreachable_patterns)]
+            backend => bail!(
+                "the `{}` backend is not compiled into this build",
+                clap::ValueEnum::to_possible_value(&backend)
+                    .unwrap()
+                    .get_name()
+            ),
+        }
+    }
+}
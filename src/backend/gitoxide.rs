@@ -0,0 +1,110 @@
+use std::{num::NonZeroU32, path::Path, sync::atomic::AtomicBool};
+
+use anyhow::Result;
+use url::Url;
+
+use super::{CloneBackend, CloneOptions};
+use crate::{git, proxy::Proxy};
+
+pub struct Gitoxide;
+
+impl CloneBackend for Gitoxide {
+    fn clone_repo(&self, url: &Url, path: &Path, opts: &CloneOptions) -> Result<()> {
+        anyhow::ensure!(
+            opts.reference.is_none(),
+            "the gix backend cannot borrow objects from other repositories"
+        );
+        let interrupt = AtomicBool::new(false);
+
+        let proxy = match &opts.proxy {
+            Proxy::Url(proxy) => proxy.as_str(),
+            Proxy::Direct => "",
+        };
+        let mut overrides = vec![format!("http.proxy={proxy}")];
+        if let Some(key) = opts.ssh_key {
+            overrides.push(format!("core.sshCommand={}", git::ssh_command(key)));
+        }
+        let verification = &opts.verification;
+        if let Some(ca_bundle) = &verification.ca_bundle {
+            overrides.push(format!("http.sslCAInfo={}", ca_bundle.display()));
+        }
+        if verification.insecure {
+            overrides.push("http.sslVerify=false".to_string());
+        }
+        for header in &opts.headers {
+            overrides.push(format!("http.extraHeader={header}"));
+        }
+        if verification.host_key.is_some() {
+            warn!("host keys are checked against known_hosts by ssh instead of `hostKey`");
+        }
+        let mut prepare = gix::prepare_clone(url.as_str(), path)?
+            .with_in_memory_config_overrides(overrides)
+            .with_remote_name(opts.remote_name)?;
+        if let Some(shallow) = shallow(opts)? {
+            prepare = prepare.with_shallow(shallow);
+        }
+        if let Some(token) = opts.token.get() {
+            let token = token.to_string();
+            prepare = prepare.configure_connection(move |connection| {
+                let token = token.clone();
+                connection.set_credentials(move |action| match action {
+                    gix::credentials::helper::Action::Get(context) => {
+                        Ok(Some(gix::credentials::protocol::Outcome {
+                            identity: gix::sec::identity::Account {
+                                username: "x-access-token".into(),
+                                password: token.clone(),
+                                oauth_refresh_token: None,
+                            },
+                            next: context.into(),
+                        }))
+                    }
+                    _ => Ok(None),
+                });
+                Ok(())
+            });
+        }
+        let (mut checkout, _) = prepare.fetch_then_checkout(gix::progress::Discard, &interrupt)?;
+        if opts.checkout {
+            checkout.main_worktree(gix::progress::Discard, &interrupt)?;
+        } else {
+            checkout.persist();
+        }
+        Ok(())
+    }
+}
+
+/// The shallow boundary of `--depth`, `--shallow-since` and `--shallow-exclude`
+fn shallow(opts: &CloneOptions) -> Result<Option<gix::remote::fetch::Shallow>> {
+    use gix::remote::fetch::Shallow;
+
+    let since = opts
+        .shallow_since
+        .map(|date| {
+            gix::date::parse(date, Some(gix::date::Zoned::now()))
+                .map_err(|err| anyhow::anyhow!("invalid date `{date}`: {err}"))
+        })
+        .transpose()?;
+    let remote_refs = opts
+        .shallow_exclude
+        .iter()
+        .map(|name| {
+            gix::refs::PartialName::try_from(name.as_str())
+                .map_err(|err| anyhow::anyhow!("invalid ref `{name}`: {err}"))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let depth = u32::try_from(opts.depth).ok().and_then(NonZeroU32::new);
+    anyhow::ensure!(
+        depth.is_none() || (since.is_none() && remote_refs.is_empty()),
+        "the gix backend cannot combine --depth with --shallow-since or --shallow-exclude"
+    );
+    Ok(if !remote_refs.is_empty() {
+        Some(Shallow::Exclude {
+            remote_refs,
+            since_cutoff: since,
+        })
+    } else if let Some(cutoff) = since {
+        Some(Shallow::Since { cutoff })
+    } else {
+        depth.map(Shallow::DepthAtRemote)
+    })
+}
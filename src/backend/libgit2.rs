@@ -0,0 +1,136 @@
+use std::path::Path;
+
+use anyhow::Result;
+use url::Url;
+
+use super::{CloneBackend, CloneOptions};
+use crate::{
+    cert::{self, fingerprint, HostKeyStatus},
+    headers,
+    proxy::Proxy,
+};
+
+pub struct Libgit2;
+
+impl CloneBackend for Libgit2 {
+    fn clone_repo(&self, url: &Url, path: &Path, opts: &CloneOptions) -> Result<()> {
+        anyhow::ensure!(
+            opts.reference.is_none(),
+            "the git2 backend cannot borrow objects from other repositories"
+        );
+        if opts.verification.ca_bundle.is_some() {
+            // libgit2 only takes CA certificates process-wide, where they would stay trusted for
+            // every later clone, so let git scope them to this one
+            #[cfg(feature = "backend-git")]
+            return super::system::SystemGit.clone_repo(url, path, opts);
+            #[cfg(not(feature = "backend-git"))]
+            anyhow::bail!("the git2 backend cannot use `grm.<host>.caBundle`; use the git backend");
+        }
+        let mut callbacks = git2::RemoteCallbacks::new();
+        callbacks.credentials(|url, username_from_url, allowed_types| {
+            use git2::Cred;
+            // the token is only looked up once the server asks for a password
+            let token = allowed_types
+                .is_user_pass_plaintext()
+                .then(|| opts.token.get())
+                .flatten();
+            if let Some(token) = token {
+                Cred::userpass_plaintext(username_from_url.unwrap_or("x-access-token"), token)
+            } else if allowed_types.is_default() {
+                Cred::default()
+            } else if allowed_types.is_username() {
+                Cred::username(opts.username)
+            } else if let (true, Some(key)) = (allowed_types.is_ssh_key(), opts.ssh_key) {
+                Cred::ssh_key(username_from_url.unwrap_or(opts.username), None, key, None)
+            } else if allowed_types.is_ssh_key() {
+                Cred::ssh_key_from_agent(opts.username)
+            } else {
+                Cred::credential_helper(opts.config, url, Some(opts.username))
+            }
+        });
+
+        let verification = &opts.verification;
+        callbacks.certificate_check(|cert, host| {
+            use git2::CertificateCheckStatus;
+            match (cert.as_hostkey(), &verification.host_key) {
+                (Some(hostkey), Some(expected)) => {
+                    let actual = hostkey.hash_sha256().map(|hash| fingerprint(hash));
+                    if actual.as_ref() == Some(expected) {
+                        Ok(CertificateCheckStatus::CertificateOk)
+                    } else {
+                        Err(git2::Error::from_str(&format!(
+                            "the host key of {host} is {}, but `{expected}` is expected",
+                            actual.as_deref().unwrap_or("unknown")
+                        )))
+                    }
+                }
+                (Some(hostkey), None) => check_host_key(hostkey, host, url.port()),
+                (None, _) if verification.insecure => Ok(CertificateCheckStatus::CertificateOk),
+                _ => Ok(CertificateCheckStatus::CertificatePassthrough),
+            }
+        });
+
+        let mut fetch_opts = git2::FetchOptions::new();
+        fetch_opts.remote_callbacks(callbacks);
+        fetch_opts.depth(opts.depth);
+        fetch_opts.proxy_options({
+            let mut proxy_opts = git2::ProxyOptions::new();
+            if let Proxy::Url(proxy) = &opts.proxy {
+                proxy_opts.url(proxy);
+            }
+            proxy_opts
+        });
+        // unlike git, libgit2 does not read `http.extraHeader`
+        let mut headers = headers::configured(opts.config, url)?;
+        headers.extend(opts.headers.iter().cloned());
+        let headers: Vec<&str> = headers.iter().map(String::as_str).collect();
+        fetch_opts.custom_headers(&headers);
+
+        let mut builder = git2::build::RepoBuilder::new();
+        builder.fetch_options(fetch_opts);
+        if !opts.checkout {
+            builder.with_checkout({
+                let mut checkout = git2::build::CheckoutBuilder::new();
+                checkout.dry_run();
+                checkout
+            });
+        }
+        builder.remote_create(|repo, _name, url| repo.remote(opts.remote_name, url));
+
+        builder.clone(url.as_str(), path)?;
+        Ok(())
+    }
+}
+
+/// Trust known host keys, and ask about ones seen for the first time
+fn check_host_key(
+    hostkey: &git2::cert::CertHostkey,
+    host: &str,
+    port: Option<u16>,
+) -> Result<git2::CertificateCheckStatus, git2::Error> {
+    use git2::CertificateCheckStatus;
+    let (Some(key), Some(key_type), Some(hash)) = (
+        hostkey.hostkey(),
+        hostkey.hostkey_type(),
+        hostkey.hash_sha256(),
+    ) else {
+        return Ok(CertificateCheckStatus::CertificatePassthrough);
+    };
+    let fingerprint = fingerprint(hash);
+    match cert::lookup_host_key(host, port, key_type.name(), key) {
+        HostKeyStatus::Known => Ok(CertificateCheckStatus::CertificateOk),
+        HostKeyStatus::Changed => Err(git2::Error::from_str(&format!(
+            "the host key of {host} has CHANGED to {fingerprint}; someone may be intercepting the connection"
+        ))),
+        HostKeyStatus::Unknown => {
+            match cert::confirm_host_key(host, port, key_type.name(), key, &fingerprint) {
+                Ok(Some(true)) => Ok(CertificateCheckStatus::CertificateOk),
+                Ok(Some(false)) => Err(git2::Error::from_str(&format!(
+                    "the host key of {host} is not trusted"
+                ))),
+                Ok(None) => Ok(CertificateCheckStatus::CertificatePassthrough),
+                Err(err) => Err(git2::Error::from_str(&format!("{err:#}"))),
+            }
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use std::{path::Path, process::Command};
+
+use anyhow::{ensure, Context, Result};
+use url::Url;
+
+use super::{CloneBackend, CloneOptions};
+use crate::{git, headers, proxy::Proxy};
+
+pub struct SystemGit;
+
+impl CloneBackend for SystemGit {
+    fn clone_repo(&self, url: &Url, path: &Path, opts: &CloneOptions) -> Result<()> {
+        let mut command = Command::new("git");
+        match &opts.proxy {
+            Proxy::Url(proxy) => command.arg("-c").arg(format!("http.proxy={proxy}")),
+            Proxy::Direct => command.env("no_proxy", "*").env("NO_PROXY", "*"),
+        };
+        if let Some(key) = opts.ssh_key {
+            git::use_ssh_key(&mut command, key);
+        }
+        let verification = &opts.verification;
+        if let Some(ca_bundle) = &verification.ca_bundle {
+            command
+                .arg("-c")
+                .arg(format!("http.sslCAInfo={}", ca_bundle.display()));
+        }
+        if verification.insecure {
+            command.arg("-c").arg("http.sslVerify=false");
+        }
+        if verification.host_key.is_some() {
+            warn!("host keys are checked against known_hosts by ssh instead of `hostKey`");
+        }
+        if let Some(token) = opts.token.get() {
+            git::use_token(&mut command, token, None);
+        }
+        headers::set_env(&mut command, url, &opts.headers);
+        command.arg("clone");
+        command.arg(format!("--origin={}", opts.remote_name));
+        if !opts.checkout {
+            command.arg("--no-checkout");
+        }
+        if opts.depth > 0 {
+            command.arg(format!("--depth={}", opts.depth));
+        }
+        if let Some(date) = opts.shallow_since {
+            command.arg(format!("--shallow-since={date}"));
+        }
+        for reference in opts.shallow_exclude {
+            command.arg(format!("--shallow-exclude={reference}"));
+        }
+        if let Some(reference) = opts.reference {
+            command.arg("--reference").arg(reference);
+            if opts.dissociate {
+                command.arg("--dissociate");
+            }
+        }
+        command.arg("--").arg(url.as_str()).arg(path);
+
+        let status = command.status().context("failed to run `git`")?;
+        ensure!(status.success(), "`git clone` exited with {status}");
+        Ok(())
+    }
+}
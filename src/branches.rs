@@ -0,0 +1,86 @@
+//! Local branches merged into the default branch, cleaned up by `grm branches --merged --delete`
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use git2::{BranchType, Oid, Repository};
+
+use crate::{fork, glob};
+
+/// Branches never deleted unless `grm.protectedBranch` is set
+const DEFAULT_PROTECTED: &[&str] = &["main", "master", "develop", "trunk", "release/*"];
+
+/// Glob patterns of `grm.protectedBranch` (can be repeated), or the default ones
+pub fn get_protected(config: &git2::Config) -> Result<Vec<String>> {
+    let mut patterns = Vec::new();
+    if let Ok(entries) = config.multivar(concat!(env!("CARGO_PKG_NAME"), ".protectedBranch"), None)
+    {
+        entries.for_each(|entry| {
+            if let Some(value) = entry.value() {
+                patterns.push(value.to_string());
+            }
+        })?;
+    }
+    if patterns.is_empty() {
+        patterns.extend(DEFAULT_PROTECTED.iter().map(|p| p.to_string()));
+    }
+    Ok(patterns)
+}
+
+pub struct Branch {
+    pub name: String,
+    pub merged: bool,
+    /// Checked out, the default branch, or matching a protected pattern
+    pub protected: bool,
+}
+
+/// Local branches of the repository at `path`, compared with the default branch of `remote`
+pub fn list(path: &Path, remote: &str, protected: &[String]) -> Result<Vec<Branch>> {
+    let repo = Repository::open(path)?;
+    let default = fork::default_branch(&repo, remote)
+        .or_else(|| {
+            ["main", "master"]
+                .into_iter()
+                .find(|name| repo.find_branch(name, BranchType::Local).is_ok())
+                .map(String::from)
+        })
+        .context("cannot find the default branch")?;
+    let target = repo
+        .find_reference(&format!("refs/remotes/{remote}/{default}"))
+        .or_else(|_| repo.find_reference(&format!("refs/heads/{default}")))?
+        .peel_to_commit()?
+        .id();
+
+    let mut branches = Vec::new();
+    for branch in repo.branches(Some(BranchType::Local))? {
+        let (branch, _) = branch?;
+        let Some(name) = branch.name()?.map(String::from) else {
+            continue;
+        };
+        let Some(tip) = branch.get().target() else {
+            continue;
+        };
+        branches.push(Branch {
+            merged: is_merged(&repo, tip, target)?,
+            protected: branch.is_head()
+                || name == default
+                || protected
+                    .iter()
+                    .any(|pattern| glob::matches(pattern, &name)),
+            name,
+        });
+    }
+    Ok(branches)
+}
+
+fn is_merged(repo: &Repository, tip: Oid, target: Oid) -> Result<bool> {
+    Ok(tip == target || repo.graph_descendant_of(target, tip)?)
+}
+
+/// Delete the local branch `name`
+pub fn delete(path: &Path, name: &str) -> Result<()> {
+    Repository::open(path)?
+        .find_branch(name, BranchType::Local)?
+        .delete()?;
+    Ok(())
+}
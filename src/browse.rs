@@ -0,0 +1,113 @@
+//! Web pages of repositories on their forges, opened by `grm open`
+
+use std::process::{Command, Stdio};
+
+use anyhow::{ensure, Context, Result};
+use url::Url;
+
+use crate::forge::ForgeKind;
+
+/// The web page of the repository cloned from `origin`, which is an HTTPS or SSH URL
+pub fn web_url(origin: &Url) -> Result<Url> {
+    let host = origin
+        .host_str()
+        .with_context(|| format!("cannot find a host name from `{origin}`"))?;
+    let path = origin.path().trim_end_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    Ok(Url::parse(&format!("https://{host}{path}"))?)
+}
+
+pub enum Rev {
+    Branch(String),
+    /// A full commit ID, for links that keep pointing to the same lines
+    Commit(String),
+}
+
+impl Rev {
+    fn name(&self) -> &str {
+        match self {
+            Self::Branch(name) | Self::Commit(name) => name,
+        }
+    }
+}
+
+/// A link to `file` at `rev` in the repository at `web`, optionally to `line`
+pub fn file_link(kind: ForgeKind, web: &Url, rev: &Rev, file: &str, line: Option<u32>) -> String {
+    let web = web.as_str().trim_end_matches('/');
+    let file = file.trim_start_matches('/');
+    let name = rev.name();
+    let mut link = match kind {
+        ForgeKind::Github => format!("{web}/blob/{name}/{file}"),
+        ForgeKind::Gitlab => format!("{web}/-/blob/{name}/{file}"),
+        ForgeKind::Gitea => match rev {
+            Rev::Branch(_) => format!("{web}/src/branch/{name}/{file}"),
+            Rev::Commit(_) => format!("{web}/src/commit/{name}/{file}"),
+        },
+        ForgeKind::Sourcehut => format!("{web}/tree/{name}/item/{file}"),
+    };
+    if let Some(line) = line {
+        link.push_str(&format!("#L{line}"));
+    }
+    link
+}
+
+/// A link to the commit `id` in the repository at `web`
+pub fn commit_link(kind: ForgeKind, web: &Url, id: &str) -> String {
+    let web = web.as_str().trim_end_matches('/');
+    match kind {
+        ForgeKind::Gitlab => format!("{web}/-/commit/{id}"),
+        ForgeKind::Github | ForgeKind::Gitea | ForgeKind::Sourcehut => {
+            format!("{web}/commit/{id}")
+        }
+    }
+}
+
+/// Open `url` in the default browser
+pub fn open(url: &str) -> Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else {
+        Command::new("xdg-open")
+    };
+    let status = command
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .status()
+        .context("failed to run the browser opener")?;
+    ensure!(status.success(), "failed to open `{url}`: {status}");
+    Ok(())
+}
+
+#[cfg(test)]
+mod test_file_link {
+    use super::*;
+
+    #[test]
+    fn forges() {
+        let web = Url::parse("https://example.com/me/repo").unwrap();
+        let main = Rev::Branch("main".into());
+        let link = |kind| file_link(kind, &web, &main, "src/lib.rs", Some(12));
+        assert_eq!(
+            link(ForgeKind::Github),
+            "https://example.com/me/repo/blob/main/src/lib.rs#L12"
+        );
+        assert_eq!(
+            link(ForgeKind::Gitlab),
+            "https://example.com/me/repo/-/blob/main/src/lib.rs#L12"
+        );
+        assert_eq!(
+            link(ForgeKind::Gitea),
+            "https://example.com/me/repo/src/branch/main/src/lib.rs#L12"
+        );
+        let commit = Rev::Commit("abc".into());
+        assert_eq!(
+            file_link(ForgeKind::Sourcehut, &web, &commit, "README", None),
+            "https://example.com/me/repo/tree/abc/item/README"
+        );
+    }
+}
@@ -0,0 +1,34 @@
+//! Git bundles of `grm bundle`, to carry repositories to machines without network access
+
+use std::{path::Path, process::Command};
+
+use anyhow::Result;
+use url::Url;
+
+use crate::git;
+
+/// Write every ref of the repository at `repo` into `file`
+pub fn create(repo: &Path, file: &Path) -> Result<()> {
+    let file = std::path::absolute(file)?;
+    git::run_quiet(
+        git::command(repo)
+            .args(["bundle", "create"])
+            .arg(file)
+            .arg("--all"),
+    )
+}
+
+/// Clone `file` into `path`, then point the remote to `origin` for later fetches
+pub fn apply(file: &Path, path: &Path, remote_name: &str, origin: &Url) -> Result<()> {
+    git::run_quiet(
+        Command::new("git")
+            .args(["clone", "--origin", remote_name])
+            .arg(file)
+            .arg(path),
+    )?;
+    git::run_quiet(
+        git::command(path)
+            .args(["remote", "set-url", remote_name])
+            .arg(origin.as_str()),
+    )
+}
@@ -0,0 +1,237 @@
+use std::{
+    io::{BufRead, Write},
+    path::PathBuf,
+};
+
+use anyhow::Result;
+use url::Url;
+
+use crate::{paths, setup};
+
+/// How to verify the server of a clone
+#[derive(Clone, Debug, Default)]
+pub struct Verification {
+    /// `grm.<host>.caBundle`, certificates to verify the server with
+    pub ca_bundle: Option<PathBuf>,
+    /// Accept any TLS certificate
+    pub insecure: bool,
+    /// `grm.<host>.hostKey`, the expected SSH host key fingerprint like `SHA256:...`
+    pub host_key: Option<String>,
+}
+
+pub fn resolve(config: &git2::Config, url: &Url, insecure: bool) -> Verification {
+    let Some(host) = url.host_str() else {
+        return Verification {
+            insecure,
+            ..Default::default()
+        };
+    };
+    let key = |key: &str| format!("{}.{host}.{key}", env!("CARGO_PKG_NAME"));
+    if insecure {
+        warn!("TLS certificates of {host} are NOT verified; the connection may be intercepted");
+    }
+    Verification {
+        ca_bundle: config.get_path(&key("caBundle")).ok(),
+        insecure,
+        host_key: config.get_string(&key("hostKey")).ok(),
+    }
+}
+
+/// Format a SHA-256 hash of a host key like `ssh-keygen -l` does
+#[cfg_attr(not(feature = "backend-git2"), allow(dead_code))]
+pub fn fingerprint(hash: &[u8]) -> String {
+    format!("SHA256:{}", base64(hash, false))
+}
+
+fn base64(bytes: &[u8], pad: bool) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::new();
+    for chunk in bytes.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..=chunk.len() {
+            encoded.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+        }
+        if pad {
+            encoded.push_str(&"=".repeat(3 - chunk.len()));
+        }
+    }
+    encoded
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum HostKeyStatus {
+    Known,
+    /// A different key of the same type is recorded for the host
+    Changed,
+    Unknown,
+}
+
+/// Host keys accepted at the prompt of grm, in the format of `~/.ssh/known_hosts`
+pub fn get_known_hosts_file() -> Result<PathBuf> {
+    Ok(paths::config_dir()?.join("known_hosts"))
+}
+
+/// The name of a host in known hosts, `[host]:port` unless it is served on the default port
+fn known_hosts_name(host: &str, port: Option<u16>) -> String {
+    match port {
+        Some(port) if port != 22 => format!("[{host}]:{port}"),
+        _ => host.to_string(),
+    }
+}
+
+/// Look up a host key in the known hosts of grm and `~/.ssh/known_hosts`
+///
+/// Hashed host names cannot be matched, so a warning is shown when the host is not found and
+/// such names are present.
+#[cfg_attr(not(feature = "backend-git2"), allow(dead_code))]
+pub fn lookup_host_key(host: &str, port: Option<u16>, key_type: &str, key: &[u8]) -> HostKeyStatus {
+    let name = known_hosts_name(host, port);
+    let files = [
+        get_known_hosts_file().ok(),
+        dirs::home_dir().map(|p| p.join(".ssh").join("known_hosts")),
+    ];
+    let contents: Vec<_> = files
+        .into_iter()
+        .flatten()
+        .filter_map(|file| std::fs::read_to_string(file).ok())
+        .collect();
+    let lines = || contents.iter().flat_map(|content| content.lines());
+    let status = match_host_key(lines(), &name, key_type, key);
+    if status == HostKeyStatus::Unknown && lines().any(|line| line.starts_with("|1|")) {
+        warn!(
+            "known_hosts has hashed host names, which cannot be matched; {name} is treated as \
+             unknown even if it is one of them"
+        );
+    }
+    status
+}
+
+fn match_host_key<'a>(
+    lines: impl Iterator<Item = &'a str>,
+    host: &str,
+    key_type: &str,
+    key: &[u8],
+) -> HostKeyStatus {
+    let key = base64(key, true);
+    let mut status = HostKeyStatus::Unknown;
+    for line in lines {
+        let mut fields = line.split_whitespace();
+        let (Some(hosts), Some(ty), Some(k)) = (fields.next(), fields.next(), fields.next()) else {
+            continue;
+        };
+        if hosts.starts_with(['#', '@']) || ty != key_type || !hosts.split(',').any(|h| h == host) {
+            continue;
+        }
+        if k == key {
+            return HostKeyStatus::Known;
+        }
+        status = HostKeyStatus::Changed;
+    }
+    status
+}
+
+/// Ask whether to trust the key of a host seen for the first time, and remember it if so
+///
+/// Returns `None` without asking in a non-interactive session.
+#[cfg_attr(not(feature = "backend-git2"), allow(dead_code))]
+pub fn confirm_host_key(
+    host: &str,
+    port: Option<u16>,
+    key_type: &str,
+    key: &[u8],
+    fingerprint: &str,
+) -> Result<Option<bool>> {
+    if !setup::is_interactive() {
+        return Ok(None);
+    }
+    let host = known_hosts_name(host, port);
+    eprintln!("The authenticity of host '{host}' can't be established.");
+    eprintln!("{key_type} key fingerprint is {fingerprint}.");
+    eprint!("Are you sure you want to continue connecting (yes/no)? ");
+    std::io::stderr().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer)?;
+    if !matches!(answer.trim(), "yes" | "y") {
+        return Ok(Some(false));
+    }
+    let file = get_known_hosts_file()?;
+    if let Some(parent) = file.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut known_hosts = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&file)?;
+    writeln!(known_hosts, "{host} {key_type} {}", base64(key, true))?;
+    info!("added {host} to {}", file.display());
+    Ok(Some(true))
+}
+
+#[cfg(test)]
+mod test_fingerprint {
+    use super::*;
+
+    #[test]
+    fn unpadded_base64() {
+        assert_eq!(fingerprint(b"foobar"), "SHA256:Zm9vYmFy");
+        assert_eq!(fingerprint(b"fo"), "SHA256:Zm8");
+        assert_eq!(fingerprint(b"f"), "SHA256:Zg");
+        assert_eq!(base64(b"f", true), "Zg==");
+    }
+}
+
+#[cfg(test)]
+mod test_match_host_key {
+    use super::*;
+
+    #[test]
+    fn statuses() {
+        let lines = [
+            "# comment",
+            "example.com,10.0.0.1 ssh-ed25519 Zm9v",
+            "other.com ssh-rsa Zm9v",
+            "[example.com]:2222 ssh-ed25519 YmFy",
+        ];
+        let status = |host, key_type, key| match_host_key(lines.into_iter(), host, key_type, key);
+        assert_eq!(
+            status("10.0.0.1", "ssh-ed25519", b"foo"),
+            HostKeyStatus::Known
+        );
+        assert_eq!(
+            status("example.com", "ssh-ed25519", b"bar"),
+            HostKeyStatus::Changed
+        );
+        assert_eq!(
+            status("example.com", "ssh-rsa", b"foo"),
+            HostKeyStatus::Unknown
+        );
+        assert_eq!(
+            status("new.com", "ssh-ed25519", b"foo"),
+            HostKeyStatus::Unknown
+        );
+        assert_eq!(
+            status("[example.com]:2222", "ssh-ed25519", b"bar"),
+            HostKeyStatus::Known
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_known_hosts_name {
+    use super::*;
+
+    #[test]
+    fn ports() {
+        assert_eq!(known_hosts_name("example.com", None), "example.com");
+        assert_eq!(known_hosts_name("example.com", Some(22)), "example.com");
+        assert_eq!(
+            known_hosts_name("example.com", Some(2222)),
+            "[example.com]:2222"
+        );
+    }
+}
@@ -0,0 +1,51 @@
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+use anyhow::{ensure, Result};
+
+/// Clipboard commands tried in order; the ones of X11 and Wayland keep serving the text after grm
+/// exits, unlike a clipboard owned by grm itself
+const COMMANDS: &[&[&str]] = &[
+    &["pbcopy"],
+    &["wl-copy"],
+    &["xclip", "-selection", "clipboard"],
+    &["xsel", "--clipboard", "--input"],
+    // also found in WSL
+    &["clip.exe"],
+];
+
+/// Put `text` onto the system clipboard with a clipboard command, or natively with the
+/// `clipboard` feature if there is none
+pub fn copy(text: &str) -> Result<()> {
+    for command in COMMANDS {
+        let child = Command::new(command[0])
+            .args(&command[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        let mut child = match child {
+            Ok(child) => child,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err.into()),
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes())?;
+        }
+        let status = child.wait()?;
+        ensure!(status.success(), "`{}` exited with {status}", command[0]);
+        return Ok(());
+    }
+
+    #[cfg(feature = "clipboard")]
+    {
+        arboard::Clipboard::new()?.set_text(text)?;
+        Ok(())
+    }
+    #[cfg(not(feature = "clipboard"))]
+    anyhow::bail!(
+        "install a clipboard command such as xclip, or build with the `clipboard` feature"
+    )
+}
@@ -0,0 +1,82 @@
+//! Candidates printed by `grm completion-data` for pickers such as fzf, wofi and Alfred
+
+use std::{
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::{index, template};
+
+#[derive(Serialize)]
+pub struct Candidate {
+    pub name: String,
+    pub path: PathBuf,
+    pub host: String,
+    /// Seconds since the Unix epoch the repository was last committed to or checked out
+    pub last_used: Option<u64>,
+}
+
+impl Candidate {
+    pub fn new(name: String, path: PathBuf) -> Self {
+        Self {
+            host: template::split_repo(&name).0.to_string(),
+            last_used: last_used(&path),
+            name,
+            path,
+        }
+    }
+
+    /// One line of tab-separated `name`, `path`, `host` and `last_used`, empty if unknown
+    pub fn to_line(&self) -> String {
+        format!(
+            "{}\t{}\t{}\t{}",
+            self.name,
+            self.path.display(),
+            self.host,
+            self.last_used.map(|t| t.to_string()).unwrap_or_default()
+        )
+    }
+}
+
+/// Repositories under `root` from the index, which is built first if there is none yet
+///
+/// Repositories removed since the index was written are skipped.
+pub fn repos(root: &Path, max_depth: Option<usize>) -> Result<Vec<PathBuf>> {
+    let mut repos = match index::load(root)? {
+        Some(repos) => repos,
+        None => {
+            index::refresh(root, max_depth)?;
+            index::load(root)?.unwrap_or_default()
+        }
+    };
+    repos.retain(|path| path.exists());
+    Ok(repos)
+}
+
+/// Sort candidates by the most recently used first, then by name
+pub fn sort(candidates: &mut [Candidate]) {
+    candidates.sort_by(|a, b| {
+        b.last_used
+            .cmp(&a.last_used)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+}
+
+/// The latest modification of the index or the reflog of `HEAD`, without opening the repository
+fn last_used(path: &Path) -> Option<u64> {
+    let dot_git = path.join(".git");
+    let git_dir = match std::fs::read_to_string(&dot_git) {
+        Ok(content) => path.join(content.strip_prefix("gitdir:")?.trim()),
+        Err(_) => dot_git,
+    };
+    [git_dir.join("index"), git_dir.join("logs").join("HEAD")]
+        .iter()
+        .filter_map(|file| file.metadata().and_then(|m| m.modified()).ok())
+        .max()?
+        .duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|d| d.as_secs())
+}
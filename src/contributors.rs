@@ -0,0 +1,80 @@
+//! Recent authorship of repositories, telling projects contributed to from references
+
+use std::{collections::HashMap, path::Path};
+
+use anyhow::Result;
+use git2::{Repository, Sort};
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct Author {
+    pub name: String,
+    pub email: String,
+    pub commits: usize,
+}
+
+#[derive(Serialize)]
+pub struct Summary {
+    pub commits: usize,
+    /// Commits by `user.email` of the repository
+    pub yours: usize,
+    /// Sorted by the number of commits in descending order
+    pub authors: Vec<Author>,
+}
+
+impl Summary {
+    /// `active` if you committed recently, `reference` if only others did and `idle` otherwise
+    pub fn role(&self) -> &'static str {
+        if self.yours > 0 {
+            "active"
+        } else if self.commits > 0 {
+            "reference"
+        } else {
+            "idle"
+        }
+    }
+}
+
+/// Count commits reachable from `HEAD` authored after `since`, in seconds since the Unix epoch
+pub fn summarize(path: &Path, since: i64) -> Result<Summary> {
+    let repo = Repository::open(path)?;
+    let me = repo
+        .config()?
+        .get_string("user.email")
+        .ok()
+        .map(|email| email.to_lowercase());
+    let mut authors: HashMap<String, Author> = HashMap::new();
+    let mut commits = 0;
+    if repo.head().is_ok() {
+        let mut walk = repo.revwalk()?;
+        walk.set_sorting(Sort::TIME)?;
+        walk.push_head()?;
+        for oid in walk {
+            let commit = repo.find_commit(oid?)?;
+            if commit.time().seconds() < since {
+                break;
+            }
+            commits += 1;
+            let author = commit.author();
+            let email = author.email().unwrap_or_default().to_lowercase();
+            authors
+                .entry(email.clone())
+                .or_insert_with(|| Author {
+                    name: author.name().unwrap_or_default().to_string(),
+                    email,
+                    commits: 0,
+                })
+                .commits += 1;
+        }
+    }
+    let yours = me
+        .and_then(|me| authors.get(&me))
+        .map_or(0, |author| author.commits);
+    let mut authors: Vec<_> = authors.into_values().collect();
+    authors.sort_by(|a, b| b.commits.cmp(&a.commits).then_with(|| a.name.cmp(&b.name)));
+    Ok(Summary {
+        commits,
+        yours,
+        authors,
+    })
+}
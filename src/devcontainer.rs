@@ -0,0 +1,61 @@
+//! Dev containers of repositories, started with the devcontainer CLI or Docker Compose
+
+use std::{path::Path, process::Command};
+
+use anyhow::{ensure, Context, Result};
+
+const CONFIGS: &[&str] = &[".devcontainer/devcontainer.json", ".devcontainer.json"];
+
+/// Compose files used when the devcontainer CLI is not installed
+const COMPOSE_FILES: &[&str] = &[
+    ".devcontainer/compose.yaml",
+    ".devcontainer/compose.yml",
+    ".devcontainer/docker-compose.yaml",
+    ".devcontainer/docker-compose.yml",
+];
+
+/// Whether the repository at `path` defines a dev container
+pub fn exists(path: &Path) -> bool {
+    CONFIGS.iter().any(|config| path.join(config).is_file())
+}
+
+/// Start the dev container of the repository at `path`
+///
+/// Output of the tools goes to stderr, so that stdout is left for grm.
+pub fn up(path: &Path) -> Result<()> {
+    ensure!(
+        exists(path),
+        "`{}` has no dev container configuration",
+        path.display()
+    );
+    let status = Command::new("devcontainer")
+        .args(["up", "--workspace-folder"])
+        .arg(path)
+        .stdout(std::io::stderr())
+        .status();
+    match status {
+        Ok(status) => {
+            ensure!(status.success(), "`devcontainer` exited with {status}");
+            return Ok(());
+        }
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+        Err(err) => return Err(err).context("failed to run `devcontainer`"),
+    }
+
+    let compose = COMPOSE_FILES
+        .iter()
+        .map(|file| path.join(file))
+        .find(|file| file.is_file())
+        .context("install the devcontainer CLI to start the dev container: npm install -g @devcontainers/cli")?;
+    info!("the devcontainer CLI is not found, starting with docker compose");
+    let status = Command::new("docker")
+        .args(["compose", "-f"])
+        .arg(&compose)
+        .args(["up", "--detach"])
+        .current_dir(path)
+        .stdout(std::io::stderr())
+        .status()
+        .context("failed to run `docker`")?;
+    ensure!(status.success(), "`docker compose` exited with {status}");
+    Ok(())
+}
@@ -0,0 +1,59 @@
+//! Differences between the config of repositories and what grm would write, for `grm diff-config`
+
+use anyhow::Result;
+
+/// A config value of a repository different from the expected one
+pub struct Drift {
+    pub key: &'static str,
+    /// `None` if the key is not set
+    pub actual: Option<String>,
+    pub expected: String,
+}
+
+/// Compare the effective config of `repo` with `expected`
+pub fn compare(repo: &git2::Repository, expected: &[(&'static str, String)]) -> Result<Vec<Drift>> {
+    let config = repo.config()?.snapshot()?;
+    let mut drifts = Vec::new();
+    for (key, expected) in expected {
+        let actual = config.get_string(key).ok();
+        if actual
+            .as_deref()
+            .is_some_and(|actual| same(actual, expected))
+        {
+            continue;
+        }
+        drifts.push(Drift {
+            key,
+            actual,
+            expected: expected.clone(),
+        });
+    }
+    Ok(drifts)
+}
+
+/// Whether two values are equal, comparing booleans like `yes` and `true` as booleans
+fn same(actual: &str, expected: &str) -> bool {
+    if actual == expected {
+        return true;
+    }
+    match (
+        git2::Config::parse_bool(actual),
+        git2::Config::parse_bool(expected),
+    ) {
+        (Ok(actual), Ok(expected)) => actual == expected,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test_same {
+    use super::*;
+
+    #[test]
+    fn booleans() {
+        assert!(same("yes", "true"));
+        assert!(same("a@example.com", "a@example.com"));
+        assert!(!same("false", "true"));
+        assert!(!same("a@example.com", "b@example.com"));
+    }
+}
@@ -0,0 +1,77 @@
+use std::path::Path;
+
+use anyhow::Result;
+use git2::Repository;
+use walkdir::WalkDir;
+
+/// Disk usage of a repository in bytes
+#[derive(Clone, Copy, Default)]
+pub struct Usage {
+    pub worktree: u64,
+    /// The git directory excluding LFS objects
+    pub git: u64,
+    pub lfs: u64,
+}
+
+impl Usage {
+    pub fn measure(path: &Path) -> Result<Self> {
+        let repo = Repository::open(path)?;
+        let git_dir = repo.path();
+        let lfs = dir_size(&git_dir.join("lfs"), None);
+        let git = dir_size(git_dir, None).saturating_sub(lfs);
+        let worktree = match repo.workdir() {
+            Some(workdir) => dir_size(workdir, Some(git_dir)),
+            None => 0,
+        };
+        Ok(Self { worktree, git, lfs })
+    }
+
+    pub fn total(&self) -> u64 {
+        self.worktree + self.git + self.lfs
+    }
+}
+
+/// Sum the sizes of the files under `path` without following symbolic links
+pub fn dir_size(path: &Path, exclude: Option<&Path>) -> u64 {
+    WalkDir::new(path)
+        .into_iter()
+        .filter_entry(|entry| exclude.is_none_or(|exclude| entry.path() != exclude))
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| entry.metadata().ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// Format a size in bytes with a binary unit prefix
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["KiB", "MiB", "GiB", "TiB", "PiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+#[cfg(test)]
+mod test_format_size {
+    use super::*;
+
+    #[test]
+    fn bytes() {
+        assert_eq!("0 B", format_size(0));
+        assert_eq!("1023 B", format_size(1023));
+    }
+
+    #[test]
+    fn binary_units() {
+        assert_eq!("1.0 KiB", format_size(1024));
+        assert_eq!("1.5 MiB", format_size(1024 * 1024 * 3 / 2));
+        assert_eq!("2.0 GiB", format_size(2 << 30));
+    }
+}
@@ -0,0 +1,93 @@
+//! Allowing directory-environment tools such as direnv and mise in fresh clones, which refuse
+//! to load files they have not been told to trust
+
+use std::{
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use anyhow::Result;
+
+use crate::setup;
+
+/// `grm.envTrust`
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Trust {
+    /// Only mention the command to run
+    Never,
+    /// Ask before running it, skipping it without a terminal
+    Ask,
+    /// Run it without asking
+    Always,
+}
+
+impl Trust {
+    pub fn from_config(config: &git2::Config) -> Result<Self> {
+        let key = concat!(env!("CARGO_PKG_NAME"), ".envTrust");
+        let Ok(name) = config.get_string(key) else {
+            return Ok(Self::Never);
+        };
+        <Self as clap::ValueEnum>::from_str(&name, true)
+            .map_err(|_| anyhow::anyhow!("unknown value `{name}` in `{key}`"))
+    }
+}
+
+struct Tool {
+    /// Files the tool refuses to load until trusted
+    files: &'static [&'static str],
+    command: &'static [&'static str],
+}
+
+const TOOLS: &[Tool] = &[
+    Tool {
+        files: &[".envrc"],
+        command: &["direnv", "allow"],
+    },
+    Tool {
+        files: &[
+            "mise.toml",
+            ".mise.toml",
+            "mise.local.toml",
+            ".config/mise.toml",
+            ".config/mise/config.toml",
+            ".tool-versions",
+        ],
+        command: &["mise", "trust"],
+    },
+];
+
+/// Run the trust commands of the tools whose files the repository at `path` has, as `trust`
+/// allows
+pub fn apply(trust: Trust, path: &Path) {
+    for tool in TOOLS {
+        let Some(file) = tool.files.iter().find(|file| path.join(file).is_file()) else {
+            continue;
+        };
+        let command = tool.command.join(" ");
+        let run = match trust {
+            Trust::Never => false,
+            Trust::Ask if !setup::is_interactive() => false,
+            Trust::Ask => setup::ask(&format!("found {file}; run `{command}`?"), "n")
+                .is_ok_and(|answer| matches!(answer.to_lowercase().as_str(), "y" | "yes")),
+            Trust::Always => true,
+        };
+        if !run {
+            info!("found {file}; run `{command}` after reviewing it, or set grm.envTrust");
+            continue;
+        }
+        let status = Command::new(tool.command[0])
+            .args(&tool.command[1..])
+            .current_dir(path)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .status();
+        match status {
+            Ok(status) if status.success() => info!("ran `{command}`"),
+            Ok(status) => warn!("`{command}` exited with {status}"),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                info!("found {file}, but {} is not installed", tool.command[0]);
+            }
+            Err(err) => warn!("failed to run `{command}`: {err}"),
+        }
+    }
+}
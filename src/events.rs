@@ -0,0 +1,85 @@
+//! Machine-readable progress of bulk commands, streamed to stdout with `--events jsonl`
+
+use std::path::Path;
+
+use serde::Serialize;
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum Format {
+    /// One JSON object per line
+    Jsonl,
+}
+
+/// A state change of a repository in a bulk command
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "lowercase")]
+pub enum Event<'a> {
+    Started {
+        repo: &'a str,
+    },
+    Progress {
+        repo: &'a str,
+        message: &'a str,
+    },
+    Finished {
+        repo: &'a str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        path: Option<&'a Path>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        message: Option<&'a str>,
+    },
+    Failed {
+        repo: &'a str,
+        error: &'a str,
+    },
+}
+
+/// Where events go, nowhere unless `--events` is given
+#[derive(Clone, Copy, Default)]
+pub struct Events {
+    format: Option<Format>,
+}
+
+impl Events {
+    pub fn new(format: Option<Format>) -> Self {
+        Self { format }
+    }
+
+    pub fn is_enabled(self) -> bool {
+        self.format.is_some()
+    }
+
+    pub fn emit(self, event: &Event) {
+        match self.format {
+            None => {}
+            // stdout is line-buffered, so each event reaches the reader as soon as it happens
+            Some(Format::Jsonl) => println!("{}", serde_json::to_string(event).unwrap()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_event {
+    use super::*;
+
+    #[test]
+    fn serialize() {
+        let event = Event::Progress {
+            repo: "example.com/me/repo",
+            message: "fetched",
+        };
+        assert_eq!(
+            serde_json::to_string(&event).unwrap(),
+            r#"{"event":"progress","repo":"example.com/me/repo","message":"fetched"}"#
+        );
+        let event = Event::Finished {
+            repo: "example.com/me/repo",
+            path: None,
+            message: None,
+        };
+        assert_eq!(
+            serde_json::to_string(&event).unwrap(),
+            r#"{"event":"finished","repo":"example.com/me/repo"}"#
+        );
+    }
+}
@@ -0,0 +1,217 @@
+use std::{
+    io::{BufRead, BufReader, Read},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+use anyhow::{ensure, Context, Result};
+use serde::Serialize;
+
+use crate::{
+    events::{Event, Events},
+    head, paths, root, template,
+};
+
+pub struct Options {
+    /// Number of commands to run in parallel
+    pub jobs: usize,
+    /// Start no more commands after one fails
+    pub fail_fast: bool,
+    /// Keep the output in [`Outcome`] instead of printing it
+    pub capture: bool,
+    /// Stream the output lines as progress events instead of printing them
+    pub events: Events,
+}
+
+/// The result of the command in a repository
+#[derive(Serialize)]
+pub struct Outcome {
+    pub repo: String,
+    /// `None` if the command did not start or was killed by a signal
+    pub exit_code: Option<i32>,
+    /// Empty unless the output is captured
+    pub stdout: String,
+    pub stderr: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Run `command` in each repository, returning the results in the order of `paths`
+///
+/// With `fail_fast`, repositories after a failure are skipped and missing from the results.
+pub fn run(
+    roots: &[PathBuf],
+    paths: Vec<PathBuf>,
+    command: &[String],
+    opts: &Options,
+) -> Vec<Outcome> {
+    let outcomes = Mutex::new(Vec::new());
+    let failed = AtomicBool::new(false);
+    parallel(
+        paths.into_iter().enumerate().collect(),
+        opts.jobs,
+        |(i, path)| {
+            if opts.fail_fast && failed.load(Ordering::Relaxed) {
+                return;
+            }
+            let name = root::relative(roots, &path);
+            opts.events.emit(&Event::Started { repo: &name });
+            let outcome = run_one(name, &path, command, opts);
+            match &outcome.error {
+                Some(error) => {
+                    opts.events.emit(&Event::Failed {
+                        repo: &outcome.repo,
+                        error,
+                    });
+                    failed.store(true, Ordering::Relaxed);
+                }
+                None => opts.events.emit(&Event::Finished {
+                    repo: &outcome.repo,
+                    path: Some(&path),
+                    message: None,
+                }),
+            }
+            outcomes.lock().unwrap().push((i, outcome));
+        },
+    );
+    let mut outcomes = outcomes.into_inner().unwrap();
+    outcomes.sort_by_key(|(i, _)| *i);
+    outcomes.into_iter().map(|(_, outcome)| outcome).collect()
+}
+
+/// Where the repositories failed or skipped in the last run are kept for `--failed`
+fn get_failed_file() -> Result<PathBuf> {
+    Ok(paths::state_dir()?.join("foreach-failed.json"))
+}
+
+/// Names of the repositories failed or skipped in the last run
+pub fn load_failed() -> Result<Vec<String>> {
+    match std::fs::read_to_string(get_failed_file()?) {
+        Ok(content) => Ok(serde_json::from_str(&content)?),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(err) => Err(err.into()),
+    }
+}
+
+pub fn save_failed(names: &[&str]) -> Result<()> {
+    let file = get_failed_file()?;
+    if let Some(parent) = file.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(file, serde_json::to_string(names)?)?;
+    Ok(())
+}
+
+/// Call `f` with each item on `jobs` threads
+pub fn parallel<T: Send>(items: Vec<T>, jobs: usize, f: impl Fn(T) + Sync) {
+    let queue = Mutex::new(items.into_iter());
+    std::thread::scope(|s| {
+        for _ in 0..jobs.max(1) {
+            s.spawn(|| loop {
+                let Some(item) = queue.lock().unwrap().next() else {
+                    break;
+                };
+                f(item);
+            });
+        }
+    });
+}
+
+/// The number of threads to use when `--jobs` is not given
+pub fn default_jobs() -> usize {
+    std::thread::available_parallelism().map_or(1, std::num::NonZeroUsize::get)
+}
+
+/// Values of a repository replacing `{key}` in commands, exported as `GRM_<KEY>` too
+fn variables(name: &str, path: &Path) -> Vec<(&'static str, String)> {
+    let (host, owner, repo_name) = template::split_repo(name);
+    let branch = head::Head::read(path)
+        .ok()
+        .and_then(|head| head.branch)
+        .unwrap_or_default();
+    vec![
+        ("path", path.display().to_string()),
+        ("repo", name.to_string()),
+        ("host", host.to_string()),
+        ("owner", owner),
+        ("name", repo_name.to_string()),
+        ("branch", branch),
+    ]
+}
+
+fn run_one(repo: String, path: &Path, command: &[String], opts: &Options) -> Outcome {
+    let mut outcome = Outcome {
+        repo,
+        exit_code: None,
+        stdout: String::new(),
+        stderr: String::new(),
+        error: None,
+    };
+    if let Err(err) = spawn(&mut outcome, path, command, opts) {
+        outcome.error = Some(format!("{err:#}"));
+    }
+    outcome
+}
+
+fn spawn(outcome: &mut Outcome, path: &Path, command: &[String], opts: &Options) -> Result<()> {
+    let name = outcome.repo.as_str();
+    let variables = variables(name, path);
+    let command: Vec<_> = command
+        .iter()
+        .map(|arg| template::expand(arg, &variables))
+        .collect();
+    let (program, args) = command.split_first().context("no command is given")?;
+    let mut child = Command::new(program)
+        .args(args)
+        .envs(
+            variables
+                .iter()
+                .map(|(key, value)| (format!("GRM_{}", key.to_uppercase()), value)),
+        )
+        .current_dir(path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("failed to run `{program}`"))?;
+
+    let stdout = child.stdout.take().unwrap();
+    let stderr = child.stderr.take().unwrap();
+    let (stdout, stderr) = std::thread::scope(|s| {
+        let stdout = s.spawn(|| forward(name, stdout, false, opts));
+        let stderr = s.spawn(|| forward(name, stderr, true, opts));
+        (stdout.join().unwrap(), stderr.join().unwrap())
+    });
+    outcome.stdout = stdout;
+    outcome.stderr = stderr;
+
+    let status = child.wait()?;
+    outcome.exit_code = status.code();
+    ensure!(status.success(), "`{program}` exited with {status}");
+    Ok(())
+}
+
+/// Print each line of `reader` prefixed with the repository name, or return them if captured
+fn forward(name: &str, reader: impl Read, to_stderr: bool, opts: &Options) -> String {
+    let mut captured = String::new();
+    for line in BufReader::new(reader).lines().map_while(Result::ok) {
+        if opts.events.is_enabled() {
+            opts.events.emit(&Event::Progress {
+                repo: name,
+                message: &line,
+            });
+        } else if opts.capture {
+            captured.push_str(&line);
+            captured.push('\n');
+        } else if to_stderr {
+            eprintln!("{name}: {line}");
+        } else {
+            println!("{name}: {line}");
+        }
+    }
+    captured
+}
@@ -0,0 +1,232 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::{profile::Profile, vault};
+
+#[cfg(feature = "forge")]
+mod cache;
+#[cfg(feature = "forge")]
+mod client;
+#[cfg(not(feature = "forge"))]
+mod disabled;
+#[cfg(feature = "forge")]
+mod gitea;
+#[cfg(feature = "forge")]
+mod github;
+#[cfg(feature = "forge")]
+mod gitlab;
+#[cfg(feature = "forge")]
+mod sourcehut;
+
+#[cfg(feature = "forge")]
+pub use client::{is_status, Client};
+#[cfg(not(feature = "forge"))]
+pub use disabled::{is_status, Client};
+
+/// A repository hosted on a forge
+pub struct RemoteRepo {
+    pub url: Url,
+    pub archived: bool,
+}
+
+/// Whether a repository on a forge still accepts changes
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct RepoState {
+    pub archived: bool,
+    /// The token in use cannot push to the repository
+    pub read_only: bool,
+}
+
+impl RepoState {
+    /// A short note shown next to the repository, if any
+    pub fn label(&self) -> Option<&'static str> {
+        if self.archived {
+            Some("archived")
+        } else if self.read_only {
+            Some("read-only")
+        } else {
+            None
+        }
+    }
+}
+
+/// State and size of a repository
+pub struct RepoDetail {
+    pub state: RepoState,
+    /// In bytes, if the forge reports it
+    pub size: Option<u64>,
+    /// The HTTPS clone URL of the repository this one was forked from
+    pub parent: Option<Url>,
+}
+
+/// The REST or GraphQL API of a kind of forge
+pub trait Forge {
+    /// The API base used unless `grm.<host>.api` is set
+    #[cfg_attr(not(feature = "forge"), allow(dead_code))]
+    fn default_api(&self, host: &str) -> String;
+    /// Fetch the details of `path` like `owner/name`
+    fn repo_detail(&self, client: &Client, path: &str) -> Result<RepoDetail>;
+    /// List repositories owned by a user or a group
+    fn list_repos(&self, client: &Client, owner: &str) -> Result<Vec<RemoteRepo>>;
+    /// Create `name` owned by `owner`, or by the user of the token if `owner` is `None`, and
+    /// return its HTTPS and SSH clone URLs, returning an existing repository as it is
+    fn create_repo(
+        &self,
+        client: &Client,
+        owner: Option<&str>,
+        name: &str,
+        private: bool,
+    ) -> Result<(Url, String)>;
+    /// The login name of the user of the token
+    fn token_user(&self, client: &Client) -> Result<String>;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum ForgeKind {
+    /// GitHub and GitHub Enterprise Server
+    Github,
+    /// GitLab.com and self-managed GitLab
+    Gitlab,
+    /// Gitea and Forgejo, including Codeberg
+    Gitea,
+    /// sourcehut (git.sr.ht)
+    Sourcehut,
+}
+
+impl ForgeKind {
+    /// Read `grm.<host>.forge`, falling back to [`ForgeKind::detect`]
+    pub fn from_config(config: &git2::Config, host: &str) -> Result<Self> {
+        let key = format!("{}.{host}.forge", env!("CARGO_PKG_NAME"));
+        let Ok(name) = config.get_string(&key) else {
+            return Ok(Self::detect(host));
+        };
+        <Self as clap::ValueEnum>::from_str(&name, true)
+            .map_err(|_| anyhow::anyhow!("unknown forge `{name}` in `{key}`"))
+    }
+
+    /// Guess the forge from well-known hosts and host name prefixes, assuming GitHub Enterprise
+    pub fn detect(host: &str) -> Self {
+        let host = host.to_ascii_lowercase();
+        let first = host.split('.').next().unwrap_or_default();
+        match host.as_str() {
+            "github.com" => Self::Github,
+            "gitlab.com" | "salsa.debian.org" | "gitlab.gnome.org" | "invent.kde.org" => {
+                Self::Gitlab
+            }
+            "codeberg.org" | "gitea.com" => Self::Gitea,
+            "git.sr.ht" => Self::Sourcehut,
+            _ if first == "gitlab" => Self::Gitlab,
+            _ if first == "gitea" || first == "forgejo" => Self::Gitea,
+            _ => Self::Github,
+        }
+    }
+}
+
+/// Fetch the state of `<host>/<path>`, where `path` is like `owner/name`
+pub fn repo_state(
+    config: &git2::Config,
+    profile: &Profile,
+    host: &str,
+    path: &str,
+) -> Result<RepoState> {
+    let (client, forge) = Client::new(config, profile, host)?;
+    Ok(forge.repo_detail(&client, path)?.state)
+}
+
+/// Fetch the size of `<host>/<path>` in bytes as reported by the forge
+pub fn repo_size(config: &git2::Config, profile: &Profile, host: &str, path: &str) -> Result<u64> {
+    let (client, forge) = Client::new(config, profile, host)?;
+    forge
+        .repo_detail(&client, path)?
+        .size
+        .with_context(|| format!("{host} does not report sizes of repositories"))
+}
+
+/// Fetch the HTTPS clone URL of the repository `<host>/<path>` was forked from, `None` if it is
+/// not a fork
+pub fn fork_parent(
+    config: &git2::Config,
+    profile: &Profile,
+    host: &str,
+    path: &str,
+) -> Result<Option<Url>> {
+    let (client, forge) = Client::new(config, profile, host)?;
+    Ok(forge.repo_detail(&client, path)?.parent)
+}
+
+/// Create the repository `name` owned by `owner`, or by the user of the token if `owner` is
+/// `None`, and return its HTTPS and SSH clone URLs
+///
+/// An existing repository of the same name is returned as it is.
+pub fn create_repo(
+    config: &git2::Config,
+    profile: &Profile,
+    host: &str,
+    owner: Option<&str>,
+    name: &str,
+    private: bool,
+) -> Result<(Url, String)> {
+    let (client, forge) = Client::new(config, profile, host)?;
+    forge
+        .create_repo(&client, owner, name, private)
+        .with_context(|| format!("failed to create `{name}` on {host}"))
+}
+
+/// The login name of the user of the token for `host`
+pub fn token_user(config: &git2::Config, profile: &Profile, host: &str) -> Result<String> {
+    let (client, forge) = Client::new(config, profile, host)?;
+    forge.token_user(&client)
+}
+
+/// List repositories owned by a user or an organization
+pub fn list_repos(
+    config: &git2::Config,
+    profile: &Profile,
+    host: &str,
+    owner: &str,
+) -> Result<Vec<RemoteRepo>> {
+    let (client, forge) = Client::new(config, profile, host)?;
+    forge.list_repos(&client, owner)
+}
+
+/// Read `grm.<host>/<owner>.token`, `grm.<host>.token` and the same keys in the vault, falling
+/// back to `GITHUB_TOKEN` / `GH_TOKEN` for github.com
+pub fn token(config: &git2::Config, host: &str, owner: Option<&str>) -> Option<String> {
+    let keys = owner
+        .map(|owner| format!("{host}/{owner}"))
+        .into_iter()
+        .chain([host.to_string()]);
+    for key in keys {
+        let config_key = format!("{}.{key}.token", env!("CARGO_PKG_NAME"));
+        if let Ok(token) = config.get_string(&config_key) {
+            return Some(token);
+        }
+        let token = vault::unlock(config).and_then(|tokens| tokens.get(&key));
+        if let Some(token) = token {
+            return Some(token.clone());
+        }
+    }
+    if host != "github.com" {
+        return None;
+    }
+    std::env::var("GITHUB_TOKEN")
+        .or_else(|_| std::env::var("GH_TOKEN"))
+        .ok()
+}
+
+#[cfg(test)]
+mod test_detect {
+    use super::*;
+
+    #[test]
+    fn hosts() {
+        assert_eq!(ForgeKind::detect("github.com"), ForgeKind::Github);
+        assert_eq!(ForgeKind::detect("ghe.corp"), ForgeKind::Github);
+        assert_eq!(ForgeKind::detect("gitlab.com"), ForgeKind::Gitlab);
+        assert_eq!(ForgeKind::detect("gitlab.corp"), ForgeKind::Gitlab);
+        assert_eq!(ForgeKind::detect("Codeberg.org"), ForgeKind::Gitea);
+        assert_eq!(ForgeKind::detect("forgejo.corp"), ForgeKind::Gitea);
+        assert_eq!(ForgeKind::detect("git.sr.ht"), ForgeKind::Sourcehut);
+    }
+}
@@ -0,0 +1,43 @@
+//! Responses of GET requests kept under the cache directory and revalidated with their ETags,
+//! so that unchanged resources do not count against the rate limit
+
+use std::path::PathBuf;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{hash, paths};
+
+#[derive(Serialize, Deserialize)]
+pub struct Cached {
+    pub etag: String,
+    pub body: String,
+    /// The URL of the next page from the `Link` header
+    pub next: Option<String>,
+}
+
+/// Responses differ by the credentials, so they are part of the key as well as the URL
+fn get_file(url: &str, authorization: Option<&str>) -> Result<PathBuf> {
+    let key = match authorization {
+        Some(authorization) => format!("{url}\n{authorization}"),
+        None => url.to_string(),
+    };
+    let hash = hash::fnv1a(key.as_bytes());
+    Ok(paths::cache_dir()?
+        .join("api")
+        .join(format!("{hash:016x}.json")))
+}
+
+pub fn load(url: &str, authorization: Option<&str>) -> Option<Cached> {
+    let file = get_file(url, authorization).ok()?;
+    serde_json::from_str(&std::fs::read_to_string(file).ok()?).ok()
+}
+
+pub fn save(url: &str, authorization: Option<&str>, cached: &Cached) -> Result<()> {
+    let file = get_file(url, authorization)?;
+    if let Some(parent) = file.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(file, serde_json::to_string(cached)?)?;
+    Ok(())
+}
@@ -0,0 +1,321 @@
+//! HTTP requests to forge APIs, compiled with the `forge` feature
+
+use std::time::{Duration, SystemTime};
+
+use anyhow::{ensure, Context, Result};
+use serde::de::DeserializeOwned;
+use url::Url;
+
+use super::{cache, gitea, github, gitlab, sourcehut, token, Forge, ForgeKind};
+use crate::{
+    headers,
+    profile::Profile,
+    proxy::{self, Proxy},
+};
+
+const MAX_RETRIES: u32 = 3;
+/// Longer waits for the rate limit to reset fail instead
+const MAX_WAIT: Duration = Duration::from_secs(60);
+
+impl ForgeKind {
+    fn build(self) -> Box<dyn Forge> {
+        match self {
+            Self::Github => Box::new(github::Github),
+            Self::Gitlab => Box::new(gitlab::Gitlab),
+            Self::Gitea => Box::new(gitea::Gitea),
+            Self::Sourcehut => Box::new(sourcehut::Sourcehut),
+        }
+    }
+}
+
+/// Requests to the API of a host with the credentials of the profile
+pub struct Client<'a> {
+    config: &'a git2::Config,
+    profile: &'a Profile,
+    host: &'a str,
+    /// The API base without a trailing slash
+    pub api: String,
+    accept: &'static str,
+}
+
+impl<'a> Client<'a> {
+    pub(super) fn new(
+        config: &'a git2::Config,
+        profile: &'a Profile,
+        host: &'a str,
+    ) -> Result<(Self, Box<dyn Forge>)> {
+        let kind = ForgeKind::from_config(config, host)?;
+        let forge = kind.build();
+        let key = format!("{}.{host}.api", env!("CARGO_PKG_NAME"));
+        let api = config
+            .get_string(&key)
+            .unwrap_or_else(|_| forge.default_api(host));
+        let client = Self {
+            config,
+            profile,
+            host,
+            api: api.trim_end_matches('/').to_string(),
+            accept: match kind {
+                ForgeKind::Github => "application/vnd.github+json",
+                _ => "application/json",
+            },
+        };
+        Ok((client, forge))
+    }
+
+    pub fn host(&self) -> &str {
+        self.host
+    }
+
+    /// `path` under the API base
+    pub fn url(&self, path: &str) -> Result<Url> {
+        Ok(Url::parse(&format!("{}{path}", self.api))?)
+    }
+
+    /// GET `url` and deserialize the JSON body
+    pub fn get_json<T: DeserializeOwned>(&self, owner: Option<&str>, url: &Url) -> Result<T> {
+        let (body, _) = self.fetch(owner, url)?;
+        serde_json::from_str(&body).with_context(|| format!("unexpected response from `{url}`"))
+    }
+
+    /// GET `url` and the pages following it in the `Link` header, and concatenate the items
+    pub fn get_pages<T: DeserializeOwned>(&self, owner: Option<&str>, url: &Url) -> Result<Vec<T>> {
+        let mut items = Vec::new();
+        let mut next = Some(url.clone());
+        while let Some(url) = next {
+            let (body, link) = self.fetch(owner, &url)?;
+            let page: Vec<T> = serde_json::from_str(&body)
+                .with_context(|| format!("unexpected response from `{url}`"))?;
+            items.extend(page);
+            next = link.map(|link| Url::parse(&link)).transpose()?;
+        }
+        Ok(items)
+    }
+
+    /// POST `body` as JSON to `url`
+    pub fn post_json(
+        &self,
+        owner: Option<&str>,
+        url: &Url,
+        body: &serde_json::Value,
+    ) -> Result<ureq::Response> {
+        let request = self.request(owner, "POST", url)?;
+        self.send(&request, Some(body))
+            .with_context(|| format!("failed to request `{url}`"))
+    }
+
+    /// GET `url`, revalidating a cached response with its ETag, and return the body and the URL
+    /// of the next page
+    fn fetch(&self, owner: Option<&str>, url: &Url) -> Result<(String, Option<String>)> {
+        let mut request = self.request(owner, "GET", url)?;
+        let authorization = request.header("Authorization").map(String::from);
+        let cached = cache::load(url.as_str(), authorization.as_deref());
+        if let Some(cached) = &cached {
+            request = request.set("If-None-Match", &cached.etag);
+        }
+        let response = self
+            .send(&request, None)
+            .with_context(|| format!("failed to request `{url}`"))?;
+        if let (304, Some(cached)) = (response.status(), cached) {
+            return Ok((cached.body, cached.next));
+        }
+        let etag = response.header("ETag").map(String::from);
+        let next = response.header("Link").and_then(next_link);
+        let body = response.into_string()?;
+        if let Some(etag) = etag {
+            let cached = cache::Cached {
+                etag,
+                body: body.clone(),
+                next: next.clone(),
+            };
+            if let Err(err) = cache::save(url.as_str(), authorization.as_deref(), &cached) {
+                info!("failed to cache the response: {err:#}");
+            }
+        }
+        Ok((body, next))
+    }
+
+    /// Send `request`, waiting and retrying while the rate limit is exceeded
+    fn send(
+        &self,
+        request: &ureq::Request,
+        body: Option<&serde_json::Value>,
+    ) -> Result<ureq::Response> {
+        let mut attempt = 0;
+        loop {
+            let result = match body {
+                Some(body) => request.clone().send_json(body),
+                None => request.clone().call(),
+            };
+            let response = match result {
+                Err(ureq::Error::Status(403 | 429, response)) if attempt < MAX_RETRIES => response,
+                result => return Ok(result?),
+            };
+            let header = |name| response.header(name).map(String::from);
+            let now = SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs());
+            let wait = retry_after(
+                header("Retry-After").as_deref(),
+                header("X-RateLimit-Remaining")
+                    .or_else(|| header("RateLimit-Remaining"))
+                    .as_deref(),
+                header("X-RateLimit-Reset")
+                    .or_else(|| header("RateLimit-Reset"))
+                    .as_deref(),
+                now,
+            );
+            let wait = match wait {
+                Some(wait) => wait,
+                // 429 without a hint is still a rate limit, while 403 may just be a denial
+                None if response.status() == 429 => Duration::from_secs(1 << attempt),
+                None => return Err(ureq::Error::Status(403, response).into()),
+            };
+            ensure!(
+                wait <= MAX_WAIT,
+                "the API rate limit of {} is exceeded for {} more minutes",
+                self.host,
+                wait.as_secs().div_ceil(60)
+            );
+            info!(
+                "the API rate limit of {} is exceeded; retrying in {}s",
+                self.host,
+                wait.as_secs()
+            );
+            std::thread::sleep(wait);
+            attempt += 1;
+        }
+    }
+
+    fn request(&self, owner: Option<&str>, method: &str, url: &Url) -> Result<ureq::Request> {
+        request(
+            self.config,
+            self.profile,
+            self.host,
+            owner,
+            self.accept,
+            method,
+            url,
+        )
+    }
+}
+
+/// How long to wait before retrying a request refused by the rate limit, from `Retry-After` or
+/// the remaining count and the reset time in seconds since the Unix epoch
+fn retry_after(
+    retry_after: Option<&str>,
+    remaining: Option<&str>,
+    reset: Option<&str>,
+    now: u64,
+) -> Option<Duration> {
+    if let Some(seconds) = retry_after.and_then(|s| s.trim().parse().ok()) {
+        return Some(Duration::from_secs(seconds));
+    }
+    if remaining?.trim() != "0" {
+        return None;
+    }
+    let reset: u64 = reset?.trim().parse().ok()?;
+    // leave a second for clock skew
+    Some(Duration::from_secs(reset.saturating_sub(now) + 1))
+}
+
+/// The URL of `rel="next"` in a `Link` header
+fn next_link(link: &str) -> Option<String> {
+    link.split(',').find_map(|part| {
+        let (url, params) = part.split_once(';')?;
+        params
+            .split(';')
+            .any(|param| {
+                param
+                    .trim()
+                    .strip_prefix("rel=")
+                    .is_some_and(|rel| rel.trim_matches('"').split(' ').any(|r| r == "next"))
+            })
+            .then(|| {
+                url.trim()
+                    .trim_start_matches('<')
+                    .trim_end_matches('>')
+                    .to_string()
+            })
+    })
+}
+
+/// Whether `err` is the response of `status`
+pub fn is_status(err: &anyhow::Error, status: &[u16]) -> bool {
+    err.chain().any(|cause| {
+        matches!(
+            cause.downcast_ref(),
+            Some(ureq::Error::Status(code, _)) if status.contains(code)
+        )
+    })
+}
+
+fn request(
+    config: &git2::Config,
+    profile: &Profile,
+    host: &str,
+    owner: Option<&str>,
+    accept: &str,
+    method: &str,
+    url: &Url,
+) -> Result<ureq::Request> {
+    let mut agent = ureq::AgentBuilder::new().user_agent(concat!(
+        env!("CARGO_PKG_NAME"),
+        "/",
+        env!("CARGO_PKG_VERSION")
+    ));
+    if let Proxy::Url(proxy) = proxy::resolve(config, url) {
+        agent = agent.proxy(ureq::Proxy::new(proxy)?);
+    }
+    let mut request = agent
+        .build()
+        .request(method, url.as_str())
+        .set("Accept", accept);
+    if let Some(token) = profile.token.clone().or_else(|| token(config, host, owner)) {
+        request = request.set("Authorization", &format!("Bearer {token}"));
+    }
+    let mut extra = headers::configured(config, url)?;
+    extra.extend(headers::generated(config, host)?);
+    for header in extra {
+        if let Some((name, value)) = header.split_once(':') {
+            request = request.set(name.trim(), value.trim());
+        }
+    }
+    Ok(request)
+}
+
+#[cfg(test)]
+mod test_retry_after {
+    use super::*;
+
+    #[test]
+    fn headers() {
+        let secs = Duration::from_secs;
+        assert_eq!(retry_after(Some("30"), None, None, 0), Some(secs(30)));
+        assert_eq!(
+            retry_after(None, Some("0"), Some("1010"), 1000),
+            Some(secs(11))
+        );
+        assert_eq!(
+            retry_after(None, Some("0"), Some("900"), 1000),
+            Some(secs(1))
+        );
+        assert_eq!(retry_after(None, Some("12"), Some("1010"), 1000), None);
+        assert_eq!(retry_after(None, None, None, 1000), None);
+    }
+}
+
+#[cfg(test)]
+mod test_next_link {
+    use super::*;
+
+    #[test]
+    fn rels() {
+        let link = r#"<https://api.github.com/users/a/repos?page=2>; rel="next", <https://api.github.com/users/a/repos?page=5>; rel="last""#;
+        assert_eq!(
+            next_link(link).as_deref(),
+            Some("https://api.github.com/users/a/repos?page=2")
+        );
+        assert_eq!(next_link(r#"<https://x/?page=1>; rel="prev""#), None);
+    }
+}
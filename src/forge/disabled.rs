@@ -0,0 +1,23 @@
+//! Stand-ins for builds without the `forge` feature, failing every request
+
+use anyhow::{bail, Result};
+
+use super::Forge;
+use crate::profile::Profile;
+
+pub struct Client;
+
+impl Client {
+    pub(super) fn new(
+        _config: &git2::Config,
+        _profile: &Profile,
+        host: &str,
+    ) -> Result<(Self, Box<dyn Forge>)> {
+        bail!("cannot request the API of {host}: build with the `forge` feature to use forge APIs")
+    }
+}
+
+/// Always `false`, as no request is made
+pub fn is_status(_err: &anyhow::Error, _status: &[u16]) -> bool {
+    false
+}
@@ -0,0 +1,47 @@
+//! Gitea and Forgejo, whose API mostly follows the one of GitHub
+
+use anyhow::Result;
+use url::Url;
+
+use super::{github, is_status, Client, Forge, RemoteRepo, RepoDetail};
+
+/// The default maximum of `limit`
+const PER_PAGE: usize = 50;
+
+pub struct Gitea;
+
+impl Forge for Gitea {
+    fn default_api(&self, host: &str) -> String {
+        format!("https://{host}/api/v1")
+    }
+
+    fn repo_detail(&self, client: &Client, path: &str) -> Result<RepoDetail> {
+        github::Github.repo_detail(client, path)
+    }
+
+    fn list_repos(&self, client: &Client, owner: &str) -> Result<Vec<RemoteRepo>> {
+        // unlike GitHub, `/users/{owner}/repos` does not list repositories of organizations
+        let path = format!("/orgs/{owner}/repos");
+        match github::list_pages(client, owner, &path, "limit", PER_PAGE) {
+            Err(err) if is_status(&err, &[404]) => {
+                let path = format!("/users/{owner}/repos");
+                github::list_pages(client, owner, &path, "limit", PER_PAGE)
+            }
+            result => result,
+        }
+    }
+
+    fn create_repo(
+        &self,
+        client: &Client,
+        owner: Option<&str>,
+        name: &str,
+        private: bool,
+    ) -> Result<(Url, String)> {
+        github::Github.create_repo(client, owner, name, private)
+    }
+
+    fn token_user(&self, client: &Client) -> Result<String> {
+        github::Github.token_user(client)
+    }
+}
@@ -0,0 +1,149 @@
+use anyhow::Result;
+use serde::Deserialize;
+use url::Url;
+
+use super::{is_status, Client, Forge, RemoteRepo, RepoDetail, RepoState};
+
+const PER_PAGE: usize = 100;
+
+pub struct Github;
+
+#[derive(Deserialize)]
+struct ApiRepo {
+    html_url: Url,
+    archived: bool,
+}
+
+#[derive(Deserialize)]
+struct ApiRepoDetail {
+    archived: bool,
+    /// Only returned for authenticated requests
+    permissions: Option<ApiPermissions>,
+    /// In KiB
+    #[serde(default)]
+    size: u64,
+    /// Only returned for forks
+    parent: Option<ApiParent>,
+}
+
+#[derive(Deserialize)]
+struct ApiParent {
+    clone_url: Url,
+}
+
+#[derive(Deserialize)]
+struct ApiPermissions {
+    push: bool,
+}
+
+#[derive(Deserialize)]
+struct ApiCreatedRepo {
+    clone_url: Url,
+    ssh_url: String,
+}
+
+#[derive(Deserialize)]
+struct ApiUser {
+    login: String,
+}
+
+impl Forge for Github {
+    fn default_api(&self, host: &str) -> String {
+        if host == "github.com" {
+            "https://api.github.com".to_string()
+        } else {
+            format!("https://{host}/api/v3")
+        }
+    }
+
+    fn repo_detail(&self, client: &Client, path: &str) -> Result<RepoDetail> {
+        let url = client.url(&format!("/repos/{path}"))?;
+        let owner = path.split('/').next();
+        let repo: ApiRepoDetail = client.get_json(owner, &url)?;
+        Ok(RepoDetail {
+            state: RepoState {
+                archived: repo.archived,
+                read_only: repo.permissions.is_some_and(|p| !p.push),
+            },
+            size: Some(repo.size * 1024),
+            parent: repo.parent.map(|parent| parent.clone_url),
+        })
+    }
+
+    fn list_repos(&self, client: &Client, owner: &str) -> Result<Vec<RemoteRepo>> {
+        // `/users/{owner}/repos` only lists public repositories, even of the token's user
+        let user = match client.get_json::<ApiUser>(Some(owner), &client.url("/user")?) {
+            Ok(user) => Some(user.login),
+            Err(err) if is_status(&err, &[401, 403, 404]) => None,
+            Err(err) => return Err(err),
+        };
+        if user.is_some_and(|user| user.eq_ignore_ascii_case(owner)) {
+            let path = "/user/repos?affiliation=owner";
+            return list_pages(client, owner, path, "per_page", PER_PAGE);
+        }
+        let path = format!("/orgs/{owner}/repos");
+        match list_pages(client, owner, &path, "per_page", PER_PAGE) {
+            Err(err) if is_status(&err, &[404]) => {
+                let path = format!("/users/{owner}/repos");
+                list_pages(client, owner, &path, "per_page", PER_PAGE)
+            }
+            result => result,
+        }
+    }
+
+    fn create_repo(
+        &self,
+        client: &Client,
+        owner: Option<&str>,
+        name: &str,
+        private: bool,
+    ) -> Result<(Url, String)> {
+        let url = match owner {
+            Some(owner) if !self.token_user(client)?.eq_ignore_ascii_case(owner) => {
+                client.url(&format!("/orgs/{owner}/repos"))?
+            }
+            _ => client.url("/user/repos")?,
+        };
+        let body = serde_json::json!({ "name": name, "private": private });
+        let repo: ApiCreatedRepo = match client.post_json(owner, &url, &body) {
+            Ok(response) => response.into_json()?,
+            // GitHub answers 422 and Gitea answers 409 for existing repositories
+            Err(err) if is_status(&err, &[409, 422]) => {
+                let owner = match owner {
+                    Some(owner) => owner.to_string(),
+                    None => self.token_user(client)?,
+                };
+                let url = client.url(&format!("/repos/{owner}/{name}"))?;
+                client.get_json(Some(&owner), &url)?
+            }
+            Err(err) => return Err(err),
+        };
+        Ok((repo.clone_url, repo.ssh_url))
+    }
+
+    fn token_user(&self, client: &Client) -> Result<String> {
+        let user: ApiUser = client.get_json(None, &client.url("/user")?)?;
+        Ok(user.login)
+    }
+}
+
+/// Fetch `path` and the following pages, with the page size set by the query parameter
+/// `size_param`
+pub(super) fn list_pages(
+    client: &Client,
+    owner: &str,
+    path: &str,
+    size_param: &str,
+    per_page: usize,
+) -> Result<Vec<RemoteRepo>> {
+    let separator = if path.contains('?') { '&' } else { '?' };
+    let url = client.url(&format!("{path}{separator}{size_param}={per_page}"))?;
+    let items: Vec<ApiRepo> = client.get_pages(Some(owner), &url)?;
+    Ok(items
+        .into_iter()
+        .map(|repo| RemoteRepo {
+            url: repo.html_url,
+            archived: repo.archived,
+        })
+        .collect())
+}
@@ -0,0 +1,172 @@
+use anyhow::Result;
+use serde::Deserialize;
+use url::Url;
+
+use super::{is_status, Client, Forge, RemoteRepo, RepoDetail, RepoState};
+
+const PER_PAGE: usize = 100;
+
+/// The lowest access level allowed to push
+const DEVELOPER: u32 = 30;
+
+pub struct Gitlab;
+
+#[derive(Deserialize)]
+struct ApiProject {
+    web_url: Url,
+    archived: bool,
+}
+
+#[derive(Deserialize)]
+struct ApiProjectDetail {
+    archived: bool,
+    /// Only returned for authenticated requests
+    permissions: Option<ApiPermissions>,
+    /// Only returned to members with at least the Reporter role
+    statistics: Option<ApiStatistics>,
+    /// Only returned for forks
+    forked_from_project: Option<ApiForkedFrom>,
+}
+
+#[derive(Deserialize)]
+struct ApiForkedFrom {
+    http_url_to_repo: Url,
+}
+
+#[derive(Deserialize)]
+struct ApiPermissions {
+    project_access: Option<ApiAccess>,
+    group_access: Option<ApiAccess>,
+}
+
+#[derive(Deserialize)]
+struct ApiAccess {
+    access_level: u32,
+}
+
+#[derive(Deserialize)]
+struct ApiStatistics {
+    /// In bytes
+    repository_size: u64,
+}
+
+#[derive(Deserialize)]
+struct ApiCreatedProject {
+    http_url_to_repo: Url,
+    ssh_url_to_repo: String,
+}
+
+#[derive(Deserialize)]
+struct ApiNamespace {
+    id: u64,
+}
+
+#[derive(Deserialize)]
+struct ApiUser {
+    username: String,
+}
+
+/// Projects and namespaces are addressed by their full paths with slashes encoded
+fn encode(path: &str) -> String {
+    path.replace('/', "%2F")
+}
+
+impl Forge for Gitlab {
+    fn default_api(&self, host: &str) -> String {
+        format!("https://{host}/api/v4")
+    }
+
+    fn repo_detail(&self, client: &Client, path: &str) -> Result<RepoDetail> {
+        let url = client.url(&format!("/projects/{}?statistics=true", encode(path)))?;
+        let owner = path.split('/').next();
+        let project: ApiProjectDetail = client.get_json(owner, &url)?;
+        let read_only = project.permissions.is_some_and(|p| {
+            let level = [p.project_access, p.group_access]
+                .into_iter()
+                .flatten()
+                .map(|access| access.access_level)
+                .max()
+                .unwrap_or(0);
+            level < DEVELOPER
+        });
+        Ok(RepoDetail {
+            state: RepoState {
+                archived: project.archived,
+                read_only,
+            },
+            size: project.statistics.map(|s| s.repository_size),
+            parent: project
+                .forked_from_project
+                .map(|parent| parent.http_url_to_repo),
+        })
+    }
+
+    fn list_repos(&self, client: &Client, owner: &str) -> Result<Vec<RemoteRepo>> {
+        let groups = format!("/groups/{}/projects?include_subgroups=true&", encode(owner));
+        match list_pages(client, owner, &groups) {
+            Err(err) if is_status(&err, &[404]) => list_pages(
+                client,
+                owner,
+                &format!("/users/{}/projects?", encode(owner)),
+            ),
+            result => result,
+        }
+    }
+
+    fn create_repo(
+        &self,
+        client: &Client,
+        owner: Option<&str>,
+        name: &str,
+        private: bool,
+    ) -> Result<(Url, String)> {
+        let namespace_id = match owner {
+            Some(owner) if !self.token_user(client)?.eq_ignore_ascii_case(owner) => {
+                let url = client.url(&format!("/namespaces/{}", encode(owner)))?;
+                let namespace: ApiNamespace = client.get_json(Some(owner), &url)?;
+                Some(namespace.id)
+            }
+            _ => None,
+        };
+        let body = serde_json::json!({
+            "name": name,
+            "path": name,
+            "namespace_id": namespace_id,
+            "visibility": if private { "private" } else { "public" },
+        });
+        let url = client.url("/projects")?;
+        let project: ApiCreatedProject = match client.post_json(owner, &url, &body) {
+            Ok(response) => response.into_json()?,
+            // a path already taken is answered with 400
+            Err(err) if is_status(&err, &[400]) => {
+                let owner = match owner {
+                    Some(owner) => owner.to_string(),
+                    None => self.token_user(client)?,
+                };
+                let url =
+                    client.url(&format!("/projects/{}", encode(&format!("{owner}/{name}"))))?;
+                client.get_json(Some(&owner), &url)?
+            }
+            Err(err) => return Err(err),
+        };
+        Ok((project.http_url_to_repo, project.ssh_url_to_repo))
+    }
+
+    fn token_user(&self, client: &Client) -> Result<String> {
+        let user: ApiUser = client.get_json(None, &client.url("/user")?)?;
+        Ok(user.username)
+    }
+}
+
+/// Fetch `path`, ending with `?` or `&`, and the following pages
+fn list_pages(client: &Client, owner: &str, path: &str) -> Result<Vec<RemoteRepo>> {
+    let url = client.url(&format!("{path}per_page={PER_PAGE}"))?;
+    let items: Vec<ApiProject> = client.get_pages(Some(owner), &url)?;
+    Ok(items
+        .into_iter()
+        .map(|project| RemoteRepo {
+            url: project.web_url,
+            archived: project.archived,
+        })
+        .collect())
+}
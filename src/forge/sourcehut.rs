@@ -0,0 +1,197 @@
+//! sourcehut, whose git.sr.ht only provides a GraphQL API
+
+use anyhow::{bail, ensure, Context, Result};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use url::Url;
+
+use super::{Client, Forge, RemoteRepo, RepoDetail, RepoState};
+
+pub struct Sourcehut;
+
+#[derive(Deserialize)]
+struct Response<T> {
+    data: Option<T>,
+    #[serde(default)]
+    errors: Vec<ApiError>,
+}
+
+#[derive(Deserialize)]
+struct ApiError {
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct UserData<T> {
+    user: Option<T>,
+}
+
+#[derive(Deserialize)]
+struct RepositoryData {
+    repository: Option<ApiRepository>,
+}
+
+#[derive(Deserialize)]
+struct ApiRepository {
+    name: String,
+    /// `RO` or `RW`
+    access: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RepositoriesData {
+    repositories: ApiCursor,
+}
+
+#[derive(Deserialize)]
+struct ApiCursor {
+    results: Vec<ApiRepository>,
+    cursor: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct MeData {
+    me: ApiUser,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiUser {
+    canonical_name: String,
+}
+
+/// Send a GraphQL query, failing on any error in the response
+fn query<T: DeserializeOwned>(
+    client: &Client,
+    owner: Option<&str>,
+    query: &str,
+    variables: Value,
+) -> Result<T> {
+    let url = client.url("")?;
+    let body = json!({ "query": query, "variables": variables });
+    let response: Response<T> = client.post_json(owner, &url, &body)?.into_json()?;
+    if let Some(error) = response.errors.first() {
+        bail!("{}", error.message);
+    }
+    response.data.context("no data in the response")
+}
+
+/// Split `~owner/name` into the user name and the repository name
+fn split(path: &str) -> Result<(&str, &str)> {
+    let (owner, name) = path
+        .split_once('/')
+        .with_context(|| format!("`{path}` has no owner"))?;
+    Ok((owner.trim_start_matches('~'), name))
+}
+
+fn find(client: &Client, owner: &str, name: &str) -> Result<Option<ApiRepository>> {
+    let data: UserData<RepositoryData> = query(
+        client,
+        Some(owner),
+        "query($username: String!, $name: String!) {
+            user(username: $username) { repository(name: $name) { name access } }
+        }",
+        json!({ "username": owner, "name": name }),
+    )?;
+    Ok(data.user.and_then(|user| user.repository))
+}
+
+fn clone_urls(host: &str, owner: &str, name: &str) -> Result<(Url, String)> {
+    Ok((
+        Url::parse(&format!("https://{host}/~{owner}/{name}"))?,
+        format!("git@{host}:~{owner}/{name}"),
+    ))
+}
+
+impl Forge for Sourcehut {
+    fn default_api(&self, host: &str) -> String {
+        format!("https://{host}/query")
+    }
+
+    fn repo_detail(&self, client: &Client, path: &str) -> Result<RepoDetail> {
+        let (owner, name) = split(path)?;
+        let repo = find(client, owner, name)?.with_context(|| format!("`{path}` not found"))?;
+        Ok(RepoDetail {
+            state: RepoState {
+                // sourcehut has no archived repositories
+                archived: false,
+                read_only: repo.access.as_deref() == Some("RO"),
+            },
+            size: None,
+            // sourcehut does not track forks
+            parent: None,
+        })
+    }
+
+    fn list_repos(&self, client: &Client, owner: &str) -> Result<Vec<RemoteRepo>> {
+        let username = owner.trim_start_matches('~');
+        let mut repos = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let data: UserData<RepositoriesData> = query(
+                client,
+                Some(owner),
+                "query($username: String!, $cursor: Cursor) {
+                    user(username: $username) {
+                        repositories(cursor: $cursor) { results { name } cursor }
+                    }
+                }",
+                json!({ "username": username, "cursor": cursor }),
+            )?;
+            let page = data
+                .user
+                .with_context(|| format!("user `{owner}` not found"))?
+                .repositories;
+            for repo in page.results {
+                repos.push(RemoteRepo {
+                    url: clone_urls(client.host(), username, &repo.name)?.0,
+                    archived: false,
+                });
+            }
+            match page.cursor {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+        Ok(repos)
+    }
+
+    fn create_repo(
+        &self,
+        client: &Client,
+        owner: Option<&str>,
+        name: &str,
+        private: bool,
+    ) -> Result<(Url, String)> {
+        let user = self.token_user(client)?;
+        let user = user.trim_start_matches('~');
+        if let Some(owner) = owner {
+            // there are no organizations, only users
+            ensure!(
+                owner.trim_start_matches('~').eq_ignore_ascii_case(user),
+                "repositories on {} can only be created for the user of the token",
+                client.host()
+            );
+        }
+        if find(client, user, name)?.is_none() {
+            let _: Value = query(
+                client,
+                owner,
+                "mutation($name: String!, $visibility: Visibility!) {
+                    createRepository(name: $name, visibility: $visibility) { name }
+                }",
+                json!({
+                    "name": name,
+                    "visibility": if private { "PRIVATE" } else { "PUBLIC" },
+                }),
+            )?;
+        }
+        clone_urls(client.host(), user, name)
+    }
+
+    fn token_user(&self, client: &Client) -> Result<String> {
+        let data: MeData = query(client, None, "query { me { canonicalName } }", json!({}))?;
+        Ok(data.me.canonical_name)
+    }
+}
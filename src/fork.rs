@@ -0,0 +1,145 @@
+use std::{path::Path, process::Command};
+
+use anyhow::{ensure, Context, Result};
+use git2::{ConfigLevel, Repository};
+
+use crate::{git, proxy, transport::Transport};
+
+/// The remote of the repository that `origin` was forked from, stored in the local config
+const KEY: &str = concat!(env!("CARGO_PKG_NAME"), ".upstreamRemote");
+const CONVENTIONAL_NAME: &str = "upstream";
+
+pub fn upstream_remote(repo: &Repository) -> Option<String> {
+    repo.config().ok()?.get_string(KEY).ok()
+}
+
+/// Get the upstream remote, recording the conventional `upstream` remote if none is recorded yet
+pub fn detect(repo: &Repository) -> Result<Option<String>> {
+    if let Some(name) = upstream_remote(repo) {
+        return Ok(Some(name));
+    }
+    if repo.find_remote(CONVENTIONAL_NAME).is_err() {
+        return Ok(None);
+    }
+    repo.config()?
+        .open_level(ConfigLevel::Local)?
+        .set_str(KEY, CONVENTIONAL_NAME)?;
+    Ok(Some(CONVENTIONAL_NAME.to_string()))
+}
+
+/// Add the repository `origin` was forked from as the conventional `upstream` remote, keeping an
+/// existing one, record it and fetch it
+pub fn add_upstream(path: &Path, url: &str) -> Result<()> {
+    let repo = Repository::open(path)?;
+    if repo.find_remote(CONVENTIONAL_NAME).is_err() {
+        repo.remote(CONVENTIONAL_NAME, url)?;
+    }
+    repo.config()?
+        .open_level(ConfigLevel::Local)?
+        .set_str(KEY, CONVENTIONAL_NAME)?;
+    let mut command = git::command(path);
+    proxy::apply(&mut command, &repo.config()?, url);
+    git::run_quiet(command.args(["fetch", "--quiet", CONVENTIONAL_NAME]))?;
+    git::run_quiet(git::command(path).args(["remote", "set-head", CONVENTIONAL_NAME, "--auto"]))
+}
+
+/// Result of syncing a fork
+pub enum Synced {
+    UpToDate(String),
+    FastForwarded(String, git2::Oid, git2::Oid),
+}
+
+/// Fetch the upstream remote, fast-forward the local default branch and push it to the fork
+///
+/// Returns `None` if the repository is not a fork.
+pub fn sync(path: &Path) -> Result<Option<Synced>> {
+    let repo = Repository::open(path)?;
+    let Some(upstream) = detect(&repo)? else {
+        return Ok(None);
+    };
+    let transport = Transport::load(&repo)?;
+    git::run_quiet(
+        remote_command(path, &repo, &transport, &upstream)?.args(["fetch", "--quiet", &upstream]),
+    )?;
+
+    let branch = match default_branch(&repo, &upstream) {
+        Some(branch) => branch,
+        None => {
+            git::run_quiet(git::command(path).args(["remote", "set-head", &upstream, "--auto"]))?;
+            default_branch(&repo, &upstream)
+                .with_context(|| format!("failed to get the default branch of `{upstream}`"))?
+        }
+    };
+    let upstream_ref = format!("refs/remotes/{upstream}/{branch}");
+    let target = repo.refname_to_id(&upstream_ref)?;
+
+    let origin = repo
+        .branch_upstream_remote(&format!("refs/heads/{branch}"))
+        .ok()
+        .and_then(|name| name.as_str().map(String::from))
+        .unwrap_or_else(|| "origin".into());
+
+    let mut synced = Synced::UpToDate(branch.clone());
+    if let Ok(mut local) = repo.find_reference(&format!("refs/heads/{branch}")) {
+        let current = local
+            .target()
+            .context("the default branch is a symbolic reference")?;
+        if current != target {
+            ensure!(
+                repo.graph_descendant_of(target, current)?,
+                "`{branch}` has diverged from `{upstream}/{branch}`"
+            );
+            let checked_out = repo
+                .head()
+                .ok()
+                .and_then(|head| head.name().map(String::from))
+                == local.name().map(String::from);
+            if checked_out {
+                git::run_quiet(git::command(path).args([
+                    "merge",
+                    "--ff-only",
+                    "--quiet",
+                    &upstream_ref,
+                ]))?;
+            } else {
+                local.set_target(target, &format!("{}: sync-fork", env!("CARGO_PKG_NAME")))?;
+            }
+            synced = Synced::FastForwarded(branch.clone(), current, target);
+        }
+    }
+    git::run_quiet(
+        remote_command(path, &repo, &transport, &origin)?
+            .args(["push", "--quiet", &origin])
+            .arg(format!("{upstream_ref}:refs/heads/{branch}")),
+    )?;
+    Ok(Some(synced))
+}
+
+/// A `git` command talking to `remote` through its proxy, authenticating as the clone did if it
+/// is the pinned remote
+fn remote_command(
+    path: &Path,
+    repo: &Repository,
+    transport: &Option<Transport>,
+    remote: &str,
+) -> Result<Command> {
+    let mut command = git::command(path);
+    if let Some(url) = repo.find_remote(remote)?.url() {
+        proxy::apply(&mut command, &repo.config()?, url);
+    }
+    if let Some(transport) = transport.as_ref().filter(|t| t.remote == remote) {
+        transport.apply(repo, &mut command)?;
+    }
+    Ok(command)
+}
+
+/// The branch `refs/remotes/<remote>/HEAD` points to
+pub fn default_branch(repo: &Repository, remote: &str) -> Option<String> {
+    let head = repo
+        .find_reference(&format!("refs/remotes/{remote}/HEAD"))
+        .ok()?;
+    let target = head.symbolic_target()?;
+    target
+        .strip_prefix(&format!("refs/remotes/{remote}/"))
+        .map(String::from)
+}
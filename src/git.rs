@@ -0,0 +1,142 @@
+use std::{
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use anyhow::{ensure, Context, Result};
+use url::Url;
+
+const TOKEN_VAR: &str = "GRM_TOKEN";
+
+/// Build a `git` command running in `dir`
+pub fn command(dir: &Path) -> Command {
+    let mut command = Command::new("git");
+    command.arg("-C").arg(dir);
+    command
+}
+
+/// Run a `git` command and fail if it exits unsuccessfully
+pub fn run(command: &mut Command) -> Result<()> {
+    let status = command.status().context("failed to run `git`")?;
+    ensure!(status.success(), "`git` exited with {status}");
+    Ok(())
+}
+
+/// Run a `git` command capturing its output, so that it does not interleave with other ones
+pub fn run_quiet(command: &mut Command) -> Result<()> {
+    let output = command
+        .stdin(Stdio::null())
+        .output()
+        .context("failed to run `git`")?;
+    ensure!(
+        output.status.success(),
+        "`git` exited with {}: {}",
+        output.status,
+        String::from_utf8_lossy(&output.stderr).trim()
+    );
+    Ok(())
+}
+
+/// Make `command` authenticate over SSH with `key` only
+pub fn use_ssh_key(command: &mut Command, key: &Path) {
+    command
+        .arg("-c")
+        .arg(format!("core.sshCommand={}", ssh_command(key)));
+}
+
+/// `core.sshCommand` authenticating with `key` only
+pub fn ssh_command(key: &Path) -> String {
+    format!(
+        "ssh -i {} -o IdentitiesOnly=yes",
+        shell_quote(&key.to_string_lossy())
+    )
+}
+
+/// Quote `arg` for the shell git runs `core.sshCommand` with
+fn shell_quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+/// Make `command` answer the credential prompts of `scope`, or of every URL, with `token`
+pub fn use_token(command: &mut Command, token: &str, scope: Option<&str>) {
+    let key = match scope {
+        Some(scope) => format!("credential.{scope}.helper"),
+        None => "credential.helper".into(),
+    };
+    // pass the token through the environment so that it does not appear in `ps`
+    command.arg("-c").arg(format!("{key}="));
+    command.arg("-c").arg(format!(
+        "{key}=!f() {{ echo username=x-access-token; echo password=\"${TOKEN_VAR}\"; }}; f"
+    ));
+    command.env(TOKEN_VAR, token);
+}
+
+/// Parse a remote URL, including the scp-like syntax `user@host:path` of SSH
+pub fn parse_remote_url(url: &str) -> Option<Url> {
+    let mut url = if url.contains("://") {
+        Url::parse(url).ok()?
+    } else {
+        let (user_host, path) = url.split_once(':')?;
+        if user_host.contains('/') || user_host.len() < 2 {
+            // a local path, possibly with a drive letter
+            return None;
+        }
+        Url::parse(&format!(
+            "ssh://{user_host}/{}",
+            path.trim_start_matches('/')
+        ))
+        .ok()?
+    };
+    normalize_host(&mut url);
+    Some(url)
+}
+
+/// Convert an internationalized host name into punycode, as the URL parser only does for
+/// `http(s)` URLs, so that every form of the name leads to the same directory
+pub fn normalize_host(url: &mut Url) {
+    let Some(host) = url.host_str() else {
+        return;
+    };
+    if host.is_ascii() && !host.contains('%') {
+        return;
+    }
+    let host = percent_encoding::percent_decode_str(host).decode_utf8_lossy();
+    if let Ok(host) = idna::domain_to_ascii(&host) {
+        _ = url.set_host(Some(&host));
+    }
+}
+
+#[cfg(test)]
+mod test_parse_remote_url {
+    use super::*;
+
+    #[test]
+    fn scp_like() -> Result<()> {
+        assert_eq!(
+            Some(Url::parse("ssh://git@github.com/foo/bar.git")?),
+            parse_remote_url("git@github.com:foo/bar.git"),
+        );
+        assert_eq!(
+            Some(Url::parse("https://github.com/foo/bar")?),
+            parse_remote_url("https://github.com/foo/bar"),
+        );
+        assert_eq!(None, parse_remote_url("/srv/git/bar.git"));
+        assert_eq!(None, parse_remote_url("C:/git/bar.git"));
+        assert_eq!(
+            Some(Url::parse("ssh://git@xn--bcher-kva.example/foo/bar.git")?),
+            parse_remote_url("git@bücher.example:foo/bar.git"),
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test_shell_quote {
+    use super::*;
+
+    #[test]
+    fn quotes() {
+        assert_eq!(shell_quote("/home/me/.ssh/id"), "'/home/me/.ssh/id'");
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+    }
+}
@@ -0,0 +1,99 @@
+//! Git directories kept apart from worktrees, e.g. worktrees on a fast disk and objects on bulk
+//! storage
+//!
+//! A worktree refers to its git directory with a `.git` file as `git clone --separate-git-dir`
+//! does, and the git directory refers back with `core.worktree`.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use crate::paths;
+
+/// Read `grm.gitDirRoot`, defaulting to the data directory
+pub fn get_git_dir_root(config: &git2::Config) -> Result<PathBuf> {
+    match config.get_path(concat!(env!("CARGO_PKG_NAME"), ".gitDirRoot")) {
+        Ok(root) => Ok(root),
+        Err(_) => Ok(paths::data_dir()?.join("git-dirs")),
+    }
+}
+
+/// Whether `grm.separateGitDir` is set to keep git directories of new clones apart
+pub fn is_enabled(config: &git2::Config) -> bool {
+    config
+        .get_bool(concat!(env!("CARGO_PKG_NAME"), ".separateGitDir"))
+        .unwrap_or(false)
+}
+
+/// Where the git directory of the repository `name` is kept
+pub fn git_dir_path(git_dir_root: &Path, name: &str) -> PathBuf {
+    if name.ends_with(".git") {
+        git_dir_root.join(name)
+    } else {
+        git_dir_root.join(format!("{name}.git"))
+    }
+}
+
+/// Move `<worktree>/.git` to `git_dir` and leave a `.git` file referring to it
+pub fn separate(worktree: &Path, git_dir: &Path) -> Result<()> {
+    anyhow::ensure!(!git_dir.exists(), "`{}` already exists", git_dir.display());
+    if let Some(parent) = git_dir.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    move_dir(&worktree.join(".git"), git_dir)?;
+    std::fs::write(
+        worktree.join(".git"),
+        format!("gitdir: {}\n", git_dir.display()),
+    )?;
+    git2::Config::open(&git_dir.join("config"))?
+        .set_str("core.worktree", &worktree.display().to_string())?;
+    Ok(())
+}
+
+/// The git directory of `worktree` if it is kept under `git_dir_root`
+///
+/// Git directories elsewhere, such as ones of `git worktree add`, are not managed by grm.
+pub fn find_separated(worktree: &Path, git_dir_root: &Path) -> Option<PathBuf> {
+    let content = std::fs::read_to_string(worktree.join(".git")).ok()?;
+    let git_dir = PathBuf::from(content.strip_prefix("gitdir:")?.trim());
+    git_dir.starts_with(git_dir_root).then_some(git_dir)
+}
+
+/// Move the git directory back into `worktree`, undoing [`separate`]
+pub fn join(worktree: &Path, git_dir: &Path) -> Result<()> {
+    let mut config = git2::Config::open(&git_dir.join("config"))?;
+    if let Err(err) = config.remove("core.worktree") {
+        if err.code() != git2::ErrorCode::NotFound {
+            return Err(err.into());
+        }
+    }
+    drop(config);
+    std::fs::remove_file(worktree.join(".git"))?;
+    move_dir(git_dir, &worktree.join(".git"))
+}
+
+/// Rename a directory, copying it where it is on another file system
+fn move_dir(from: &Path, to: &Path) -> Result<()> {
+    match std::fs::rename(from, to) {
+        Err(err) if err.kind() == std::io::ErrorKind::CrossesDevices => {
+            copy_dir(from, to).with_context(|| format!("failed to copy `{}`", from.display()))?;
+            std::fs::remove_dir_all(from)?;
+            Ok(())
+        }
+        result => Ok(result?),
+    }
+}
+
+fn copy_dir(from: &Path, to: &Path) -> std::io::Result<()> {
+    std::fs::create_dir(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            std::fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
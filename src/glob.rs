@@ -0,0 +1,95 @@
+//! Glob patterns selecting repositories by their path relative to the root directory
+//!
+//! `*` and `?` match within a path component and `**` matches any number of components.
+//! Like plain queries of `remove` and friends, a pattern may match the trailing components of
+//! the path, so `*/infra-*` selects `github.com/org/infra-web`. Matching is case-insensitive.
+
+/// Whether `s` should be treated as a pattern rather than a literal path
+pub fn is_pattern(s: &str) -> bool {
+    s.contains(['*', '?'])
+}
+
+/// `name` is a path relative to the root directory, separated by `/`
+pub fn matches(pattern: &str, name: &str) -> bool {
+    let (pattern, name) = (components(pattern), components(name));
+    (0..=name.len()).any(|start| matches_components(&pattern, &name[start..]))
+}
+
+/// Whether the gitignore-style `pattern` matches `name` or one of its parent directories
+///
+/// A pattern starting with `/` only matches from the root directory.
+pub fn excludes(pattern: &str, name: &str) -> bool {
+    let anchored = pattern.starts_with('/');
+    let (pattern, name) = (components(pattern), components(name));
+    (1..=name.len()).any(|end| {
+        let dirs = &name[..end];
+        if anchored {
+            matches_components(&pattern, dirs)
+        } else {
+            (0..end).any(|start| matches_components(&pattern, &dirs[start..]))
+        }
+    })
+}
+
+fn components(path: &str) -> Vec<&str> {
+    path.split('/').filter(|c| !c.is_empty()).collect()
+}
+
+fn matches_components(pattern: &[&str], name: &[&str]) -> bool {
+    match (pattern.split_first(), name.split_first()) {
+        (None, None) => true,
+        (Some((&"**", rest)), _) => {
+            matches_components(rest, name)
+                || (!name.is_empty() && matches_components(pattern, &name[1..]))
+        }
+        (Some((p, pattern)), Some((n, name))) => {
+            let p: Vec<_> = p.to_lowercase().chars().collect();
+            let n: Vec<_> = n.to_lowercase().chars().collect();
+            matches_component(&p, &n) && matches_components(pattern, name)
+        }
+        _ => false,
+    }
+}
+
+fn matches_component(pattern: &[char], name: &[char]) -> bool {
+    match (pattern.split_first(), name.split_first()) {
+        (None, None) => true,
+        (Some(('*', rest)), _) => {
+            matches_component(rest, name)
+                || (!name.is_empty() && matches_component(pattern, &name[1..]))
+        }
+        (Some(('?', pattern)), Some((_, name))) => matches_component(pattern, name),
+        (Some((p, pattern)), Some((n, name))) => p == n && matches_component(pattern, name),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test_matches {
+    use super::*;
+
+    #[test]
+    fn components() {
+        assert!(matches("github.com/myorg/*", "github.com/myorg/api"));
+        assert!(!matches("github.com/myorg/*", "github.com/other/api"));
+        assert!(matches("*/infra-*", "github.com/org/Infra-web"));
+        assert!(!matches("infra-*", "github.com/infra-org/web"));
+        assert!(matches("github.com/**/web", "github.com/a/b/web"));
+        assert!(matches("gitlab.com/?/*", "gitlab.com/a/b"));
+        assert!(!matches("gitlab.com/?/*", "gitlab.com/ab/c"));
+    }
+}
+
+#[cfg(test)]
+mod test_excludes {
+    use super::*;
+
+    #[test]
+    fn parent_directories() {
+        assert!(excludes("mirrors", "mirrors/github.com/a/b"));
+        assert!(excludes("vendor-*", "github.com/vendor-x/b"));
+        assert!(excludes("/github.com/big", "github.com/big/mono"));
+        assert!(!excludes("/big", "github.com/big/mono"));
+        assert!(!excludes("mirrors", "github.com/a/mirrors-b"));
+    }
+}
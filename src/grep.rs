@@ -0,0 +1,68 @@
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use anyhow::Result;
+use git2::Repository;
+use regex::Regex;
+
+use crate::{foreach, root};
+
+/// Error of a search matching no line, which exits with 1 like grep without printing anything
+#[derive(Debug)]
+pub struct NoMatch;
+
+impl std::fmt::Display for NoMatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "no lines matched")
+    }
+}
+
+impl std::error::Error for NoMatch {}
+
+/// Search tracked files of each repository and return the number of matched lines
+pub fn run(roots: &[PathBuf], paths: Vec<PathBuf>, regex: &Regex, jobs: usize) -> usize {
+    let count = AtomicUsize::new(0);
+    foreach::parallel(paths, jobs, |path| {
+        let name = root::relative(roots, &path);
+        match search(&path, regex) {
+            Ok(matches) => {
+                count.fetch_add(matches.len(), Ordering::Relaxed);
+                // print a whole repository at once so that lines are not interleaved
+                let mut stdout = std::io::stdout().lock();
+                for (file, line_number, line) in matches {
+                    _ = writeln!(stdout, "{name}:{file}:{line_number}:{line}");
+                }
+            }
+            Err(err) => info!("{name}: {err}"),
+        }
+    });
+    count.into_inner()
+}
+
+/// Lines of tracked files matching `regex` as file names, line numbers and the lines
+pub fn search(path: &Path, regex: &Regex) -> Result<Vec<(String, usize, String)>> {
+    let repo = Repository::open(path)?;
+    let Some(workdir) = repo.workdir() else {
+        return Ok(Vec::new());
+    };
+    let mut matches = Vec::new();
+    for entry in repo.index()?.iter() {
+        let file = String::from_utf8_lossy(&entry.path).into_owned();
+        let Ok(content) = std::fs::read(workdir.join(&file)) else {
+            continue;
+        };
+        if content.iter().take(8000).any(|&b| b == 0) {
+            continue;
+        }
+        let content = String::from_utf8_lossy(&content);
+        for (i, line) in content.lines().enumerate() {
+            if regex.is_match(line) {
+                matches.push((file.clone(), i + 1, line.to_string()));
+            }
+        }
+    }
+    Ok(matches)
+}
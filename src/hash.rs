@@ -0,0 +1,18 @@
+/// 64-bit FNV-1a, for names of files under the cache directory that, unlike the ones of
+/// `DefaultHasher`, stay the same in every build of grm
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod test_fnv1a {
+    use super::*;
+
+    #[test]
+    fn known_values() {
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
+}
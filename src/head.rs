@@ -0,0 +1,62 @@
+use std::path::Path;
+
+use anyhow::Result;
+use chrono::{DateTime, FixedOffset, TimeZone};
+use git2::Repository;
+
+/// What HEAD of a repository points to
+pub struct Head {
+    /// `None` if HEAD is detached
+    pub branch: Option<String>,
+    /// `None` if the branch has no commits yet
+    pub commit: Option<Commit>,
+}
+
+pub struct Commit {
+    pub short_id: String,
+    pub time: DateTime<FixedOffset>,
+    pub summary: String,
+}
+
+impl Head {
+    pub fn read(path: &Path) -> Result<Self> {
+        let repo = Repository::open(path)?;
+        let branch = if repo.head_detached()? {
+            None
+        } else {
+            let head = repo.find_reference("HEAD")?;
+            head.symbolic_target()
+                .map(|target| target.trim_start_matches("refs/heads/").to_string())
+        };
+        let commit = match repo.head() {
+            Ok(head) => Some(Commit::new(&head.peel_to_commit()?)?),
+            Err(err) if err.code() == git2::ErrorCode::UnbornBranch => None,
+            Err(err) => return Err(err.into()),
+        };
+        Ok(Self { branch, commit })
+    }
+}
+
+impl Commit {
+    pub fn new(commit: &git2::Commit) -> Result<Self> {
+        Ok(Self {
+            short_id: commit
+                .as_object()
+                .short_id()?
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            time: to_datetime(commit.time()),
+            summary: commit.summary().unwrap_or_default().to_string(),
+        })
+    }
+}
+
+pub fn to_datetime(time: git2::Time) -> DateTime<FixedOffset> {
+    let offset = FixedOffset::east_opt(time.offset_minutes() * 60)
+        .unwrap_or_else(|| FixedOffset::east_opt(0).unwrap());
+    offset
+        .timestamp_opt(time.seconds(), 0)
+        .single()
+        .unwrap_or_default()
+}
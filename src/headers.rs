@@ -0,0 +1,136 @@
+//! Extra HTTP headers required by some servers, from git's `http.<url>.extraHeader` and the
+//! output of `grm.<host>.headerCommand`, e.g. an SSO helper printing `Authorization: Bearer ...`
+
+use std::{
+    collections::HashMap,
+    process::{Command, Stdio},
+    sync::{LazyLock, Mutex},
+};
+
+use anyhow::{ensure, Context, Result};
+use url::Url;
+
+/// Outputs of header commands by host, not to run them for each repository
+static OUTPUTS: LazyLock<Mutex<HashMap<String, Vec<String>>>> = LazyLock::new(Mutex::default);
+
+/// Headers printed by `grm.<host>.headerCommand`, one per line, run at most once per host
+pub fn generated(config: &git2::Config, host: &str) -> Result<Vec<String>> {
+    let key = format!("{}.{host}.headerCommand", env!("CARGO_PKG_NAME"));
+    let Ok(command) = config.get_string(&key) else {
+        return Ok(Vec::new());
+    };
+    let mut outputs = OUTPUTS.lock().unwrap();
+    if let Some(headers) = outputs.get(host) {
+        return Ok(headers.clone());
+    }
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(&command)
+        .stdin(Stdio::null())
+        .stderr(Stdio::inherit())
+        .output()
+        .with_context(|| format!("failed to run the header command of {host}"))?;
+    ensure!(
+        output.status.success(),
+        "the header command of {host} exited with {}",
+        output.status
+    );
+    let headers: Vec<String> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect();
+    outputs.insert(host.to_string(), headers.clone());
+    Ok(headers)
+}
+
+/// Values of `http.extraHeader` and `http.<url>.extraHeader` applying to `url`
+#[cfg_attr(
+    not(any(feature = "backend-git2", feature = "forge")),
+    allow(dead_code)
+)]
+pub fn configured(config: &git2::Config, url: &Url) -> Result<Vec<String>> {
+    let mut headers = Vec::new();
+    let mut entries = config.entries(Some(r"^http\..*extraheader$"))?;
+    while let Some(entry) = entries.next() {
+        let entry = entry?;
+        let (Some(name), Some(value)) = (entry.name(), entry.value()) else {
+            continue;
+        };
+        let applies = match name
+            .strip_prefix("http.")
+            .and_then(|name| name.strip_suffix(".extraheader"))
+        {
+            Some(prefix) => matches(prefix, url),
+            None => name == "http.extraheader",
+        };
+        if applies {
+            // an empty value resets the list like git does
+            if value.is_empty() {
+                headers.clear();
+            } else {
+                headers.push(value.to_string());
+            }
+        }
+    }
+    Ok(headers)
+}
+
+/// Whether the `http.<url>` section for `prefix` applies to `url`
+fn matches(prefix: &str, url: &Url) -> bool {
+    let Ok(prefix) = Url::parse(prefix) else {
+        return false;
+    };
+    if prefix.scheme() != url.scheme()
+        || prefix.host_str() != url.host_str()
+        || prefix.port_or_known_default() != url.port_or_known_default()
+    {
+        return false;
+    }
+    let base = prefix.path().trim_end_matches('/');
+    let path = url.path();
+    path.strip_prefix(base)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// Send `headers` with requests of a `git` command to the server of `url`
+///
+/// They are passed through the environment rather than `-c`, so that they do not appear in `ps`.
+pub fn set_env(command: &mut Command, url: &Url, headers: &[String]) {
+    if headers.is_empty() {
+        return;
+    }
+    // keep entries set by the caller or by earlier calls for other remotes
+    let count = command
+        .get_envs()
+        .find(|(key, _)| *key == "GIT_CONFIG_COUNT")
+        .and_then(|(_, value)| value.map(|value| value.to_string_lossy().into_owned()))
+        .or_else(|| std::env::var("GIT_CONFIG_COUNT").ok());
+    let start: usize = count.and_then(|count| count.parse().ok()).unwrap_or(0);
+    let mut base = format!("{}://{}", url.scheme(), url.host_str().unwrap_or_default());
+    if let Some(port) = url.port() {
+        base.push_str(&format!(":{port}"));
+    }
+    let key = format!("http.{base}/.extraHeader");
+    for (i, header) in headers.iter().enumerate() {
+        command.env(format!("GIT_CONFIG_KEY_{}", start + i), &key);
+        command.env(format!("GIT_CONFIG_VALUE_{}", start + i), header);
+    }
+    command.env("GIT_CONFIG_COUNT", (start + headers.len()).to_string());
+}
+
+#[cfg(test)]
+mod test_matches {
+    use super::*;
+
+    #[test]
+    fn prefixes() {
+        let url = Url::parse("https://git.corp/team/repo.git").unwrap();
+        assert!(matches("https://git.corp", &url));
+        assert!(matches("https://git.corp/team/", &url));
+        assert!(!matches("https://git.corp/tea", &url));
+        assert!(!matches("http://git.corp", &url));
+        assert!(!matches("https://other.corp", &url));
+    }
+}
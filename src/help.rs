@@ -0,0 +1,777 @@
+use std::io::Write;
+
+use anyhow::Result;
+
+/// A help page that does not belong to a command, shown by `grm help <topic>`
+pub struct Topic {
+    pub name: &'static str,
+    pub summary: &'static str,
+    pub body: &'static str,
+}
+
+pub const TOPICS: &[Topic] = &[
+    Topic {
+        name: "spec",
+        summary: "How repository specs given to `get` and `new` are resolved",
+        body: "\
+A spec is completed into an origin URL depending on the number of slashes:
+
+  NAME              <user>/NAME on the default host, where <user> is the username
+  OWNER/NAME        OWNER/NAME on the default host, `grm.host` or github.com
+  HOST/OWNER/NAME   https://HOST/OWNER/NAME, or ssh://git@HOST/OWNER/NAME with --ssh
+  URL               used as is, e.g. https://example.com/a/b.git or ssh://git@host/a/b
+
+The username is read from GRM_USER, `grm.profile.<profile>.user`, `user.name` and the
+login name in this order. --ssh is implied by GRM_PROTOCOL=ssh, the protocol of the
+profile or `grm.protocol`.
+
+Commands taking an existing repository, such as `archive` and `remove`, also accept a
+path relative to the root, or trailing components of it like `foo` or `owner/foo`.
+When several repositories match, an interactive prompt is shown, and in a
+non-interactive session grm exits with status 3 listing the candidates.",
+    },
+    Topic {
+        name: "config",
+        summary: "Configuration keys read from git config",
+        body: "\
+grm reads git config, so keys can be set with `git config --global grm.<key> <value>`.
+
+  grm.root                   Root directory of repositories (GRM_ROOT overrides it)
+  grm.protocol               Protocol used to complete specs: https or ssh
+  grm.host                   Host assumed for OWNER/NAME specs, github.com by default
+  grm.owner                  Owners compared by `list --remote` (multi-valued)
+  grm.exclude                gitignore-style pattern of repositories to skip (multi-valued)
+  grm.scanDepth              How deep to look for repositories under a root, 5 by default
+  grm.nested                 Also find repositories inside other repositories
+  grm.separateGitDir         Keep git directories of new clones apart from their worktrees
+  grm.gitDirRoot             Where such git directories are kept, e.g. on bulk storage
+  grm.objectCache            Let clones borrow objects from a shared cache of each host
+  grm.confirmSizeMb          Ask before cloning repositories larger than this, per the forge API
+  grm.protectedBranch        Glob pattern of branches `branches --delete` keeps (multi-valued)
+  grm.backend                Clone backend: git2, git or gix
+  grm.defaultBranch          Initial branch of `new` if init.defaultBranch is unset
+  grm.readOnly               Disable commands modifying repositories
+  grm.archiveDir             Where `archive` stores repositories
+  grm.historyFile            Where mutating operations are logged
+  grm.trashDir               Where `remove` moves repositories
+  grm.trashRetentionDays     Days to keep removed repositories, 0 keeps them forever
+  grm.notifyAfter            Notify when a command runs longer than this many seconds
+  grm.vault                  Where `grm token` stores encrypted tokens
+  grm.autoUpstream           Add the parent of a fork as `upstream` when `get` clones it, per the
+                             forge API
+  grm.pinned                 Repositories pinned by `grm pin` (multi-valued)
+  grm.alias.<name>           Definition of an alias expanded by `grm <name>`
+  grm.notifyCommand          Command run to notify, with GRM_SUMMARY, GRM_BODY and GRM_STATUS
+  grm.envTrust               Whether `get` runs `direnv allow` and `mise trust` in new clones:
+                             never (the default), ask or always
+  grm.invalidNames           What to do with repository names invalid on some filesystems, such as
+                             `aux` or `a:b` on Windows: keep, refuse or replace, remembering the
+                             original name; keep by default except on Windows
+  grm.invalidNameReplacement String replacing the invalid parts, `_` by default
+  grm.workspaceCommand       Command scaffolding `new --workspace` in the new repository instead
+                             of a cargo workspace, with GRM_PATH and GRM_MEMBERS
+
+  grm.<host>.root            Root directory of repositories on the host
+  grm.<host>.forge           Kind of the forge: github, gitlab, gitea or sourcehut, guessed by
+                             default from the host name
+  grm.<host>.api             Base URL of the forge API
+  grm.<host>.token           Token of the forge used by the API and HTTPS clones
+  grm.<host>/<owner>.token   Token used instead for repositories of the owner
+  grm.<host>.proxy           Proxy URL for the host, or an empty string to bypass proxies
+  grm.<host>.caBundle        CA certificates to verify the host with, e.g. a private CA
+  grm.<host>.hostKey         Expected SSH host key fingerprint (SHA256:...) for git2 clones
+  grm.<host>.objectCache     Let clones from the host borrow objects from a shared cache
+  grm.<host>.pushProtocol    Set the push URL to ssh or https
+  grm.<host>.headerCommand   Command printing HTTP headers for clones, fetches and API calls
+  grm.<host>.user            Commit author name of repositories on the host
+  grm.<host>.email           Commit author email of repositories on the host
+  grm.<host>.signingKey      Key signing commits of new repositories, an SSH key or a GPG key ID
+
+  grm.profile.<name>.root, .user, .protocol, .token, .sshKey, .name, .email, .signingKey,
+                             .host
+                             Settings selected with --profile, or by host with .host
+
+Keys stored in the local config of each repository:
+
+  grm.tag                    Tags attached by `grm tag` (multi-valued)
+  grm.upstreamRemote         Remote of the repository a fork was made from
+  grm.transport.remote, .protocol, .profile, .sshKey, .token
+                             How `get` cloned the repository, which `update` and `sync-fork`
+                             keep using for that remote until `remote set-protocol` rewrites it",
+    },
+];
+
+/// Examples shown at the end of `--help` of each subcommand
+const EXAMPLES: &[(&str, &[(&str, &str)])] = &[
+    (
+        "root",
+        &[("cd \"$(grm root)\"", "go to the root directory")],
+    ),
+    (
+        "doctor",
+        &[
+            ("grm doctor", "check which root directory is used"),
+            (
+                "grm doctor --mark",
+                "mark the active root as managed by grm",
+            ),
+        ],
+    ),
+    (
+        "list",
+        &[
+            (
+                "grm list -v",
+                "show the branch and the last commit of each repository",
+            ),
+            (
+                "grm list -t work -q api",
+                "repositories tagged `work` with `api` in the path",
+            ),
+            (
+                "grm list --format '{host}/{owner}/{name}\\t{path}'",
+                "custom columns",
+            ),
+            (
+                "grm list --exclude mirrors --exclude '*/vendor-*'",
+                "skip repositories like `grm.exclude` does",
+            ),
+            (
+                "grm list --nested --json",
+                "include nested repositories with their parents",
+            ),
+            ("grm list --tree", "group repositories by host and owner"),
+            (
+                "grm list -v --check-upstream",
+                "mark repositories archived or read-only on the forge",
+            ),
+            (
+                "grm list --remote --owner rust-lang",
+                "compare with repositories on the forge",
+            ),
+        ],
+    ),
+    (
+        "get",
+        &[
+            (
+                "grm get rust-lang/rust",
+                "clone https://github.com/rust-lang/rust",
+            ),
+            ("grm get --ssh gitlab.com/owner/repo", "clone with SSH"),
+            (
+                "grm get --copy owner/repo",
+                "clone and copy the path to paste into a GUI app",
+            ),
+            (
+                "grm get dev.azure.com/org/project/repo",
+                "clone from Azure DevOps into dev.azure.com/org/project/repo",
+            ),
+            (
+                "grm get --shallow-since '1 year ago' owner/huge-repo",
+                "clone only the history of the last year",
+            ),
+            (
+                "grm get -f repos.txt",
+                "clone every repository listed in the file",
+            ),
+            (
+                "gh repo list --json url -q '.[].url' | grm get --stdin",
+                "clone every repository printed by another command",
+            ),
+            (
+                "grm get -f repos.txt --events jsonl",
+                "report each clone as a JSON line for CI",
+            ),
+            (
+                "cd \"$(grm get foo)\"",
+                "clone your repository `foo` and enter it",
+            ),
+            (
+                "grm get --json owner/repo",
+                "print the result for editor plugins",
+            ),
+            (
+                "grm get --devcontainer owner/repo",
+                "clone and start its dev container",
+            ),
+            (
+                "grm get me/fork --reference",
+                "borrow objects from the original repository cloned before",
+            ),
+            (
+                "grm get owner/monorepo --sparse tools/cli",
+                "check out only one directory",
+            ),
+        ],
+    ),
+    (
+        "new",
+        &[
+            ("grm new my-tool", "create <root>/github.com/<user>/my-tool"),
+            (
+                "grm new --here --move owner/repo",
+                "initialize the current directory and move it",
+            ),
+            (
+                "mkdir my-tool && cd my-tool && grm new --move",
+                "name the repository after the empty directory",
+            ),
+            (
+                "grm new --raw scratch/notes",
+                "create <root>/scratch/notes without an origin",
+            ),
+            (
+                "grm new myproj --workspace member-a member-b",
+                "scaffold a cargo workspace with two crates",
+            ),
+        ],
+    ),
+    (
+        "archive",
+        &[(
+            "grm archive old-project",
+            "pack the repository and remove its working copy",
+        )],
+    ),
+    (
+        "restore",
+        &[(
+            "grm restore old-project",
+            "bring the archived repository back",
+        )],
+    ),
+    (
+        "remove",
+        &[
+            ("grm remove foo", "move the repository to the trash"),
+            ("grm remove --permanent foo", "delete the repository"),
+            (
+                "grm remove --force 'github.com/old-org/*'",
+                "remove every repository of the owner",
+            ),
+            (
+                "grm remove --file old.txt",
+                "remove the repositories listed in the file",
+            ),
+        ],
+    ),
+    (
+        "devcontainer",
+        &[(
+            "grm devcontainer up owner/repo",
+            "start the dev container of a repository",
+        )],
+    ),
+    (
+        "bundle",
+        &[
+            (
+                "grm bundle create owner/repo -o /media/usb/repo.bundle",
+                "write a repository to a USB drive",
+            ),
+            (
+                "grm bundle apply /media/usb/repo.bundle owner/repo",
+                "clone it on another machine",
+            ),
+        ],
+    ),
+    (
+        "trash",
+        &[
+            ("grm trash list", "show removed repositories"),
+            (
+                "grm trash restore owner/foo",
+                "restore the latest one removed from the path",
+            ),
+            (
+                "grm trash empty --expired",
+                "delete repositories kept longer than the retention",
+            ),
+        ],
+    ),
+    (
+        "du",
+        &[("grm du", "show the disk usage of each repository")],
+    ),
+    (
+        "license",
+        &[
+            ("grm license", "show the license of each repository"),
+            (
+                "grm license --missing -t work",
+                "work repositories without a license",
+            ),
+        ],
+    ),
+    (
+        "diff-config",
+        &[
+            (
+                "grm diff-config",
+                "show repositories with a different identity, push URL or signing",
+            ),
+            ("grm diff-config --fix", "write the expected values"),
+        ],
+    ),
+    (
+        "verify",
+        &[
+            ("grm verify", "check every repository"),
+            (
+                "grm verify -q github.com/rust-lang",
+                "check repositories of an owner",
+            ),
+        ],
+    ),
+    (
+        "stats",
+        &[
+            ("grm stats", "summarize every repository"),
+            (
+                "grm stats -t work -n 10",
+                "show the top 10 of work repositories",
+            ),
+        ],
+    ),
+    (
+        "branches",
+        &[
+            (
+                "grm branches --merged",
+                "print branches merged into the default branch",
+            ),
+            (
+                "grm branches --merged --delete -q github.com/myorg",
+                "delete them in the repositories of the organization",
+            ),
+        ],
+    ),
+    (
+        "prune",
+        &[
+            (
+                "grm prune --stale 180d --suggest",
+                "print repositories untouched for half a year",
+            ),
+            (
+                "grm prune --stale 1y",
+                "ask whether to remove each one untouched for a year",
+            ),
+        ],
+    ),
+    (
+        "contributors",
+        &[
+            (
+                "grm contributors --months 12",
+                "tell projects you work on from references",
+            ),
+            (
+                "grm contributors owner/repo",
+                "print who commits to the repository",
+            ),
+        ],
+    ),
+    (
+        "status",
+        &[
+            (
+                "grm status",
+                "find detached HEADs, branches without upstreams and unpushed work",
+            ),
+            (
+                "grm status --all -q github.com/me",
+                "report every repository of yours",
+            ),
+        ],
+    ),
+    (
+        "stashes",
+        &[
+            ("grm stashes", "find work forgotten in stashes"),
+            (
+                "grm stashes --json | jq -r .repo | sort -u",
+                "print repositories with stashes",
+            ),
+        ],
+    ),
+    (
+        "todo",
+        &[
+            ("grm todo", "print TODO comments of every repository"),
+            (
+                "grm todo -k FIXME -q github.com/myorg",
+                "print FIXME comments of the organization",
+            ),
+        ],
+    ),
+    (
+        "replicate",
+        &[
+            (
+                "grm replicate owner/repo gitea.internal/mirrors",
+                "create the repository on Gitea and push everything to it",
+            ),
+            (
+                "grm replicate owner/repo git@backup.local:owner/repo.git",
+                "push to an existing repository",
+            ),
+        ],
+    ),
+    (
+        "update",
+        &[
+            (
+                "grm update --prune",
+                "fetch every repository and prune gone branches",
+            ),
+            (
+                "grm update -t work --rebase --autostash",
+                "also rebase diverged branches",
+            ),
+            (
+                "grm update --remote upstream",
+                "fetch only the `upstream` remotes",
+            ),
+            (
+                "grm update -f repos.txt",
+                "update only the repositories listed in the file",
+            ),
+            (
+                "grm update --events jsonl | my-dashboard",
+                "stream progress as JSON lines for another UI",
+            ),
+            (
+                "grm update -q 'github.com/myorg/*'",
+                "update repositories matching a glob pattern",
+            ),
+        ],
+    ),
+    (
+        "sync-fork",
+        &[
+            (
+                "grm sync-fork me/fork",
+                "fast-forward the fork from `upstream` and push it",
+            ),
+            (
+                "grm sync-fork --all",
+                "sync every repository with an upstream remote",
+            ),
+        ],
+    ),
+    (
+        "gc",
+        &[
+            ("grm gc foo", "run `git gc` in the repository"),
+            (
+                "grm gc --all --prune",
+                "run `git gc --prune=now` everywhere",
+            ),
+        ],
+    ),
+    (
+        "foreach",
+        &[
+            (
+                "grm foreach -- git status --short",
+                "run a command in every repository",
+            ),
+            (
+                "grm foreach -- tar czf /backup/{owner}-{name}.tar.gz .",
+                "use values of each repository in the command",
+            ),
+            (
+                "grm foreach --fail-fast --script ./migrate.sh",
+                "run a script, stopping at the first failure",
+            ),
+            (
+                "grm foreach --failed -- make",
+                "retry only the repositories failed last time",
+            ),
+            (
+                "grm foreach -j 1 -t work -- make",
+                "run serially in repositories tagged `work`",
+            ),
+            (
+                "grm foreach --events jsonl -- make",
+                "stream each output line and result as JSON lines",
+            ),
+        ],
+    ),
+    (
+        "grep",
+        &[
+            (
+                "grm grep 'fn main'",
+                "search tracked files of every repository",
+            ),
+            (
+                "grm grep -iF todo -q api",
+                "case-insensitive literal search",
+            ),
+        ],
+    ),
+    (
+        "pin",
+        &[
+            ("grm pin foo/bar", "show `foo/bar` first in `grm list`"),
+            ("grm update --pinned", "update only the pinned repositories"),
+        ],
+    ),
+    ("unpin", &[("grm unpin foo/bar", "stop pinning `foo/bar`")]),
+    (
+        "tag",
+        &[
+            (
+                "grm tag add foo work",
+                "tag the repository `foo` with `work`",
+            ),
+            ("grm tag list", "show every tag in use"),
+        ],
+    ),
+    (
+        "note",
+        &[
+            (
+                "grm note set foo/bar \"candidate for archive\"",
+                "leave a note shown by `grm list -v`",
+            ),
+            ("grm note list", "show every note"),
+        ],
+    ),
+    (
+        "watch",
+        &[
+            ("grm watch &", "keep the index fresh in the background"),
+            ("grm list --cached", "list repositories from the index"),
+        ],
+    ),
+    (
+        "completion-data",
+        &[(
+            "cd \"$(grm completion-data | fzf -d '\\t' --with-nth 1 | cut -f 2)\"",
+            "pick a repository with fzf and enter it",
+        )],
+    ),
+    (
+        "serve",
+        &[
+            (
+                "grm serve &",
+                "listen on the socket shown by `grm paths socket`",
+            ),
+            (
+                "echo '{\"id\":1,\"method\":\"list\"}' | nc -U \"$(grm paths socket)\"",
+                "list repositories through the socket",
+            ),
+        ],
+    ),
+    (
+        "paths",
+        &[
+            ("grm paths", "show where grm keeps its files"),
+            ("cd \"$(grm paths trash)\"", "go to the trash directory"),
+        ],
+    ),
+    (
+        "history",
+        &[("grm history -n 10", "show the last 10 operations")],
+    ),
+    (
+        "init",
+        &[
+            ("grm init", "answer a few questions to get started"),
+            (
+                "grm init --root ~/src --protocol ssh",
+                "set up without questions",
+            ),
+        ],
+    ),
+    (
+        "shell-init",
+        &[
+            (
+                "eval \"$(grm shell-init bash)\"",
+                "add `gcd`, Ctrl-G and completions to bash",
+            ),
+            ("grm shell-init fish | source", "the same for fish"),
+        ],
+    ),
+    (
+        "open",
+        &[
+            ("grm open owner/repo", "open the repository on its forge"),
+            (
+                "grm open --file src/main.rs --line 42",
+                "open a line of a file in the current repository",
+            ),
+            (
+                "grm open --file README.md --commit -p",
+                "print a permanent link to share",
+            ),
+        ],
+    ),
+    (
+        "url",
+        &[
+            ("grm url", "print the URL of the current commit"),
+            (
+                "grm url HEAD~2 src/main.rs --line 10",
+                "link to a line of a file two commits ago",
+            ),
+        ],
+    ),
+    (
+        "remote",
+        &[
+            (
+                "grm remote set-protocol ssh --host github.com",
+                "switch origins on GitHub to SSH",
+            ),
+            (
+                "grm remote set-protocol https -n -q 'github.com/myorg/*'",
+                "preview switching the repositories of an organization to HTTPS",
+            ),
+        ],
+    ),
+    (
+        "token",
+        &[
+            (
+                "grm token set gitlab.example.com",
+                "encrypt and store a token",
+            ),
+            (
+                "grm token set github.com/myorg",
+                "store a token used only for the organization",
+            ),
+            (
+                "GRM_PASSPHRASE=... grm list --remote",
+                "use stored tokens without a prompt",
+            ),
+        ],
+    ),
+    (
+        "whoami",
+        &[
+            ("grm whoami", "check credentials of every configured host"),
+            (
+                "grm whoami --no-ssh gitlab.example.com",
+                "check only the API token of a host",
+            ),
+            (
+                "grm whoami && grm update",
+                "fetch everything only if the credentials work",
+            ),
+        ],
+    ),
+    (
+        "project",
+        &[
+            (
+                "grm project add services/payment pay",
+                "name a directory of the repository in the current directory",
+            ),
+            (
+                "cd \"$(grm list -l --projects -q pay)\"",
+                "jump to the project",
+            ),
+        ],
+    ),
+    (
+        "alias",
+        &[
+            (
+                "grm alias set up \"update --prune\"",
+                "make `grm up` run `grm update --prune`",
+            ),
+            ("grm alias list", "show aliases"),
+        ],
+    ),
+    (
+        "help",
+        &[
+            ("grm help spec", "show how specs are resolved"),
+            ("grm help tag add", "show help of a subcommand"),
+            ("grm help --man > grm.1", "write the man page"),
+        ],
+    ),
+];
+
+pub fn examples(command: &str) -> Option<&'static [(&'static str, &'static str)]> {
+    EXAMPLES
+        .iter()
+        .find(|(name, _)| *name == command)
+        .map(|(_, examples)| *examples)
+}
+
+fn format_examples(examples: &[(&str, &str)]) -> String {
+    let width = examples.iter().map(|(cmd, _)| cmd.len()).max().unwrap_or(0);
+    let mut text = String::from("Examples:");
+    for (cmd, description) in examples {
+        text.push_str(&format!("\n  {cmd:<width$}  # {description}"));
+    }
+    text
+}
+
+/// Attach examples and the list of topics to the help of `command`
+pub fn augment(command: clap::Command) -> clap::Command {
+    let mut topics = String::from("Topics (see `grm help <topic>`):");
+    for topic in TOPICS {
+        topics.push_str(&format!("\n  {:<8}{}", topic.name, topic.summary));
+    }
+    let mut command = command.after_long_help(topics);
+    let names: Vec<_> = command
+        .get_subcommands()
+        .map(|sub| sub.get_name().to_string())
+        .collect();
+    for name in names {
+        if let Some(examples) = examples(&name) {
+            command =
+                command.mut_subcommand(name, |sub| sub.after_long_help(format_examples(examples)));
+        }
+    }
+    command
+}
+
+pub fn topic(name: &str) -> Option<&'static Topic> {
+    TOPICS.iter().find(|topic| topic.name == name)
+}
+
+/// Write a man page of `command` with the topics as extra sections
+pub fn render_man(command: clap::Command, w: &mut dyn Write) -> Result<()> {
+    clap_mangen::Man::new(command).render(w)?;
+    for topic in TOPICS {
+        writeln!(w, ".SH \"{}\"", topic.name.to_uppercase())?;
+        writeln!(w, "{}", escape_roff(topic.summary))?;
+        writeln!(w, ".PP\n.nf")?;
+        for line in topic.body.lines() {
+            writeln!(w, "{}", escape_roff(line))?;
+        }
+        writeln!(w, ".fi")?;
+    }
+    Ok(())
+}
+
+fn escape_roff(line: &str) -> String {
+    let line = line.replace('\\', "\\e");
+    if line.starts_with(['.', '\'']) {
+        format!("\\&{line}")
+    } else {
+        line
+    }
+}
+
+#[cfg(test)]
+mod test_escape_roff {
+    use super::*;
+
+    #[test]
+    fn control_characters() {
+        assert_eq!(escape_roff(".SH"), "\\&.SH");
+        assert_eq!(escape_roff("a\\tb"), "a\\etb");
+        assert_eq!(escape_roff("plain"), "plain");
+    }
+}
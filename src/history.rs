@@ -0,0 +1,94 @@
+use std::{
+    fs::OpenOptions,
+    io::{BufRead, BufReader, Write},
+    path::PathBuf,
+};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::paths;
+
+/// A mutating operation, stored as a line of JSON in the history file
+#[derive(Default, Serialize, Deserialize)]
+pub struct Entry {
+    pub time: String,
+    pub operation: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spec: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<Url>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+    pub outcome: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Entry {
+    pub fn new(operation: String) -> Self {
+        Self {
+            operation,
+            ..Default::default()
+        }
+    }
+
+    /// Record the outcome and append the entry to the history file
+    pub fn finish<T>(mut self, config: &git2::Config, result: &Result<T>) -> Result<()> {
+        self.time = chrono::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, false);
+        match result {
+            Ok(_) => self.outcome = "ok".into(),
+            Err(err) => {
+                self.outcome = "error".into();
+                self.error = Some(format!("{err:#}"));
+            }
+        }
+
+        let path = get_history_file(config)?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let mut line = serde_json::to_string(&self)?;
+        line.push('\n');
+        file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Read every entry in the history file, oldest first
+pub fn read(config: &git2::Config) -> Result<Vec<Entry>> {
+    let path = get_history_file(config)?;
+    let file = match std::fs::File::open(&path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err.into()),
+    };
+    let mut entries = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line) {
+            Ok(entry) => entries.push(entry),
+            Err(err) => warn!("skipped a malformed history entry: {err}"),
+        }
+    }
+    Ok(entries)
+}
+
+/// `grm.historyFile`, defaulting to the state directory
+///
+/// The file under the data directory, where older versions wrote it, is kept in use if it exists.
+pub fn get_history_file(config: &git2::Config) -> Result<PathBuf> {
+    if let Ok(path) = config.get_path(concat!(env!("CARGO_PKG_NAME"), ".historyFile")) {
+        return Ok(path);
+    }
+    let legacy = paths::data_dir()?.join("history.jsonl");
+    if legacy.exists() {
+        return Ok(legacy);
+    }
+    Ok(paths::state_dir()?.join("history.jsonl"))
+}
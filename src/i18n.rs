@@ -0,0 +1,219 @@
+//! User-facing messages are looked up in the catalog below by [`Message`] keys through the
+//! `t!` macro, in the language selected by `GRM_LANG`, `LC_ALL`, `LC_MESSAGES` or `LANG`.
+//!
+//! The catalog covers what the repository commands report and ask: selection, summaries of bulk
+//! commands, updates, confirmations of lost work, the trash, archives and the vault passphrase.
+//! Help, errors passed through from git or the forges, and diagnostics of malformed files stay in
+//! English.
+
+use std::{fmt::Display, sync::OnceLock};
+
+use crate::template;
+
+/// Format a message in the current language, replacing `{0}`, `{1}`, ... with the arguments
+macro_rules! t {
+    ($message:ident $(, $arg:expr)* $(,)?) => {
+        $crate::i18n::format(
+            $crate::i18n::Message::$message,
+            &[$(&$arg as &dyn std::fmt::Display),*],
+        )
+    };
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Lang {
+    En,
+    Ja,
+}
+
+impl Lang {
+    /// Parse a locale name such as `ja_JP.UTF-8`
+    fn parse(locale: &str) -> Self {
+        match locale.split(['_', '.', '-', '@']).next() {
+            Some("ja") => Self::Ja,
+            _ => Self::En,
+        }
+    }
+
+    fn detect() -> Self {
+        ["GRM_LANG", "LC_ALL", "LC_MESSAGES", "LANG"]
+            .into_iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .map_or(Self::En, |value| Self::parse(&value))
+    }
+}
+
+pub fn lang() -> Lang {
+    static LANG: OnceLock<Lang> = OnceLock::new();
+    *LANG.get_or_init(Lang::detect)
+}
+
+#[derive(Clone, Copy)]
+pub enum Message {
+    ReadOnly,
+    AlreadyCloned,
+    NotEmptyDir,
+    NotRepository,
+    AlreadyArchived,
+    NotArchived,
+    NotInTrash,
+    ReposFailed,
+    NeedsAttention,
+    MatchesMultiple,
+    SelectRepository,
+    NoRepositorySelected,
+    AlreadyExists,
+    WorkWouldBeLost,
+    PassForce,
+    ConfirmNeedsTerminal,
+    TypeNameToLose,
+    Cancelled,
+    UncommittedFiles,
+    AndMore,
+    UnpushedCommits,
+    Stashes,
+    OlderStashes,
+    UpToDate,
+    FastForwarded,
+    Rebased,
+    Diverged,
+    UpstreamGone,
+    RebaseAborted,
+    TrashOnOtherFilesystem,
+    Skipped,
+    #[cfg_attr(not(feature = "archive"), allow(dead_code))]
+    BareRepository,
+    #[cfg_attr(not(feature = "archive"), allow(dead_code))]
+    UnresolvedConflicts,
+    VaultPassphrase,
+    ConfirmPassphrase,
+    PassphraseNeedsTerminal,
+    PassphrasesDiffer,
+    VaultSkipped,
+}
+
+impl Message {
+    fn text(self, lang: Lang) -> &'static str {
+        use Lang::*;
+        use Message::*;
+        match (self, lang) {
+            (ReadOnly, En) => "this command is disabled in read-only mode",
+            (ReadOnly, Ja) => "読み取り専用モードではこのコマンドは使えません",
+            (AlreadyCloned, En) => "already cloned",
+            (AlreadyCloned, Ja) => "既にクローンされています",
+            (NotEmptyDir, En) => "`{0}` already exists and is not an empty directory",
+            (NotEmptyDir, Ja) => "`{0}` は既に存在し、空のディレクトリではありません",
+            (NotRepository, En) => "`{0}` is not a repository",
+            (NotRepository, Ja) => "`{0}` はリポジトリではありません",
+            (AlreadyArchived, En) => "`{0}` is already archived",
+            (AlreadyArchived, Ja) => "`{0}` は既にアーカイブされています",
+            (NotArchived, En) => "`{0}` is not archived",
+            (NotArchived, Ja) => "`{0}` はアーカイブされていません",
+            (NotInTrash, En) => "`{0}` is not in the trash",
+            (NotInTrash, Ja) => "`{0}` はゴミ箱にありません",
+            (ReposFailed, En) => "{0} of {1} repositories failed",
+            (ReposFailed, Ja) => "{1} 個中 {0} 個のリポジトリで失敗しました",
+            (NeedsAttention, En) => "needs manual attention:",
+            (NeedsAttention, Ja) => "手動での対応が必要です:",
+            (MatchesMultiple, En) => "`{0}` matches multiple repositories:",
+            (MatchesMultiple, Ja) => "`{0}` は複数のリポジトリに一致します:",
+            (SelectRepository, En) => "select a repository [1-{0}]: ",
+            (SelectRepository, Ja) => "リポジトリを選択してください [1-{0}]: ",
+            (NoRepositorySelected, En) => "no repository is selected",
+            (NoRepositorySelected, Ja) => "リポジトリが選択されていません",
+            (AlreadyExists, En) => "`{0}` already exists",
+            (AlreadyExists, Ja) => "`{0}` は既に存在します",
+            (WorkWouldBeLost, En) => "{0} has work that would be lost:",
+            (WorkWouldBeLost, Ja) => "{0} には失われる作業があります:",
+            (PassForce, En) => "pass --force to lose it anyway",
+            (PassForce, Ja) => "それでも破棄するには --force を指定してください",
+            (ConfirmNeedsTerminal, En) => "confirming the loss requires an interactive terminal",
+            (ConfirmNeedsTerminal, Ja) => "破棄の確認には対話的な端末が必要です",
+            (TypeNameToLose, En) => "type `{0}` to lose it anyway",
+            (TypeNameToLose, Ja) => "それでも破棄するには `{0}` と入力してください",
+            (Cancelled, En) => "cancelled",
+            (Cancelled, Ja) => "キャンセルしました",
+            (UncommittedFiles, En) => "{0} uncommitted files: {1}",
+            (UncommittedFiles, Ja) => "コミットされていないファイル {0} 個: {1}",
+            (AndMore, En) => " and {0} more",
+            (AndMore, Ja) => " ほか {0} 個",
+            (UnpushedCommits, En) => "{0} unpushed commits on `{1}`",
+            (UnpushedCommits, Ja) => "`{1}` のプッシュされていないコミット {0} 個",
+            (Stashes, En) => "{0} stashes",
+            (Stashes, Ja) => "スタッシュ {0} 個",
+            (OlderStashes, En) => "{0} older stashes",
+            (OlderStashes, Ja) => "古いスタッシュ {0} 個",
+            (UpToDate, En) => "up to date",
+            (UpToDate, Ja) => "最新です",
+            (FastForwarded, En) => "fast-forwarded {0}..{1}",
+            (FastForwarded, Ja) => "{0}..{1} を早送りしました",
+            (Rebased, En) => "rebased {0} onto {1}",
+            (Rebased, Ja) => "{0} を {1} にリベースしました",
+            (Diverged, En) => "diverged from the upstream ({0} ahead, {1} behind)",
+            (Diverged, Ja) => "上流から分岐しています ({0} 個先行、{1} 個遅れ)",
+            (UpstreamGone, En) => "the upstream of `{0}` is gone",
+            (UpstreamGone, Ja) => "`{0}` の上流は削除されています",
+            (RebaseAborted, En) => "failed to rebase, the rebase was aborted",
+            (RebaseAborted, Ja) => "リベースに失敗したため中止しました",
+            (TrashOnOtherFilesystem, En) => "failed to move `{0}` into the trash at `{1}`, set `{2}` to a directory on the same filesystem or use `--permanent`",
+            (TrashOnOtherFilesystem, Ja) => "`{0}` をゴミ箱 `{1}` に移動できませんでした。`{2}` に同じファイルシステム上のディレクトリを設定するか `--permanent` を使ってください",
+            (Skipped, En) => "skipped `{0}`: {1}",
+            (Skipped, Ja) => "`{0}` をスキップしました: {1}",
+            (BareRepository, En) => "cannot archive a bare repository",
+            (BareRepository, Ja) => "ベアリポジトリはアーカイブできません",
+            (UnresolvedConflicts, En) => "cannot archive a repository with unresolved conflicts",
+            (UnresolvedConflicts, Ja) => "未解決の競合があるリポジトリはアーカイブできません",
+            (VaultPassphrase, En) => "vault passphrase: ",
+            (VaultPassphrase, Ja) => "保管庫のパスフレーズ: ",
+            (ConfirmPassphrase, En) => "confirm passphrase: ",
+            (ConfirmPassphrase, Ja) => "パスフレーズの確認: ",
+            (PassphraseNeedsTerminal, En) => "set GRM_PASSPHRASE to use the token vault without a terminal",
+            (PassphraseNeedsTerminal, Ja) => "端末なしでトークン保管庫を使うには GRM_PASSPHRASE を設定してください",
+            (PassphrasesDiffer, En) => "passphrases do not match",
+            (PassphrasesDiffer, Ja) => "パスフレーズが一致しません",
+            (VaultSkipped, En) => "skipped the token vault: {0}",
+            (VaultSkipped, Ja) => "トークン保管庫をスキップしました: {0}",
+        }
+    }
+}
+
+pub fn format(message: Message, args: &[&dyn Display]) -> String {
+    format_text(message.text(lang()), args)
+}
+
+/// Replace `{N}` with the N-th argument in one pass, so that arguments containing `{N}` are kept
+fn format_text(text: &str, args: &[&dyn Display]) -> String {
+    let keys: Vec<String> = (0..args.len()).map(|i| i.to_string()).collect();
+    let values: Vec<(&str, String)> = keys
+        .iter()
+        .zip(args)
+        .map(|(key, arg)| (key.as_str(), arg.to_string()))
+        .collect();
+    template::expand(text, &values)
+}
+
+#[cfg(test)]
+mod test_lang {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!(Lang::parse("ja_JP.UTF-8"), Lang::Ja);
+        assert_eq!(Lang::parse("ja"), Lang::Ja);
+        assert_eq!(Lang::parse("en_US.UTF-8"), Lang::En);
+        assert_eq!(Lang::parse("C"), Lang::En);
+    }
+}
+
+#[cfg(test)]
+mod test_format_text {
+    use super::*;
+
+    #[test]
+    fn positional() {
+        assert_eq!(format_text("{1} 個中 {0} 個", &[&2, &"10"]), "10 個中 2 個");
+        assert_eq!(format_text("`{0}` in {1}", &[&"{1}", &"x"]), "`{1}` in x");
+        assert_eq!(format_text("{2} {x}", &[&0]), "{2} {x}");
+    }
+}
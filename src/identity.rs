@@ -0,0 +1,116 @@
+//! Identities grm authenticates as on each configured host, checked by `grm whoami`
+
+use std::{
+    path::Path,
+    process::{Command, Stdio},
+    sync::LazyLock,
+};
+
+use anyhow::{bail, Result};
+use regex::Regex;
+
+/// Greetings of GitHub, GitLab, Gitea/Forgejo, sourcehut and Bitbucket to `ssh -T`
+static GREETING: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?:Hi there, |Hi |Welcome to GitLab, @|logged in as )([^\s!,]+)").unwrap()
+});
+
+/// Hosts named in `grm.<host>.*` and `grm.<host>/<owner>.token` keys, plus `default_host`
+pub fn hosts(config: &git2::Config, default_host: &str) -> Result<Vec<String>> {
+    let prefix = concat!(env!("CARGO_PKG_NAME"), ".");
+    let mut hosts = vec![default_host.to_string()];
+    config
+        .entries(Some(&format!("^{}", regex::escape(prefix))))?
+        .for_each(|entry| {
+            let Some(name) = entry.name().and_then(|name| name.strip_prefix(prefix)) else {
+                return;
+            };
+            if name.starts_with("profile.") || name.starts_with("alias.") {
+                return;
+            }
+            let Some((host, _)) = name.rsplit_once('.') else {
+                return;
+            };
+            let host = host.split('/').next().unwrap_or(host);
+            // host names have dots, unlike the other keys
+            if host.contains('.') {
+                hosts.push(host.to_lowercase());
+            }
+        })?;
+    hosts.sort();
+    hosts.dedup();
+    Ok(hosts)
+}
+
+/// The user `ssh -T git@<host>` is greeted as
+pub fn ssh_user(host: &str, key: Option<&Path>) -> Result<String> {
+    let mut command = Command::new("ssh");
+    command.args(["-T", "-o", "BatchMode=yes", "-o", "ConnectTimeout=10"]);
+    if let Some(key) = key {
+        command
+            .arg("-i")
+            .arg(key)
+            .args(["-o", "IdentitiesOnly=yes"]);
+    }
+    // forges exit with 1 after the greeting as they provide no shell
+    let output = command
+        .arg(format!("git@{host}"))
+        .stdin(Stdio::null())
+        .output()?;
+    let text = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    if let Some(user) = parse_greeting(&text) {
+        return Ok(user);
+    }
+    match text.lines().map(str::trim).rfind(|line| !line.is_empty()) {
+        Some(line) => bail!("{}", line.strip_prefix("ssh: ").unwrap_or(line)),
+        None => bail!("ssh exited with {}", output.status),
+    }
+}
+
+fn parse_greeting(text: &str) -> Option<String> {
+    let user = GREETING.captures(text)?.get(1)?.as_str();
+    Some(user.trim_end_matches('.').to_string())
+}
+
+/// The comment of the public key next to the private key `key`, usually naming its owner
+pub fn key_comment(key: &Path) -> Option<String> {
+    let mut public = key.as_os_str().to_owned();
+    public.push(".pub");
+    let content = std::fs::read_to_string(public).ok()?;
+    let comment = content
+        .split_whitespace()
+        .skip(2)
+        .collect::<Vec<_>>()
+        .join(" ");
+    (!comment.is_empty()).then_some(comment)
+}
+
+#[cfg(test)]
+mod test_parse_greeting {
+    use super::*;
+
+    #[test]
+    fn forges() {
+        let user = |text| parse_greeting(text);
+        assert_eq!(
+            user("Hi octo! You've successfully authenticated, but GitHub does not provide shell access."),
+            Some("octo".into())
+        );
+        assert_eq!(
+            user("Welcome to GitLab, @first.last!"),
+            Some("first.last".into())
+        );
+        assert_eq!(
+            user("Hi there, bob! You've successfully authenticated with the key named x"),
+            Some("bob".into())
+        );
+        assert_eq!(
+            user("Hi ~bob! You've successfully authenticated"),
+            Some("~bob".into())
+        );
+        assert_eq!(user("git@github.com: Permission denied (publickey)."), None);
+    }
+}
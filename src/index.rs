@@ -0,0 +1,75 @@
+//! Repositories found under each root, cached for `list --cached` and kept fresh by `grm watch`
+
+use std::{
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{paths, scan};
+
+#[cfg(feature = "watch")]
+mod watch;
+
+#[cfg(feature = "watch")]
+pub use watch::watch;
+
+#[derive(Serialize, Deserialize)]
+struct Index {
+    root: PathBuf,
+    updated_at: String,
+    /// Paths relative to the root
+    repos: Vec<PathBuf>,
+}
+
+fn get_index_file(root: &Path) -> Result<PathBuf> {
+    let mut hasher = std::hash::DefaultHasher::new();
+    root.hash(&mut hasher);
+    Ok(paths::cache_dir()?
+        .join("index")
+        .join(format!("{:016x}.json", hasher.finish())))
+}
+
+/// Cached repositories under `root`, or `None` if the root has not been indexed
+pub fn load(root: &Path) -> Result<Option<Vec<PathBuf>>> {
+    let file = get_index_file(root)?;
+    let index = match std::fs::read_to_string(&file) {
+        Ok(index) => index,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+    let index: Index = serde_json::from_str(&index)?;
+    Ok(Some(
+        index.repos.iter().map(|repo| root.join(repo)).collect(),
+    ))
+}
+
+/// Scan `root` and write the result into the index
+pub fn refresh(root: &Path, max_depth: Option<usize>) -> Result<usize> {
+    let repos: Vec<_> = scan::repos(root, max_depth)
+        .into_iter()
+        .map(|path| path.strip_prefix(root).unwrap_or(&path).to_path_buf())
+        .collect();
+    let count = repos.len();
+    let file = get_index_file(root)?;
+    if let Some(parent) = file.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let index = Index {
+        root: root.to_path_buf(),
+        updated_at: chrono::Local::now().to_rfc3339(),
+        repos,
+    };
+    // write and rename so that readers never see a partial file
+    let temp = file.with_extension("json.tmp");
+    std::fs::write(&temp, serde_json::to_string(&index)?)?;
+    std::fs::rename(&temp, &file)?;
+    Ok(count)
+}
+
+#[cfg(not(feature = "watch"))]
+pub fn watch(_roots: &[PathBuf], _max_depth: Option<usize>) -> Result<()> {
+    anyhow::bail!("build with the `watch` feature to watch the root directories")
+}
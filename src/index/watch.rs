@@ -0,0 +1,114 @@
+//! Refreshing the index on changes under the roots, compiled with the `watch` feature
+
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc,
+    time::Duration,
+};
+
+use anyhow::Result;
+use notify::{
+    event::{EventKind, ModifyKind},
+    RecursiveMode, Watcher,
+};
+
+use super::refresh;
+
+/// Events arriving within this period are handled with a single scan
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Whether a change of `path` may add or remove a repository under `root`
+///
+/// Changes inside git directories and deeper than `max_depth` are ignored.
+fn is_relevant(root: &Path, path: &Path, max_depth: Option<usize>) -> bool {
+    let Ok(relative) = path.strip_prefix(root) else {
+        return false;
+    };
+    let components: Vec<_> = relative.components().map(|c| c.as_os_str()).collect();
+    if let Some(i) = components.iter().position(|c| *c == ".git") {
+        return i + 1 == components.len();
+    }
+    max_depth.is_none_or(|depth| components.len() <= depth)
+}
+
+/// Index `roots`, then refresh a root whenever a repository may have appeared or disappeared
+pub fn watch(roots: &[PathBuf], max_depth: Option<usize>) -> Result<()> {
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    for root in roots {
+        std::fs::create_dir_all(root)?;
+        watcher.watch(root, RecursiveMode::Recursive)?;
+        info!(
+            "indexed {} repositories in {}",
+            refresh(root, max_depth)?,
+            root.display()
+        );
+    }
+
+    let mut dirty = Vec::new();
+    loop {
+        let event = if dirty.is_empty() {
+            Some(rx.recv()?)
+        } else {
+            match rx.recv_timeout(DEBOUNCE) {
+                Ok(event) => Some(event),
+                Err(mpsc::RecvTimeoutError::Timeout) => None,
+                Err(err) => return Err(err.into()),
+            }
+        };
+        match event {
+            Some(event) => {
+                let event = event?;
+                // opening files while scanning would otherwise trigger another scan
+                if !matches!(
+                    event.kind,
+                    EventKind::Create(_)
+                        | EventKind::Remove(_)
+                        | EventKind::Modify(ModifyKind::Name(_))
+                        | EventKind::Any
+                ) {
+                    continue;
+                }
+                for path in event.paths {
+                    let root = roots.iter().find(|root| path.starts_with(root));
+                    if let Some(root) = root {
+                        if is_relevant(root, &path, max_depth) && !dirty.contains(root) {
+                            dirty.push(root.clone());
+                        }
+                    }
+                }
+            }
+            None => {
+                for root in dirty.drain(..) {
+                    match refresh(&root, max_depth) {
+                        Ok(count) => info!("indexed {count} repositories in {}", root.display()),
+                        Err(err) => warn!("failed to index `{}`: {err:#}", root.display()),
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_is_relevant {
+    use super::*;
+
+    #[test]
+    fn git_dirs_and_depth() {
+        let root = Path::new("/r");
+        assert!(is_relevant(root, Path::new("/r/github.com/a/b"), Some(5)));
+        assert!(is_relevant(
+            root,
+            Path::new("/r/github.com/a/b/.git"),
+            Some(5)
+        ));
+        assert!(!is_relevant(
+            root,
+            Path::new("/r/github.com/a/b/.git/index"),
+            Some(5)
+        ));
+        assert!(!is_relevant(root, Path::new("/r/a/b/c/d/e/f"), Some(5)));
+        assert!(!is_relevant(root, Path::new("/other"), None));
+    }
+}
@@ -0,0 +1,138 @@
+use std::{collections::BTreeSet, io::Read, path::Path};
+
+use anyhow::Result;
+use git2::Repository;
+
+/// Phrases identifying license texts, checked in order against the lowercased text
+///
+/// Variants of the GPL come first since their texts mention the GPL.
+const LICENSES: &[(&str, &[&str])] = &[
+    ("AGPL-3.0", &["gnu affero general public license"]),
+    (
+        "LGPL-3.0",
+        &["gnu lesser general public license", "version 3"],
+    ),
+    ("LGPL-2.1", &["gnu lesser general public license"]),
+    ("GPL-3.0", &["gnu general public license", "version 3"]),
+    ("GPL-2.0", &["gnu general public license", "version 2"]),
+    ("Apache-2.0", &["apache license", "version 2.0"]),
+    ("MPL-2.0", &["mozilla public license", "2.0"]),
+    ("BSL-1.0", &["boost software license"]),
+    (
+        "Unlicense",
+        &["free and unencumbered software released into the public domain"],
+    ),
+    ("CC0-1.0", &["cc0"]),
+    (
+        "BSD-3-Clause",
+        &[
+            "redistribution and use in source and binary forms",
+            "neither the name",
+        ],
+    ),
+    (
+        "BSD-2-Clause",
+        &["redistribution and use in source and binary forms"],
+    ),
+    (
+        "ISC",
+        &["permission to use, copy, modify, and/or distribute this software for any purpose"],
+    ),
+    ("MIT", &["permission is hereby granted, free of charge"]),
+    ("Zlib", &["this software is provided 'as-is'"]),
+];
+
+/// Tracked files whose headers are read for `SPDX-License-Identifier` at most
+const MAX_HEADER_FILES: usize = 200;
+
+/// Licenses of the repository at `path` in SPDX identifiers, `Unknown` for unrecognized texts
+///
+/// License files at the top level are read first, then headers of tracked files.
+pub fn detect(path: &Path) -> Result<BTreeSet<String>> {
+    let mut licenses = BTreeSet::new();
+    for entry in std::fs::read_dir(path)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_uppercase();
+        if !["LICENSE", "LICENCE", "COPYING", "UNLICENSE"]
+            .iter()
+            .any(|prefix| name.starts_with(prefix))
+            || !entry.file_type()?.is_file()
+        {
+            continue;
+        }
+        let text = std::fs::read_to_string(entry.path()).unwrap_or_default();
+        let license = spdx_identifier(&text)
+            .or_else(|| identify(&text))
+            .unwrap_or("Unknown");
+        licenses.insert(license.to_string());
+    }
+    if licenses.is_empty() {
+        licenses.extend(header_licenses(path));
+    }
+    Ok(licenses)
+}
+
+/// The license whose text is `text`
+fn identify(text: &str) -> Option<&'static str> {
+    let text = text.to_lowercase();
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    LICENSES
+        .iter()
+        .find(|(_, phrases)| phrases.iter().all(|phrase| text.contains(phrase)))
+        .map(|(license, _)| *license)
+}
+
+fn spdx_identifier(text: &str) -> Option<&str> {
+    let (_, rest) = text.split_once("SPDX-License-Identifier:")?;
+    let id = rest.lines().next()?.trim();
+    // closing comment delimiters such as `*/` and `-->`
+    let id = id.trim_end_matches(|c: char| "*/->".contains(c)).trim();
+    (!id.is_empty()).then_some(id)
+}
+
+fn header_licenses(path: &Path) -> BTreeSet<String> {
+    let mut licenses = BTreeSet::new();
+    let Ok(index) = Repository::open(path).and_then(|repo| repo.index()) else {
+        return licenses;
+    };
+    for entry in index.iter().take(MAX_HEADER_FILES) {
+        let file = path.join(String::from_utf8_lossy(&entry.path).as_ref());
+        let mut head = Vec::new();
+        let Ok(file) = std::fs::File::open(file) else {
+            continue;
+        };
+        if file.take(1024).read_to_end(&mut head).is_err() {
+            continue;
+        }
+        if let Some(id) = spdx_identifier(&String::from_utf8_lossy(&head)) {
+            licenses.insert(id.to_string());
+        }
+    }
+    licenses
+}
+
+#[cfg(test)]
+mod test_identify {
+    use super::*;
+
+    #[test]
+    fn texts() {
+        assert_eq!(
+            identify("Permission is hereby granted,\nfree of charge, to any person"),
+            Some("MIT")
+        );
+        assert_eq!(
+            identify("GNU LESSER GENERAL PUBLIC LICENSE\n Version 3, 29 June 2007"),
+            Some("LGPL-3.0")
+        );
+        assert_eq!(
+            identify("Apache License\n Version 2.0, January 2004"),
+            Some("Apache-2.0")
+        );
+        assert_eq!(identify("All rights reserved."), None);
+        assert_eq!(
+            spdx_identifier("/* SPDX-License-Identifier: MIT OR Apache-2.0 */"),
+            Some("MIT OR Apache-2.0")
+        );
+    }
+}
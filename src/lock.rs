@@ -0,0 +1,44 @@
+use std::{
+    fs::{File, TryLockError},
+    path::Path,
+};
+
+use anyhow::{bail, Result};
+
+use crate::{hash, paths};
+
+/// An exclusive lock on a path, released when dropped
+///
+/// The lock is held on a file under the cache directory, so it does not leave anything
+/// behind in the target directory and is released by the OS even if grm is killed.
+pub struct Lock {
+    _file: File,
+}
+
+impl Lock {
+    /// Lock `target`, waiting for other processes if `wait` is set
+    pub fn acquire(target: &Path, wait: bool) -> Result<Self> {
+        let dir = paths::cache_dir()?.join("locks");
+        std::fs::create_dir_all(&dir)?;
+
+        let hash = hash::fnv1a(target.as_os_str().as_encoded_bytes());
+        let file = File::create(dir.join(format!("{hash:016x}.lock")))?;
+
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) if wait => {
+                info!(
+                    "waiting for another process working on `{}`",
+                    target.display()
+                );
+                file.lock()?;
+            }
+            Err(TryLockError::WouldBlock) => bail!(
+                "another process is already working on `{}` (use `--wait` to wait for it)",
+                target.display()
+            ),
+            Err(TryLockError::Error(err)) => return Err(err.into()),
+        }
+        Ok(Self { _file: file })
+    }
+}
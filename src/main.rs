@@ -1,166 +1,3556 @@
-use std::{
-    io::{BufRead, IsTerminal},
-    path::{Path, PathBuf},
-};
+#[macro_use]
+mod output;
+#[macro_use]
+mod i18n;
+
+mod alias;
+mod alternates;
+mod archive;
+mod backend;
+mod branches;
+mod browse;
+mod bundle;
+mod cert;
+mod clipboard;
+mod completion;
+mod contributors;
+mod devcontainer;
+mod drift;
+mod du;
+mod envtrust;
+mod events;
+mod foreach;
+mod forge;
+mod fork;
+mod git;
+mod gitdir;
+mod glob;
+mod grep;
+mod hash;
+mod head;
+mod headers;
+mod help;
+mod history;
+mod identity;
+mod index;
+mod license;
+mod lock;
+mod note;
+mod notify;
+mod objcache;
+mod paths;
+mod pinned;
+mod profile;
+mod project;
+mod provider;
+mod proxy;
+mod replicate;
+mod root;
+mod safety;
+mod sanitize;
+mod scan;
+mod select;
+mod serve;
+mod setup;
+mod shell;
+mod specs;
+mod staging;
+mod stale;
+mod startup;
+mod stash;
+mod stats;
+mod status;
+mod tag;
+mod template;
+mod todo;
+mod transport;
+mod trash;
+mod update;
+mod upstream;
+mod vault;
+mod verify;
+mod workspace;
 
-use anyhow::{Context, Result};
-use clap::Parser;
-use dirs::home_dir;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, ensure, Context, Result};
+use clap::{CommandFactory, FromArgMatches, Parser};
 use git2::Repository;
 use url::Url;
 
+use crate::{
+    backend::{Backend, CloneOptions},
+    profile::Profile,
+};
+
 const DEFAULT_HOST: &str = "github.com";
 
 /// Git Repository Manager
 #[derive(Parser)]
+#[command(disable_help_subcommand = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: CliCommand,
+    /// Use the settings of `grm.profile.<PROFILE>.*`
+    #[arg(long, global = true)]
+    profile: Option<String>,
+    /// Disable commands that modify repositories, the configuration or the vault (also enabled by
+    /// `grm.readOnly`)
+    #[arg(long, global = true, default_value_t = false)]
+    read_only: bool,
+    /// Send a notification when the command finishes (also sent after `grm.notifyAfter` seconds)
+    #[arg(long, global = true, default_value_t = false)]
+    notify: bool,
+    /// Print the time spent in each phase of startup to stderr
+    #[arg(long, global = true, hide = true, default_value_t = false)]
+    profile_startup: bool,
+}
+
+#[derive(clap::Subcommand)]
 enum CliCommand {
     /// Print repositories' root directory
     Root,
 
+    /// Diagnose the configuration
+    Doctor {
+        /// Mark the active root directory as managed by grm
+        #[arg(long, default_value_t = false)]
+        mark: bool,
+    },
+
     /// List managed local repositories
     #[command(visible_alias = "l")]
     List {
         /// Print absolute paths
         #[arg(long, short = 'l', default_value_t = false)]
         absolute: bool,
+        /// Compare local repositories with the ones owned by `--owner` on the forge
+        #[arg(long, default_value_t = false)]
+        remote: bool,
+        /// User or organization to compare with, defaults to `grm.owner` or the username
+        #[arg(long, value_name = "[HOST/]OWNER", requires = "remote")]
+        owner: Vec<String>,
+        /// Print the branch and the last commit of each repository
+        #[arg(long, short = 'v', visible_alias = "verbose", default_value_t = false)]
+        last_commit: bool,
+        /// Print each repository with a template, e.g. `{host}/{owner}/{name}\t{path}\t{branch}`
+        ///
+        /// Placeholders: {host}, {owner}, {name}, {repo} (relative path), {path} (absolute path),
+        /// {branch}, {commit}, {date}, {subject} and {tags}. `{{` and `}}` are literal braces,
+        /// and `\t`, `\n` and `\\` are escapes.
+        #[arg(long, value_name = "TEMPLATE", conflicts_with_all = ["remote", "last_commit"])]
+        format: Option<template::Template>,
+        /// Read the index kept by `grm watch` instead of scanning, if the root is indexed
+        #[arg(long, conflicts_with = "remote", default_value_t = false)]
+        cached: bool,
+        /// Print a JSON object per line, with the repository containing each one as `parent`
+        #[arg(long, conflicts_with_all = ["remote", "last_commit", "format"], default_value_t = false)]
+        json: bool,
+        /// Flag repositories archived or read-only on the forge, checked once a day
+        #[arg(long, conflicts_with_all = ["remote", "format"], default_value_t = false)]
+        check_upstream: bool,
+        /// Check the upstreams again instead of using the cached states
+        #[arg(long, requires = "check_upstream", default_value_t = false)]
+        refresh_upstream: bool,
+        /// Also print projects whose name or path matches `--query`, unless `--tag` is given
+        #[arg(long, conflicts_with_all = ["remote", "last_commit", "format", "json"], default_value_t = false)]
+        projects: bool,
+        /// Draw the repositories as a tree grouped by host and owner, with counts
+        #[arg(long, conflicts_with_all = ["absolute", "remote", "last_commit", "format", "json", "check_upstream", "projects"], default_value_t = false)]
+        tree: bool,
+        #[command(flatten)]
+        filter: scan::Filter,
     },
 
     /// Clone a remote repository
     #[command(visible_alias = "g", alias = "clone")]
     Get {
-        repo: String,
+        #[arg(required_unless_present_any = ["file", "stdin"])]
+        repo: Option<String>,
+        /// Read repositories from the file, one per line, instead
+        #[arg(long, short, value_name = "FILE", conflicts_with = "repo")]
+        file: Option<PathBuf>,
+        /// Read repositories from stdin, one per line or as JSON lines, instead
+        #[arg(long, conflicts_with_all = ["repo", "file"], default_value_t = false)]
+        stdin: bool,
         /// Clone with SSH instead of HTTPS
         #[arg(long, default_value_t = false)]
         ssh: bool,
         /// Set fetch depth, 0 means to pull everything
         #[arg(long, default_value_t = 0)]
         depth: i32,
+        /// Only fetch the history after the date, like `2024-01-01` or `1 year ago`
+        #[arg(long, value_name = "DATE")]
+        shallow_since: Option<String>,
+        /// Don't fetch the history reachable from the branch or tag (can be repeated)
+        #[arg(long, value_name = "REF")]
+        shallow_exclude: Vec<String>,
+        /// Clone backend to use, overriding `grm.backend`
+        #[arg(long, value_enum)]
+        backend: Option<Backend>,
+        /// What to print to stdout
+        #[arg(long, value_enum, default_value_t = Print::Path)]
+        print: Print,
+        /// Wait for other processes cloning into the same path instead of failing
+        #[arg(long, default_value_t = false)]
+        wait: bool,
+        /// Name of the remote to clone from
+        #[arg(long, default_value = "origin")]
+        origin_name: String,
+        /// Print the result as a JSON object instead
+        #[arg(long, conflicts_with = "print", default_value_t = false)]
+        json: bool,
+        /// Also copy the path of the repository to the clipboard
+        #[arg(long, conflicts_with_all = ["file", "stdin"], default_value_t = false)]
+        copy: bool,
+        /// Accept any TLS certificate of the server, which is dangerous
+        #[arg(long, default_value_t = false)]
+        insecure_skip_tls_verify: bool,
+        /// Don't populate the working tree after cloning
+        #[arg(long, default_value_t = false)]
+        no_checkout: bool,
+        /// Check out only these directories with sparse-checkout
+        #[arg(long, num_args = 1.., value_name = "PATH")]
+        sparse: Vec<String>,
+        /// Sign commits with the signing key of the profile or host, as `grm new` does
+        #[arg(long, default_value_t = false)]
+        sign: bool,
+        /// Start the dev container of the repository with the devcontainer CLI or Docker Compose
+        #[arg(long, default_value_t = false)]
+        devcontainer: bool,
+        /// Keep the git directory under `grm.gitDirRoot` apart from the worktree, as
+        /// `grm.separateGitDir` does
+        #[arg(long, default_value_t = false)]
+        separate_git_dir: bool,
+        /// Borrow objects from a managed repository instead of downloading them again, by
+        /// default the one of the same name on the host such as the original of a fork
+        #[arg(long, value_name = "REPO", num_args = 0..=1, default_missing_value = "")]
+        reference: Option<String>,
+        /// Copy the borrowed objects so that the reference can be removed later
+        #[arg(long, requires = "reference", default_value_t = false)]
+        dissociate: bool,
+        /// Clone without asking even if the repository is larger than `grm.confirmSizeMb`
+        #[arg(long, short, default_value_t = false)]
+        yes: bool,
+        /// Stream an event to stdout whenever a repository starts or finishes cloning instead of
+        /// printing the paths
+        #[arg(long, value_enum, value_name = "FORMAT", conflicts_with_all = ["print", "json", "copy"])]
+        events: Option<events::Format>,
     },
 
     /// Create a new local repository
     #[command(visible_alias = "n")]
     New {
-        repo: String,
+        /// Defaults to the name of the current directory, which must be empty, implying --here
+        repo: Option<String>,
         /// Don't complete the origin URL
         #[arg(long, short, default_value_t = false)]
         raw: bool,
         /// Use SSH scheme for the origin URL instead of HTTPS scheme
         #[arg(long, default_value_t = false)]
         ssh: bool,
+        /// Initialize the current directory instead of a directory under the root
+        #[arg(long, default_value_t = false)]
+        here: bool,
+        /// Move the current directory into the root after initializing it
+        #[arg(long = "move", default_value_t = false)]
+        move_to_root: bool,
+        /// Symlink the current directory into the root after initializing it
+        #[arg(long, conflicts_with = "move_to_root", default_value_t = false)]
+        link: bool,
+        /// What to print to stdout
+        #[arg(long, value_enum, default_value_t = Print::Path)]
+        print: Print,
+        /// Name of the remote pointing to the origin URL
+        #[arg(long, default_value = "origin")]
+        origin_name: String,
+        /// Print the result as a JSON object instead
+        #[arg(long, conflicts_with = "print", default_value_t = false)]
+        json: bool,
+        /// Also copy the path of the repository to the clipboard
+        #[arg(long, default_value_t = false)]
+        copy: bool,
+        /// Scaffold a workspace with these members, a cargo workspace unless
+        /// `grm.workspaceCommand` is set
+        #[arg(long, value_name = "MEMBER", num_args = 0..)]
+        workspace: Option<Vec<String>>,
+    },
+
+    /// Pack a repository into the archive directory and remove its working copy
+    Archive {
+        repo: String,
+        /// Also keep ignored files
+        #[arg(long, default_value_t = false)]
+        ignored: bool,
+        /// Archive even if stashes would be lost, after typing the name of the repository
+        #[arg(long, short, default_value_t = false)]
+        force: bool,
+    },
+
+    /// Bring an archived repository back
+    Restore { repo: String },
+
+    /// Manage dev containers of repositories
+    Devcontainer {
+        #[command(subcommand)]
+        command: DevcontainerCommand,
+    },
+
+    /// Carry repositories as git bundle files, e.g. to an air-gapped machine
+    Bundle {
+        #[command(subcommand)]
+        command: BundleCommand,
+    },
+
+    /// Move a repository to the trash
+    #[command(visible_alias = "rm")]
+    Remove {
+        /// Repository, or a glob pattern like `github.com/myorg/*` which requires --force
+        #[arg(required_unless_present_any = ["file", "stdin"])]
+        repo: Option<String>,
+        /// Read repositories from the file, one per line, instead
+        #[arg(long, value_name = "FILE", conflicts_with = "repo")]
+        file: Option<PathBuf>,
+        /// Read repositories from stdin, one per line or as JSON lines, instead
+        #[arg(long, conflicts_with_all = ["repo", "file"], default_value_t = false)]
+        stdin: bool,
+        /// Delete the repository instead of moving it to the trash
+        #[arg(long, default_value_t = false)]
+        permanent: bool,
+        /// Remove every repository matching the pattern, and with --permanent, delete
+        /// repositories with uncommitted changes, unpushed commits or stashes after typing their
+        /// names
+        #[arg(long, short, default_value_t = false)]
+        force: bool,
+        /// Copy objects into repositories borrowing them from the removed ones first
+        #[arg(long, default_value_t = false)]
+        dissociate: bool,
+    },
+
+    /// Manage removed repositories
+    Trash {
+        #[command(subcommand)]
+        command: TrashCommand,
+    },
+
+    /// Report disk usage of managed repositories
+    Du {
+        /// Print sizes in bytes
+        #[arg(long, short, default_value_t = false)]
+        bytes: bool,
+        #[command(flatten)]
+        filter: scan::Filter,
+    },
+
+    /// Summarize repositories by host, owner and language, with their size and state
+    Stats {
+        /// Number of owners and dirty repositories to show
+        #[arg(long, short = 'n', default_value_t = 5)]
+        top: usize,
+        #[command(flatten)]
+        filter: scan::Filter,
+    },
+
+    /// Report the license of each repository, detected from license files and SPDX headers
+    License {
+        /// Print only repositories without a license
+        #[arg(long, default_value_t = false)]
+        missing: bool,
+        #[command(flatten)]
+        filter: scan::Filter,
+    },
+
+    /// Show config of repositories that differs from what grm would write: the commit identity,
+    /// the push URL and commit signing
+    DiffConfig {
+        /// Write the expected values into the local config of each repository
+        #[arg(long, default_value_t = false)]
+        fix: bool,
+        #[command(flatten)]
+        filter: scan::Filter,
+    },
+
+    /// Check objects and references of managed repositories for corruption
+    Verify {
+        #[command(flatten)]
+        filter: scan::Filter,
+    },
+
+    /// Fetch managed repositories and fast-forward their current branches
+    #[command(visible_alias = "u")]
+    Update {
+        #[command(flatten)]
+        filter: scan::Filter,
+        #[command(flatten)]
+        opts: update::Options,
+        /// Number of repositories to update in parallel
+        #[arg(long, short)]
+        jobs: Option<usize>,
+        /// Only update the repositories listed in the file, one per line
+        #[arg(long, short, value_name = "FILE")]
+        file: Option<PathBuf>,
+        /// Only update the repositories listed in stdin, one per line or as JSON lines
+        #[arg(long, conflicts_with = "file", default_value_t = false)]
+        stdin: bool,
+        /// Stream an event to stdout whenever a repository starts, progresses, finishes or fails
+        #[arg(long, value_enum, value_name = "FORMAT")]
+        events: Option<events::Format>,
+    },
+
+    /// Push every branch and tag of a repository to a second remote, creating the repository on
+    /// the forge unless a URL is given
+    Replicate {
+        repo: String,
+        /// `host` or `host/owner` to create the repository on through the API, or a URL
+        target: String,
+        /// Name of the remote to push to
+        #[arg(long, default_value = "mirror")]
+        remote_name: String,
+        /// Name of the remote whose branches are pushed
+        #[arg(long, default_value = "origin")]
+        origin_name: String,
+        /// Push with SSH instead of HTTPS to the created repository
+        #[arg(long, default_value_t = false)]
+        ssh: bool,
+        /// Make the created repository private
+        #[arg(long, default_value_t = false)]
+        private: bool,
+        /// Overwrite branches and tags that differ on the target
+        #[arg(long, default_value_t = false)]
+        force: bool,
+    },
+
+    /// Fast-forward the default branch of forks from their upstream and push it to the fork
+    SyncFork {
+        #[arg(required_unless_present = "all")]
+        repo: Option<String>,
+        /// Sync every managed fork
+        #[arg(long, conflicts_with = "repo", default_value_t = false)]
+        all: bool,
+    },
+
+    /// Suggest removing repositories left untouched for a while, and remove the confirmed ones
+    Prune {
+        /// Period without commits, checkouts, fetches or modified files, like `180d`, `6w` or `1y`
+        #[arg(long, value_name = "PERIOD", value_parser = stale::parse_period)]
+        stale: std::time::Duration,
+        /// Only print the suggestions instead of asking whether to remove each one
+        #[arg(long, default_value_t = false)]
+        suggest: bool,
+        /// Delete the repositories instead of moving them to the trash
+        #[arg(long, default_value_t = false)]
+        permanent: bool,
+        #[command(flatten)]
+        filter: scan::Filter,
+    },
+
+    /// Run `git gc` in managed repositories
+    Gc {
+        #[arg(required_unless_present = "all")]
+        repo: Option<String>,
+        /// Run in every managed repository
+        #[arg(long, conflicts_with = "repo", default_value_t = false)]
+        all: bool,
+        /// Optimize the repository more aggressively at the expense of time
+        #[arg(long, default_value_t = false)]
+        aggressive: bool,
+        /// Prune loose objects older than the date (defaults to `now`)
+        #[arg(long, value_name = "DATE", num_args = 0..=1, default_missing_value = "now")]
+        prune: Option<String>,
+        /// Narrow down the repositories of --all
+        #[command(flatten)]
+        filter: scan::Filter,
+    },
+
+    /// Run a command in every managed repository
+    ///
+    /// `{path}`, `{repo}`, `{host}`, `{owner}`, `{name}` and `{branch}` in the command are replaced
+    /// with the values of each repository, which are also exported as `GRM_PATH`, `GRM_REPO` and
+    /// so on.
+    Foreach {
+        #[command(flatten)]
+        filter: scan::Filter,
+        /// Number of commands to run in parallel
+        #[arg(long, short)]
+        jobs: Option<usize>,
+        /// Start no more commands after one fails
+        #[arg(long, default_value_t = false)]
+        fail_fast: bool,
+        /// Run the command in every repository even if some fail, which is the default
+        #[arg(long, conflicts_with = "fail_fast", default_value_t = false)]
+        keep_going: bool,
+        /// Print a JSON object per repository with the exit code and the output instead
+        #[arg(long, default_value_t = false)]
+        json: bool,
+        /// Stream an event to stdout whenever a command starts, prints a line, finishes or fails
+        #[arg(long, value_enum, value_name = "FORMAT", conflicts_with = "json")]
+        events: Option<events::Format>,
+        /// Run only in the repositories failed or skipped in the last run
+        #[arg(long, default_value_t = false)]
+        failed: bool,
+        /// Run a shell script in every repository instead of a command
+        #[arg(long, value_name = "FILE", conflicts_with = "command")]
+        script: Option<PathBuf>,
+        #[arg(last = true, required_unless_present = "script")]
+        command: Vec<String>,
+    },
+
+    /// Search tracked files in managed repositories
+    Grep {
+        pattern: String,
+        /// Match case insensitively
+        #[arg(long, short, default_value_t = false)]
+        ignore_case: bool,
+        /// Treat the pattern as a literal string
+        #[arg(long, short = 'F', default_value_t = false)]
+        fixed_strings: bool,
+        #[command(flatten)]
+        filter: scan::Filter,
+        /// Number of repositories to search in parallel
+        #[arg(long, short)]
+        jobs: Option<usize>,
+    },
+
+    /// Print local branches of managed repositories, and clean up merged ones
+    Branches {
+        /// Only print branches fully merged into the default branch, except protected ones
+        #[arg(long, default_value_t = false)]
+        merged: bool,
+        /// Delete the merged branches
+        #[arg(long, requires = "merged", default_value_t = false)]
+        delete: bool,
+        /// Also protect branches matching the glob pattern (can be repeated), in addition to
+        /// `grm.protectedBranch`
+        #[arg(long, value_name = "PATTERN")]
+        protect: Vec<String>,
+        /// Remote whose default branch the branches are compared with
+        #[arg(long, default_value = "origin")]
+        remote: String,
+        #[command(flatten)]
+        filter: scan::Filter,
+    },
+
+    /// Summarize recent commit authorship of a repository, or of every managed repository
+    ///
+    /// Repositories are `active` if you committed to them, `reference` if only others did and
+    /// `idle` if nobody did.
+    Contributors {
+        /// Print the authors of this repository instead
+        repo: Option<String>,
+        /// Count commits of the last N months
+        #[arg(long, default_value_t = 6, value_name = "N")]
+        months: u32,
+        #[command(flatten)]
+        filter: scan::Filter,
+        /// Print JSON lines with `repo`, `role`, `commits`, `yours` and `authors` instead
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+
+    /// Report managed repositories where work is easily lost: on a detached HEAD, on a branch
+    /// other than the default one without an upstream, with unpushed commits, or with
+    /// uncommitted changes
+    Status {
+        /// Also print repositories with nothing to report
+        #[arg(long, short, default_value_t = false)]
+        all: bool,
+        /// Remote whose default branch needs no upstream
+        #[arg(long, default_value = "origin")]
+        remote: String,
+        #[command(flatten)]
+        filter: scan::Filter,
+        /// Print JSON lines with `repo`, `branch`, `detached_at`, `no_upstream`, `ahead`, `dirty`
+        /// and `issues` instead
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+
+    /// Print stashes left in managed repositories with their age, the oldest first
+    Stashes {
+        #[command(flatten)]
+        filter: scan::Filter,
+        /// Print JSON lines with `repo`, `stash`, `date` and `message` instead
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+
+    /// Print TODO, FIXME and HACK comments in tracked files of managed repositories
+    Todo {
+        /// Only print comments of the keyword, e.g. `FIXME` (can be repeated)
+        #[arg(long = "kind", short, value_name = "KEYWORD")]
+        kinds: Vec<String>,
+        #[command(flatten)]
+        filter: scan::Filter,
+        /// Print JSON lines with `repo`, `file`, `line`, `kind` and `text` instead
+        #[arg(long, default_value_t = false)]
+        json: bool,
+        /// Number of repositories to search in parallel
+        #[arg(long, short)]
+        jobs: Option<usize>,
+    },
+
+    /// Pin repositories so that `list` shows them first and `prune --stale` keeps them, or
+    /// print the pinned ones
+    Pin { repos: Vec<String> },
+
+    /// Unpin repositories
+    Unpin {
+        #[arg(required = true)]
+        repos: Vec<String>,
+    },
+
+    /// Manage tags of repositories
+    Tag {
+        #[command(subcommand)]
+        command: TagCommand,
+    },
+
+    /// Manage notes on repositories, shown by `list -v` and `list --json`
+    Note {
+        #[command(subcommand)]
+        command: NoteCommand,
+    },
+
+    /// Set up the root directory, the protocol and the default host
+    Init {
+        /// Root directory of repositories
+        #[arg(long)]
+        root: Option<PathBuf>,
+        /// Protocol used to complete specs
+        #[arg(long, value_parser = ["https", "ssh"])]
+        protocol: Option<String>,
+        /// Host assumed for `OWNER/NAME` specs
+        #[arg(long)]
+        host: Option<String>,
+    },
+
+    /// Print shell functions, key bindings and completions to evaluate in the rc file
+    ShellInit { shell: shell::Shell },
+
+    /// Open the page of a repository on its forge in the browser, or of a file in it
+    Open {
+        /// Defaults to the repository containing the current directory
+        repo: Option<String>,
+        /// File to link to, relative to the repository if one is given, or else to the current
+        /// directory
+        #[arg(long)]
+        file: Option<PathBuf>,
+        /// Line of the file to link to
+        #[arg(long, requires = "file")]
+        line: Option<u32>,
+        /// Link to the current commit instead of the current branch, so that the link keeps
+        /// pointing to the same lines
+        #[arg(long, default_value_t = false)]
+        commit: bool,
+        /// Remote whose forge to open
+        #[arg(long, default_value = "origin")]
+        remote: String,
+        /// Print the URL instead of opening it
+        #[arg(long, short, default_value_t = false)]
+        print: bool,
+    },
+
+    /// Print the forge URL of a commit of the current repository, or of a file at the commit
+    Url {
+        /// Commit, branch, tag or an expression like `HEAD~2`
+        #[arg(default_value = "HEAD")]
+        rev: String,
+        /// File at the commit, relative to the current directory
+        file: Option<PathBuf>,
+        /// Line of the file to link to
+        #[arg(long, requires = "file")]
+        line: Option<u32>,
+        /// Remote whose forge to link to
+        #[arg(long, default_value = "origin")]
+        remote: String,
+    },
+
+    /// Rewrite remotes of managed repositories
+    Remote {
+        #[command(subcommand)]
+        command: RemoteCommand,
+    },
+
+    /// Manage API tokens in the encrypted vault
+    Token {
+        #[command(subcommand)]
+        command: TokenCommand,
+    },
+
+    /// Check who grm is authenticated as on each host, with the API token and over SSH
+    ///
+    /// Hosts default to the default host and every host named in `grm.<host>.*` keys. Fails if
+    /// any credential does not work, so it can be run before a long bulk operation.
+    Whoami {
+        /// Check only these hosts
+        hosts: Vec<String>,
+        /// Skip the SSH checks
+        #[arg(long, default_value_t = false)]
+        no_ssh: bool,
+        /// Print JSON lines with `host`, `api`, `ssh`, `key` and `error` instead
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+
+    /// Name subdirectories of monorepos to refer to them like repositories
+    Project {
+        #[command(subcommand)]
+        command: ProjectCommand,
+    },
+
+    /// Manage user-defined command aliases
+    Alias {
+        #[command(subcommand)]
+        command: AliasCommand,
+    },
+
+    /// Show help of a command or a topic such as `spec` and `config`
+    Help {
+        topic: Vec<String>,
+        /// Print a man page in roff format
+        #[arg(long, conflicts_with = "topic", default_value_t = false)]
+        man: bool,
+    },
+
+    /// Keep the index for `list --cached` up to date by watching the root directories
+    Watch,
+
+    /// Print repositories for pickers such as fzf, most recently used first, from the index
+    ///
+    /// Each line has tab-separated name, path, host and the Unix time of the last commit or
+    /// checkout, which is empty if unknown.
+    CompletionData {
+        /// Print JSON lines with `name`, `path`, `host` and `last_used` instead
+        #[arg(long, default_value_t = false)]
+        json: bool,
+        /// Scan the root directories again instead of using the index
+        #[arg(long, default_value_t = false)]
+        refresh: bool,
+    },
+
+    /// Answer JSON-RPC requests of editor plugins: list, resolve, get and status
+    Serve {
+        /// Unix domain socket to listen on, defaulting to `grm.sock` in the runtime directory
+        #[arg(long, value_name = "PATH")]
+        socket: Option<PathBuf>,
+        /// Listen on a TCP address such as `127.0.0.1:7878` instead
+        #[arg(long, value_name = "ADDR", conflicts_with = "socket")]
+        tcp: Option<String>,
+        /// Accept a --tcp address other hosts can connect to, although requests are not
+        /// authenticated
+        #[arg(long, requires = "tcp")]
+        allow_remote: bool,
+    },
+
+    /// Print the directories and files grm uses
+    Paths {
+        /// Print only this one: root, config, vault, history, trash, archive, known_hosts, objects,
+        /// cache or socket
+        name: Option<String>,
+    },
+
+    /// Show the log of operations that modified repositories
+    History {
+        /// Show only the last N entries
+        #[arg(long, short = 'n')]
+        limit: Option<usize>,
+    },
+}
+
+impl CliCommand {
+    /// Whether the command modifies repositories or the root directory
+    fn is_mutating(&self) -> bool {
+        match self {
+            Self::Root
+            | Self::List { .. }
+            | Self::Du { .. }
+            | Self::Stats { .. }
+            | Self::Verify { .. }
+            | Self::License { .. }
+            | Self::Devcontainer { .. }
+            | Self::Grep { .. }
+            | Self::Todo { .. }
+            | Self::Stashes { .. }
+            | Self::Status { .. }
+            | Self::Contributors { .. }
+            | Self::History { .. }
+            | Self::Help { .. }
+            | Self::Whoami { .. }
+            | Self::Open { .. }
+            | Self::Url { .. }
+            | Self::ShellInit { .. }
+            | Self::Paths { .. }
+            | Self::Watch
+            | Self::CompletionData { .. }
+            | Self::Serve { .. } => false,
+            Self::Doctor { mark } => *mark,
+            Self::DiffConfig { fix, .. } => *fix,
+            Self::Branches { delete, .. } => *delete,
+            Self::Prune { suggest, .. } => !*suggest,
+            Self::Tag { command } => !matches!(command, TagCommand::List { .. }),
+            Self::Note { command } => !matches!(command, NoteCommand::List { .. }),
+            Self::Alias { command } => !matches!(command, AliasCommand::List),
+            Self::Project { command } => !matches!(command, ProjectCommand::List),
+            Self::Token { command } => !matches!(command, TokenCommand::List),
+            Self::Pin { repos } => !repos.is_empty(),
+            Self::Remote { command } => match command {
+                RemoteCommand::SetProtocol { dry_run, .. } => !*dry_run,
+            },
+            Self::Trash { command } => !matches!(command, TrashCommand::List),
+            Self::Bundle { command } => matches!(command, BundleCommand::Apply { .. }),
+            Self::Get { .. }
+            | Self::New { .. }
+            | Self::Archive { .. }
+            | Self::Restore { .. }
+            | Self::Remove { .. }
+            | Self::Update { .. }
+            | Self::Replicate { .. }
+            | Self::SyncFork { .. }
+            | Self::Gc { .. }
+            | Self::Foreach { .. }
+            | Self::Unpin { .. }
+            | Self::Init { .. } => true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum Print {
+    /// The path of the repository
+    Path,
+    /// The origin URL
+    Origin,
+    /// Nothing
+    None,
+    /// The origin URL and the path, one per line
+    All,
+}
+
+impl Print {
+    fn print(self, origin_url: Option<&Url>, path: &Path) {
+        if matches!(self, Self::Origin | Self::All) {
+            if let Some(origin_url) = origin_url {
+                println!("{origin_url}");
+            }
+        }
+        if matches!(self, Self::Path | Self::All) {
+            println!("{}", path.display());
+        }
+    }
+}
+
+#[derive(clap::Subcommand)]
+enum RemoteCommand {
+    /// Switch remote URLs between HTTPS and SSH, e.g. after changing how to authenticate
+    ///
+    /// Push URLs set explicitly are left as they are.
+    SetProtocol {
+        protocol: Protocol,
+        /// Only rewrite remotes on this host
+        #[arg(long)]
+        host: Option<String>,
+        /// Name of the remote to rewrite
+        #[arg(long, default_value = "origin")]
+        remote: String,
+        #[command(flatten)]
+        filter: scan::Filter,
+        /// Print the changes without rewriting anything
+        #[arg(long, short = 'n', default_value_t = false)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum Protocol {
+    Https,
+    Ssh,
+}
+
+#[derive(clap::Subcommand)]
+enum TokenCommand {
+    /// Store the token of a host, or of an owner on the host, prompting for it
+    Set {
+        #[arg(value_name = "HOST[/OWNER]")]
+        host: String,
+        /// Read the token from the environment variable instead
+        #[arg(long, value_name = "VAR")]
+        from_env: Option<String>,
+    },
+    /// Delete the token of a host
+    Remove { host: String },
+    /// Print hosts with a stored token
+    List,
+}
+
+#[derive(clap::Subcommand)]
+enum ProjectCommand {
+    /// Register a directory inside a repository, e.g. `grm project add services/a svc-a`
+    Add { path: PathBuf, name: String },
+    /// Unregister a project
+    Remove { name: String },
+    /// Print projects
+    List,
+}
+
+#[derive(clap::Subcommand)]
+enum AliasCommand {
+    /// Define an alias, e.g. `grm alias set up "update --prune"`
+    Set { name: String, definition: String },
+    /// Delete an alias
+    Remove { name: String },
+    /// Print aliases
+    List,
+}
+
+/// The result of `get` and `new` printed with `--json`
+#[derive(serde::Serialize)]
+struct Created<'a> {
+    origin: Option<&'a Url>,
+    path: &'a Path,
+    default_branch: Option<String>,
+    /// Seconds taken
+    elapsed: f64,
+    existed: bool,
+}
+
+impl<'a> Created<'a> {
+    fn new(
+        origin: Option<&'a Url>,
+        path: &'a Path,
+        started: std::time::Instant,
+        existed: bool,
+    ) -> Result<Self> {
+        Ok(Self {
+            origin,
+            path,
+            default_branch: head::Head::read(path)?.branch,
+            elapsed: started.elapsed().as_secs_f64(),
+            existed,
+        })
+    }
+
+    fn print(&self) -> Result<()> {
+        println!("{}", serde_json::to_string(self)?);
+        Ok(())
+    }
+}
+
+/// A repository printed by `list --json`
+#[derive(serde::Serialize)]
+struct Listed<'a> {
+    name: String,
+    path: &'a Path,
+    /// Name of the repository containing this one, found with `--nested`
+    parent: Option<String>,
+    /// `archived` or `read-only`, found with `--check-upstream`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    upstream: Option<&'static str>,
+    /// Set with `grm note set`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    note: Option<&'a str>,
+}
+
+impl Listed<'_> {
+    fn print(&self) -> Result<()> {
+        println!("{}", serde_json::to_string(self)?);
+        Ok(())
+    }
+}
+
+#[derive(clap::Subcommand)]
+enum NoteCommand {
+    /// Attach a note to a repository, replacing the existing one
+    Set { repo: String, note: String },
+    /// Delete the note of a repository
+    Remove { repo: String },
+    /// Print the note of a repository, or every note
+    List { repo: Option<String> },
+}
+
+#[derive(clap::Subcommand)]
+enum TagCommand {
+    /// Attach tags to a repository
+    Add {
+        repo: String,
+        #[arg(required = true)]
+        tags: Vec<String>,
+    },
+    /// Detach tags from a repository
+    Remove {
+        repo: String,
+        #[arg(required = true)]
+        tags: Vec<String>,
+    },
+    /// Print tags of a repository, or every tag in use
+    List { repo: Option<String> },
+}
+
+#[derive(clap::Subcommand)]
+enum TrashCommand {
+    /// Print removed repositories
+    List,
+    /// Move a removed repository back to its original path
+    Restore {
+        /// ID or name of the removed repository, the latest one is chosen for a name
+        item: String,
+    },
+    /// Delete removed repositories permanently
+    Empty {
+        /// Delete only the ones kept longer than `grm.trashRetentionDays` (defaults to 30)
+        #[arg(long, default_value_t = false)]
+        expired: bool,
+    },
+}
+
+#[derive(clap::Subcommand)]
+enum DevcontainerCommand {
+    /// Start the dev container of a managed repository
+    Up { repo: String },
+}
+
+#[derive(clap::Subcommand)]
+enum BundleCommand {
+    /// Write every ref of a managed repository into a bundle file
+    Create {
+        repo: String,
+        /// Defaults to `<name>.bundle` in the current directory
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Clone a bundle file to the path where `grm get` would clone the repository
+    Apply {
+        file: PathBuf,
+        /// Where the repository is fetched from once the network is available
+        repo: String,
+        /// Use SSH for the origin
+        #[arg(long, default_value_t = false)]
+        ssh: bool,
+        #[arg(long, default_value = "origin")]
+        origin_name: String,
     },
 }
 
-fn main() -> Result<()> {
-    let command = {
-        let stdin = std::io::stdin().lock();
-        if stdin.is_terminal() {
-            CliCommand::parse()
-        } else {
-            CliCommand::parse_from(std::env::args().chain(stdin.lines().map_while(Result::ok)))
+fn main() -> std::process::ExitCode {
+    match run() {
+        Ok(()) => std::process::ExitCode::SUCCESS,
+        Err(err) if err.is::<grep::NoMatch>() => std::process::ExitCode::FAILURE,
+        Err(err) => {
+            eprintln!("Error: {err:?}");
+            if err.is::<select::Ambiguous>() {
+                std::process::ExitCode::from(select::AMBIGUOUS_EXIT_CODE)
+            } else {
+                std::process::ExitCode::FAILURE
+            }
+        }
+    }
+}
+
+fn run() -> Result<()> {
+    let mut timings = startup::Timings::new();
+    let config = startup::LazyConfig::default();
+    let command = help::augment(Cli::command());
+    timings.mark("command");
+    let args: Vec<String> = std::env::args().collect();
+    let args = alias::expand(&config, &command, args)?;
+    timings.mark("aliases");
+    let matches = command.get_matches_from(args);
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+    timings.mark("parse");
+    let read_only = cli.read_only
+        || cfg!(feature = "read-only")
+        || config
+            .get()?
+            .get_bool(concat!(env!("CARGO_PKG_NAME"), ".readOnly"))
+            .unwrap_or(false);
+    ensure!(!read_only || !cli.command.is_mutating(), t!(ReadOnly));
+    timings.mark("config");
+
+    let mut operation = Vec::new();
+    let mut matches = &matches;
+    while let Some((name, sub_matches)) = matches.subcommand() {
+        operation.push(name);
+        matches = sub_matches;
+    }
+    let operation = operation.join(" ");
+
+    let is_mutating = cli.command.is_mutating();
+    let mut entry = history::Entry::new(operation.clone());
+    let started = std::time::Instant::now();
+    let result = execute(cli.command, cli.profile.as_deref(), read_only, &mut entry);
+    timings.mark("execute");
+    let config = config.get()?;
+    if is_mutating {
+        if let Err(err) = entry.finish(config, &result) {
+            warn!("failed to record the operation: {err:#}");
+        }
+    }
+    notify::finished(config, cli.notify, &operation, started.elapsed(), &result);
+    if cli.profile_startup {
+        timings.mark("finish");
+        timings.print();
+    }
+    result
+}
+
+/// Run the command, filling `entry` with what it operated on
+fn execute(
+    command: CliCommand,
+    profile_name: Option<&str>,
+    read_only: bool,
+    entry: &mut history::Entry,
+) -> Result<()> {
+    match command {
+        CliCommand::Root => {
+            let config = open_config(false)?;
+            let profile = Profile::load(&config, profile_name)?;
+            println!("{}", get_root_dir(&config, &profile)?.display());
+        }
+
+        CliCommand::Doctor { mark } => {
+            let config = open_config(false)?;
+            let profile = Profile::load(&config, profile_name)?;
+            let candidates = root::Candidates::new(&config, &profile);
+            if mark {
+                let root = candidates.active().context("failed to get root dir")?;
+                root::mark(root)?;
+            }
+            let describe = |path: &Option<PathBuf>| match path {
+                None => "(unset)".to_string(),
+                Some(path) if root::is_marked(path) => format!("{} (marked)", path.display()),
+                Some(path) if path.exists() => format!("{} (not marked)", path.display()),
+                Some(path) => format!("{} (missing)", path.display()),
+            };
+            if candidates.env.is_some() {
+                println!("GRM_ROOT: {}", describe(&candidates.env));
+            }
+            if candidates.profile.is_some() {
+                println!("profile root: {}", describe(&candidates.profile));
+            }
+            println!("configured root: {}", describe(&candidates.configured));
+            println!("fallback root: {}", describe(&candidates.fallback));
+            root::check(&candidates);
+
+            if let Some(root) = candidates.active() {
+                let temp_dirs = scan::walk(root, scan::get_depth(&config), false).temp_dirs;
+                let removed = if read_only {
+                    Vec::new()
+                } else {
+                    staging::clean(temp_dirs.clone())
+                };
+                for temp in temp_dirs {
+                    if removed.contains(&temp) {
+                        println!("removed a stale temporary directory: {}", temp.display());
+                    } else {
+                        println!("clone in progress: {}", temp.display());
+                    }
+                }
+            }
+        }
+
+        CliCommand::List {
+            absolute,
+            remote,
+            owner,
+            last_commit,
+            format,
+            cached,
+            json,
+            check_upstream,
+            refresh_upstream,
+            projects,
+            tree,
+            filter,
+        } => {
+            let config = open_config(false)?;
+            let profile = Profile::load(&config, profile_name)?;
+            let mut upstreams = check_upstream.then(upstream::Cache::load).transpose()?;
+            let mut upstream_of = |name: &str| {
+                let upstreams = upstreams.as_mut()?;
+                upstreams.label(&config, &profile, name, refresh_upstream)
+            };
+
+            if remote {
+                let owners = if owner.is_empty() {
+                    get_owners(&config, &profile)?
+                } else {
+                    owner
+                };
+                for owner in owners {
+                    list_remote(&config, &profile, absolute, &owner)?;
+                }
+                return Ok(());
+            }
+
+            let filter = filter.with_config(&config)?;
+            let notes = note::Notes::load()?;
+            let mut has_notes = false;
+            let mut rows = vec![["REPO", "BRANCH", "DATE", "COMMIT", "SUBJECT"]
+                .map(String::from)
+                .to_vec()];
+            let mut paths = Vec::new();
+            let root_dirs = get_root_dirs(&config, &profile)?;
+            for root_dir in &root_dirs {
+                let walk = match cached.then(|| index::load(root_dir)).transpose()? {
+                    Some(Some(repos)) => scan::Walk {
+                        repos,
+                        ..Default::default()
+                    },
+                    _ => scan::walk(root_dir, filter.depth(), filter.nested),
+                };
+                if !read_only {
+                    for temp in staging::clean(walk.temp_dirs) {
+                        info!("removed a stale temporary directory: {}", temp.display());
+                    }
+                }
+                if json {
+                    for path in filter.apply(root_dir, walk.repos.clone()) {
+                        let parent = scan::parent(&walk.repos, &path);
+                        let name = repo_name(root_dir, &path);
+                        Listed {
+                            upstream: upstream_of(&name),
+                            note: notes.get(&name),
+                            name,
+                            path: &path,
+                            parent: parent.map(|parent| repo_name(root_dir, parent)),
+                        }
+                        .print()?;
+                    }
+                    continue;
+                }
+                let repos = filter.apply(root_dir, walk.repos);
+                paths.extend(repos.into_iter().map(|path| (root_dir.clone(), path)));
+            }
+            // pinned repositories first, keeping the order otherwise
+            let pinned = pinned::list(&config)?;
+            paths.sort_by_key(|(root_dir, path)| !pinned.contains(&repo_name(root_dir, path)));
+            if tree {
+                let names: Vec<_> = paths
+                    .iter()
+                    .map(|(root_dir, path)| repo_name(root_dir, path))
+                    .collect();
+                for line in output::format_tree(names.iter().map(String::as_str)) {
+                    println!("{line}");
+                }
+                return Ok(());
+            }
+            for (root_dir, path) in paths {
+                if let Some(format) = &format {
+                    println!("{}", format_repo(format, &root_dir, &path)?);
+                    continue;
+                }
+                let name = if absolute {
+                    &path
+                } else {
+                    path.strip_prefix(&root_dir).unwrap_or(&path)
+                };
+                let name = name.display().to_string().replace('\\', "/");
+                let upstream = upstream_of(&repo_name(&root_dir, &path));
+                if !last_commit {
+                    match upstream {
+                        Some(upstream) => println!("{name} ({upstream})"),
+                        None => println!("{name}"),
+                    }
+                    continue;
+                }
+                let head = head::Head::read(&path)?;
+                let mut row = vec![name, head.branch.unwrap_or_else(|| "(detached)".into())];
+                match head.commit {
+                    Some(commit) => row.extend([
+                        commit.time.format("%Y-%m-%d").to_string(),
+                        commit.short_id,
+                        commit.summary,
+                    ]),
+                    None => row.extend(["-".into(), "-".into(), "-".into()]),
+                }
+                if check_upstream {
+                    row.push(upstream.unwrap_or("-").to_string());
+                }
+                if let Some(note) = notes.get(&repo_name(&root_dir, &path)) {
+                    has_notes = true;
+                    row.push(note.to_string());
+                }
+                rows.push(row);
+            }
+            if last_commit {
+                if check_upstream {
+                    rows[0].push("UPSTREAM".into());
+                }
+                if has_notes {
+                    rows[0].push("NOTE".into());
+                }
+                output::print_table(&rows);
+            }
+            if projects && filter.tags.is_empty() {
+                let query = filter.query.as_deref().unwrap_or_default().to_lowercase();
+                for (name, path) in project::list(&config)? {
+                    let root_dir = root_dirs.iter().find(|root| path.starts_with(root));
+                    let shown = match root_dir {
+                        Some(root_dir) if !absolute => repo_name(root_dir, &path),
+                        _ => path.display().to_string(),
+                    };
+                    if name.to_lowercase().contains(&query) || shown.to_lowercase().contains(&query)
+                    {
+                        println!("{shown}");
+                    }
+                }
+            }
+            if let Some(upstreams) = &upstreams {
+                upstreams.save()?;
+            }
+        }
+
+        CliCommand::Get {
+            repo: spec,
+            file,
+            stdin,
+            ssh,
+            depth,
+            shallow_since,
+            shallow_exclude,
+            backend,
+            print,
+            wait,
+            origin_name,
+            json,
+            copy,
+            insecure_skip_tls_verify,
+            no_checkout,
+            sparse,
+            sign,
+            devcontainer,
+            separate_git_dir,
+            reference,
+            dissociate,
+            yes,
+            events,
+        } if file.is_some() || stdin || events.is_some() => {
+            entry.spec = file.as_ref().map(|file| file.display().to_string());
+            let specs = match specs::load(file.as_deref(), stdin)? {
+                Some(specs) => specs,
+                None => Vec::from_iter(spec),
+            };
+            let events = events::Events::new(events);
+            let config = open_config(false)?;
+            let count = specs.len();
+            let mut failures = 0;
+            for repo in specs {
+                let command = CliCommand::Get {
+                    repo: Some(repo.clone()),
+                    file: None,
+                    stdin: false,
+                    ssh,
+                    depth,
+                    shallow_since: shallow_since.clone(),
+                    shallow_exclude: shallow_exclude.clone(),
+                    backend,
+                    // the paths are in the events instead
+                    print: if events.is_enabled() {
+                        Print::None
+                    } else {
+                        print
+                    },
+                    wait,
+                    origin_name: origin_name.clone(),
+                    json,
+                    copy,
+                    insecure_skip_tls_verify,
+                    no_checkout,
+                    sparse: sparse.clone(),
+                    sign,
+                    devcontainer,
+                    separate_git_dir,
+                    reference: reference.clone(),
+                    dissociate,
+                    yes,
+                    events: None,
+                };
+                let mut entry = history::Entry::new("get".into());
+                events.emit(&events::Event::Started { repo: &repo });
+                let result = execute(command, profile_name, read_only, &mut entry);
+                let path = entry.path.clone();
+                // each clone is logged on its own, in addition to the whole run
+                if let Err(err) = entry.finish(&config, &result) {
+                    warn!("failed to record the operation: {err:#}");
+                }
+                match result {
+                    Ok(()) => events.emit(&events::Event::Finished {
+                        repo: &repo,
+                        path: path.as_deref(),
+                        message: None,
+                    }),
+                    Err(err) => {
+                        events.emit(&events::Event::Failed {
+                            repo: &repo,
+                            error: &format!("{err:#}"),
+                        });
+                        warn!("{repo}: {err:#}");
+                        failures += 1;
+                    }
+                }
+            }
+            if failures > 0 {
+                bail!(t!(ReposFailed, failures, count));
+            }
+        }
+
+        CliCommand::Get {
+            repo,
+            file: _,
+            stdin: _,
+            ssh,
+            depth,
+            shallow_since,
+            shallow_exclude,
+            backend,
+            print,
+            wait,
+            origin_name,
+            json,
+            copy,
+            insecure_skip_tls_verify,
+            no_checkout,
+            sparse,
+            sign,
+            devcontainer,
+            separate_git_dir,
+            reference,
+            dissociate,
+            yes,
+            events: _,
+        } => {
+            let started = std::time::Instant::now();
+            let repo = repo.context("no repository is given")?;
+            entry.spec = Some(repo.clone());
+            let config = open_config(true)?;
+            let (profile, origin_url) = resolve_origin(&config, profile_name, ssh, &repo)?;
+            entry.url = Some(origin_url.clone());
+            let root_dir = get_host_root_dir(&config, &profile, origin_url.host_str())?;
+            let username = get_username(&config, &profile)?;
+            ensure!(
+                !sign
+                    || profile
+                        .signing_key(&config, origin_url.host_str())
+                        .is_some(),
+                "no signing key is configured for {}",
+                origin_url.host_str().unwrap_or("the host")
+            );
+
+            root::prepare(&root_dir)?;
+
+            let path = &get_new_repo_path(&config, &root_dir, &origin_url)?;
+            entry.path = Some(path.clone());
+            info!("origin: {origin_url}");
+            info!("path: {}", path.display());
+
+            let _lock = lock::Lock::acquire(path, wait)?;
+
+            let existed = Repository::open(path).is_ok();
+            if existed {
+                info!("{}", t!(AlreadyCloned));
+            } else {
+                if !yes {
+                    confirm_size(&config, &profile, &origin_url)?;
+                }
+                let mut backend = match backend {
+                    Some(backend) => backend,
+                    None => Backend::from_config(&config)?,
+                };
+                let reference = match reference.as_deref() {
+                    None => None,
+                    Some("") => {
+                        let host_dir = root_dir.join(origin_url.domain().unwrap_or_default());
+                        let repos = scan::repos(&host_dir, scan::get_depth(&config));
+                        let reference =
+                            alternates::find_same_name(path, &repos).with_context(|| {
+                                format!(
+                                    "no repository of the same name to reference in `{}`",
+                                    host_dir.display()
+                                )
+                            })?;
+                        Some(reference.clone())
+                    }
+                    Some(repo) => Some(resolve_repo(&config, &profile, repo)?.1),
+                };
+                let host = origin_url.host_str().unwrap_or_default();
+                let reference = match reference {
+                    None if objcache::is_enabled(&config, host) => {
+                        let cache = objcache::get_cache_path(host)?;
+                        info!("updating the object cache: {}", cache.display());
+                        match objcache::update(&cache, &repo_name(&root_dir, path), &origin_url) {
+                            Ok(()) => Some(cache),
+                            Err(err) => {
+                                warn!("cloning without the object cache: {err:#}");
+                                None
+                            }
+                        }
+                    }
+                    reference => reference,
+                };
+                if let Some(reference) = &reference {
+                    ensure!(
+                        Repository::open(reference).is_ok(),
+                        t!(NotRepository, reference.display())
+                    );
+                    if backend != Backend::Git {
+                        info!("cloning with the git backend to borrow objects");
+                        backend = Backend::Git;
+                    }
+                    info!("reference: {}", reference.display());
+                }
+                if (shallow_since.is_some() || !shallow_exclude.is_empty())
+                    && backend == Backend::Git2
+                {
+                    info!("cloning with the git backend to cut the history by date or ref");
+                    backend = Backend::Git;
+                }
+                ensure!(
+                    !path.exists() || path.read_dir()?.next().is_none(),
+                    t!(NotEmptyDir, path.display())
+                );
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                let temp = staging::temp_path(path)?;
+                let lookup_token = || {
+                    if origin_url.scheme() != "https" {
+                        return None;
+                    }
+                    let owner = origin_url.path_segments().and_then(|mut s| s.next());
+                    profile.token.clone().or_else(|| {
+                        forge::token(&config, origin_url.host_str().unwrap_or_default(), owner)
+                    })
+                };
+                let token = backend::Token::new(&lookup_token);
+                let result = backend.build()?.clone_repo(
+                    &origin_url,
+                    &temp,
+                    &CloneOptions {
+                        config: &config,
+                        username: &username,
+                        depth,
+                        shallow_since: shallow_since.as_deref(),
+                        shallow_exclude: &shallow_exclude,
+                        proxy: proxy::resolve(&config, &origin_url),
+                        ssh_key: profile.ssh_key.as_deref(),
+                        remote_name: &origin_name,
+                        checkout: !no_checkout && sparse.is_empty(),
+                        token: &token,
+                        verification: cert::resolve(&config, &origin_url, insecure_skip_tls_verify),
+                        reference: reference.as_deref(),
+                        dissociate,
+                        headers: headers::generated(&config, host)?,
+                    },
+                );
+                let result = result.and_then(|()| {
+                    if sparse.is_empty() {
+                        return Ok(());
+                    }
+                    sparse_checkout(&temp, &sparse, !no_checkout)
+                });
+                if let Err(err) = result {
+                    _ = std::fs::remove_dir_all(&temp);
+                    return Err(err);
+                }
+                if path.exists() {
+                    std::fs::remove_dir(path)?;
+                }
+                std::fs::rename(&temp, path)?;
+                if separate_git_dir || gitdir::is_enabled(&config) {
+                    let git_dir = gitdir::git_dir_path(
+                        &gitdir::get_git_dir_root(&config)?,
+                        &repo_name(&root_dir, path),
+                    );
+                    gitdir::separate(path, &git_dir)?;
+                    info!("git dir: {}", git_dir.display());
+                }
+                let repository = Repository::open(path)?;
+                profile.apply_identity(&config, origin_url.host_str(), &repository)?;
+                if sign {
+                    profile.apply_signing(&config, origin_url.host_str(), &repository)?;
+                }
+                set_push_url(&config, &repository, &origin_name, &origin_url)?;
+                transport::Transport {
+                    remote: origin_name.clone(),
+                    protocol: origin_url.scheme().to_string(),
+                    profile: profile.name.clone(),
+                    ssh_key: profile.ssh_key.clone(),
+                    token: token.was_used(),
+                }
+                .record(&repository)?;
+                if auto_upstream(&config) {
+                    if let Err(err) = add_fork_upstream(&config, &profile, path, &origin_url) {
+                        warn!("failed to add the upstream of the fork: {err:#}");
+                    }
+                }
+            }
+
+            if !existed {
+                envtrust::apply(envtrust::Trust::from_config(&config)?, path);
+            }
+            if devcontainer {
+                devcontainer::up(path)?;
+            } else if !existed && devcontainer::exists(path) {
+                info!(
+                    "start its dev container with: grm devcontainer up {}",
+                    repo_name(&root_dir, path)
+                );
+            }
+
+            if json {
+                Created::new(Some(&origin_url), path, started, existed)?.print()?;
+            } else {
+                print.print(Some(&origin_url), path);
+            }
+            if copy {
+                copy_path(path);
+            }
+        }
+
+        CliCommand::New {
+            repo,
+            ssh,
+            raw,
+            here,
+            move_to_root,
+            link,
+            print,
+            origin_name,
+            json,
+            copy,
+            workspace,
+        } => {
+            let started = std::time::Instant::now();
+            let (repo, here) = match repo {
+                Some(repo) => (repo, here),
+                None => (current_dir_name()?, true),
+            };
+            ensure!(
+                here || !(move_to_root || link),
+                "--move and --link require --here or no repository"
+            );
+            entry.spec = Some(repo.clone());
+            let config = open_config(true)?;
+            let (profile, origin_url) = if raw {
+                (Profile::load(&config, profile_name)?, None)
+            } else {
+                let (profile, origin_url) = resolve_origin(&config, profile_name, ssh, &repo)?;
+                (profile, Some(origin_url))
+            };
+            entry.url = origin_url.clone();
+            let host = origin_url.as_ref().and_then(Url::host_str);
+            let root_dir = get_host_root_dir(&config, &profile, host)?;
+
+            if !here || move_to_root || link {
+                root::prepare(&root_dir)?;
+            }
+
+            let mut opts = git2::RepositoryInitOptions::new();
+            opts.no_reinit(true);
+            opts.initial_head(&get_default_branch(&config));
+
+            let target = match &origin_url {
+                None => root_dir.join(repo),
+                Some(origin_url) => {
+                    info!("origin: {origin_url}");
+                    get_new_repo_path(&config, &root_dir, origin_url)?
+                }
+            };
+            let path = if here {
+                std::env::current_dir()?
+            } else {
+                target.clone()
+            };
+            info!("path: {}", path.display());
+            entry.path = Some(if move_to_root || link { &target } else { &path }.clone());
+            let _lock = lock::Lock::acquire(&target, false)?;
+            if move_to_root || link {
+                ensure!(!target.exists(), "`{}` already exists", target.display());
+            }
+
+            let repository = Repository::init_opts(&path, &opts)?;
+            if let Some(origin_url) = &origin_url {
+                repository.remote(&origin_name, origin_url.as_str())?;
+                set_push_url(&config, &repository, &origin_name, origin_url)?;
+            }
+            let host = origin_url.as_ref().and_then(Url::host_str);
+            profile.apply_identity(&config, host, &repository)?;
+            profile.apply_signing(&config, host, &repository)?;
+            if let Some(members) = &workspace {
+                workspace::scaffold(&config, &path, members)?;
+            }
+
+            if move_to_root || link {
+                if let Some(parent) = target.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                if move_to_root {
+                    std::fs::rename(&path, &target).with_context(|| {
+                        format!("failed to move `{}` into the root", path.display())
+                    })?;
+                    info!("moved: {}", target.display());
+                } else {
+                    symlink_dir(&path, &target).with_context(|| {
+                        format!("failed to link `{}` into the root", path.display())
+                    })?;
+                    info!("linked: {}", target.display());
+                }
+            }
+            let path = if move_to_root || link { &target } else { &path };
+            if json {
+                Created::new(origin_url.as_ref(), path, started, false)?.print()?;
+            } else {
+                print.print(origin_url.as_ref(), path);
+            }
+            if copy {
+                copy_path(path);
+            }
+        }
+
+        CliCommand::Archive {
+            repo,
+            ignored,
+            force,
+        } => {
+            entry.spec = Some(repo.clone());
+            let config = open_config(false)?;
+            let profile = Profile::load(&config, profile_name)?;
+            let (root_dir, path) = resolve_repo(&config, &profile, &repo)?;
+            entry.path = Some(path.clone());
+            ensure!(
+                Repository::open(&path).is_ok(),
+                t!(NotRepository, path.display())
+            );
+            ensure!(
+                !path.is_symlink(),
+                "`{}` is a symbolic link",
+                path.display()
+            );
+
+            let name = path.strip_prefix(&root_dir)?;
+            let archive = archive::Archive::new(&archive::get_archive_dir(&config)?, name);
+            ensure!(!archive.exists(), t!(AlreadyArchived, name.display()));
+            safety::confirm(
+                &repo_name(&root_dir, &path),
+                &safety::check_archive(&path)?,
+                force,
+            )?;
+            let repos = repo_paths(all_repos(&config, &profile)?);
+            release_dependents(&root_dir, &path, &repos, false)?;
+            let git_dir_root = gitdir::get_git_dir_root(&config)?;
+            if let Some(git_dir) = gitdir::find_separated(&path, &git_dir_root) {
+                gitdir::join(&path, &git_dir)?;
+                remove_empty_parents(&git_dir, &git_dir_root);
+            }
+            archive.create(&path, ignored)?;
+
+            std::fs::remove_dir_all(&path)?;
+            remove_empty_parents(&path, &root_dir);
+            info!("archived: {}", name.display());
+        }
+
+        CliCommand::Devcontainer {
+            command: DevcontainerCommand::Up { repo },
+        } => {
+            let config = open_config(false)?;
+            let profile = Profile::load(&config, profile_name)?;
+            let (_, path) = resolve_repo(&config, &profile, &repo)?;
+            devcontainer::up(&path)?;
+        }
+
+        CliCommand::Bundle {
+            command: BundleCommand::Create { repo, output },
+        } => {
+            let config = open_config(false)?;
+            let profile = Profile::load(&config, profile_name)?;
+            let (root_dir, path) = resolve_repo(&config, &profile, &repo)?;
+            ensure!(
+                Repository::open(&path).is_ok(),
+                t!(NotRepository, path.display())
+            );
+            let output = match output {
+                Some(output) => output,
+                None => {
+                    let name = path
+                        .file_name()
+                        .context("failed to get the repository name")?;
+                    PathBuf::from(format!("{}.bundle", name.to_string_lossy()))
+                }
+            };
+            bundle::create(&path, &output)?;
+            info!("bundled: {}", repo_name(&root_dir, &path));
+            println!("{}", output.display());
+        }
+
+        CliCommand::Bundle {
+            command:
+                BundleCommand::Apply {
+                    file,
+                    repo,
+                    ssh,
+                    origin_name,
+                },
+        } => {
+            entry.spec = Some(repo.clone());
+            let config = open_config(true)?;
+            let (profile, origin_url) = resolve_origin(&config, profile_name, ssh, &repo)?;
+            entry.url = Some(origin_url.clone());
+            let root_dir = get_host_root_dir(&config, &profile, origin_url.host_str())?;
+            root::prepare(&root_dir)?;
+            let path = &get_new_repo_path(&config, &root_dir, &origin_url)?;
+            entry.path = Some(path.clone());
+            info!("origin: {origin_url}");
+            info!("path: {}", path.display());
+
+            let _lock = lock::Lock::acquire(path, false)?;
+            ensure!(
+                !path.exists() || path.read_dir()?.next().is_none(),
+                t!(NotEmptyDir, path.display())
+            );
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            let temp = staging::temp_path(path)?;
+            if let Err(err) = bundle::apply(&file, &temp, &origin_name, &origin_url) {
+                _ = std::fs::remove_dir_all(&temp);
+                return Err(err);
+            }
+            if path.exists() {
+                std::fs::remove_dir(path)?;
+            }
+            std::fs::rename(&temp, path)?;
+            let repository = Repository::open(path)?;
+            profile.apply_identity(&config, origin_url.host_str(), &repository)?;
+            set_push_url(&config, &repository, &origin_name, &origin_url)?;
+            println!("{}", path.display());
+        }
+
+        CliCommand::Restore { repo } => {
+            entry.spec = Some(repo.clone());
+            let config = open_config(false)?;
+            let profile = Profile::load(&config, profile_name)?;
+            let (root_dir, path) = resolve_repo(&config, &profile, &repo)?;
+            entry.path = Some(path.clone());
+
+            let name = path.strip_prefix(&root_dir)?;
+            let archive = archive::Archive::new(&archive::get_archive_dir(&config)?, name);
+            ensure!(archive.exists(), t!(NotArchived, name.display()));
+            archive.restore(&path)?;
+            println!("{}", path.display());
+        }
+
+        CliCommand::Remove {
+            repo,
+            file,
+            stdin,
+            permanent,
+            force,
+            dissociate,
+        } => {
+            entry.spec = repo
+                .clone()
+                .or_else(|| file.as_ref().map(|file| file.display().to_string()));
+            let specs = match specs::load(file.as_deref(), stdin)? {
+                Some(specs) => specs,
+                None => vec![repo.context("no repository is given")?],
+            };
+            let config = open_config(false)?;
+            let profile = Profile::load(&config, profile_name)?;
+            let mut paths = Vec::new();
+            for repo in specs {
+                paths.extend(if glob::is_pattern(&repo) {
+                    let filter = scan::Filter {
+                        query: Some(repo.clone()),
+                        ..Default::default()
+                    }
+                    .with_config(&config)?;
+                    let paths = filtered_repos(&config, &profile, &filter)?;
+                    ensure!(!paths.is_empty(), "no repository matches `{repo}`");
+                    if !force {
+                        let mut message = format!(
+                            "`{repo}` matches {} repositories; pass --force to remove them:",
+                            paths.len()
+                        );
+                        for (root_dir, path) in &paths {
+                            message.push_str(&format!("\n  {}", repo_name(root_dir, path)));
+                        }
+                        bail!(message);
+                    }
+                    paths
+                } else {
+                    vec![resolve_repo(&config, &profile, &repo)?]
+                });
+            }
+            paths.sort();
+            paths.dedup();
+            if let [(_, path)] = paths.as_slice() {
+                entry.path = Some(path.clone());
+            }
+
+            let trash = if permanent {
+                None
+            } else {
+                Some(trash::Trash::new(&config)?)
+            };
+            // check every listed repository before removing any of them
+            for (_, path) in &paths {
+                ensure!(
+                    path.is_symlink() || Repository::open(path).is_ok(),
+                    t!(NotRepository, path.display())
+                );
+            }
+            if permanent {
+                for (root_dir, path) in paths.iter().filter(|(_, path)| !path.is_symlink()) {
+                    let losses = safety::check(path)?;
+                    safety::confirm(&repo_name(root_dir, path), &losses, force)?;
+                }
+            }
+            let repos = repo_paths(all_repos(&config, &profile)?);
+            for (root_dir, path) in &paths {
+                release_dependents(root_dir, path, &repos, dissociate)?;
+            }
+            let git_dir_root = gitdir::get_git_dir_root(&config)?;
+            for (root_dir, path) in &paths {
+                remove_repo(root_dir, path, trash.as_ref(), &git_dir_root)?;
+            }
+            if let (Some(trash), Some(retention)) = (&trash, trash::get_retention(&config)) {
+                for item in trash.empty(Some(retention))? {
+                    info!("deleted from the trash: {} ({})", item.info.name, item.id);
+                }
+            }
+        }
+
+        CliCommand::Trash { command } => {
+            let config = open_config(false)?;
+            let trash = trash::Trash::new(&config)?;
+
+            match command {
+                TrashCommand::List => {
+                    let mut rows = vec![["ID", "REMOVED", "REPO"].map(String::from).to_vec()];
+                    for item in trash.items()? {
+                        let removed_at = item
+                            .removed_at()
+                            .map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string())
+                            .unwrap_or_else(|| item.info.removed_at.clone());
+                        rows.push(vec![item.id, removed_at, item.info.name]);
+                    }
+                    output::print_table(&rows);
+                }
+                TrashCommand::Restore { item } => {
+                    entry.spec = Some(item.clone());
+                    let item = trash.find(&item)?;
+                    let path = &item.info.path;
+                    entry.path = Some(path.clone());
+                    let _lock = lock::Lock::acquire(path, false)?;
+                    trash.restore(&item)?;
+                    println!("{}", path.display());
+                }
+                TrashCommand::Empty { expired } => {
+                    let retention = if expired {
+                        match trash::get_retention(&config) {
+                            Some(retention) => Some(retention),
+                            None => return Ok(()),
+                        }
+                    } else {
+                        None
+                    };
+                    for item in trash.empty(retention)? {
+                        info!("deleted: {} ({})", item.info.name, item.id);
+                    }
+                }
+            }
+        }
+
+        CliCommand::Du { bytes, filter } => {
+            let config = open_config(false)?;
+            let profile = Profile::load(&config, profile_name)?;
+            let filter = filter.with_config(&config)?;
+
+            let mut usages = Vec::new();
+            for (root_dir, path) in filtered_repos(&config, &profile, &filter)? {
+                let name = repo_name(&root_dir, &path);
+                match du::Usage::measure(&path) {
+                    Ok(usage) => usages.push((name, usage)),
+                    Err(err) => warn!("skipped `{name}`: {err:#}"),
+                }
+            }
+            usages.sort_by_key(|(_, usage)| std::cmp::Reverse(usage.total()));
+
+            let format = |size: u64| {
+                if bytes {
+                    size.to_string()
+                } else {
+                    du::format_size(size)
+                }
+            };
+            println!(
+                "{:>10}  {:>10}  {:>10}  {:>10}  REPO",
+                "TOTAL", "WORKTREE", ".git", "LFS"
+            );
+            let mut total = du::Usage::default();
+            for (name, usage) in &usages {
+                println!(
+                    "{:>10}  {:>10}  {:>10}  {:>10}  {name}",
+                    format(usage.total()),
+                    format(usage.worktree),
+                    format(usage.git),
+                    format(usage.lfs),
+                );
+                total.worktree += usage.worktree;
+                total.git += usage.git;
+                total.lfs += usage.lfs;
+            }
+            println!(
+                "{:>10}  {:>10}  {:>10}  {:>10}  total",
+                format(total.total()),
+                format(total.worktree),
+                format(total.git),
+                format(total.lfs),
+            );
+        }
+
+        CliCommand::Stats { top, filter } => {
+            let config = open_config(false)?;
+            let profile = Profile::load(&config, profile_name)?;
+            let mut repos = Vec::new();
+            for (root_dir, path) in
+                filtered_repos(&config, &profile, &filter.with_config(&config)?)?
+            {
+                match stats::RepoStats::collect(repo_name(&root_dir, &path), &path) {
+                    Ok(repo) => repos.push(repo),
+                    Err(err) => warn!("skipped `{}`: {err:#}", path.display()),
+                }
+            }
+
+            println!("repositories  {}", repos.len());
+            println!(
+                "total size    {}",
+                du::format_size(repos.iter().map(|repo| repo.size).sum())
+            );
+            if let Some(oldest) = repos.iter().min_by_key(|repo| repo.fetched) {
+                let fetched = oldest.fetched.map_or("never fetched".into(), |time| {
+                    chrono::DateTime::<chrono::Local>::from(time)
+                        .format("fetched %Y-%m-%d")
+                        .to_string()
+                });
+                println!("oldest fetch  {} ({fetched})", oldest.name);
+            }
+
+            let sections = [
+                (
+                    "HOST",
+                    stats::count(repos.iter().map(|repo| repo.host())),
+                    None,
+                ),
+                (
+                    "OWNER",
+                    stats::count(repos.iter().map(|repo| repo.owner())),
+                    Some(top),
+                ),
+                (
+                    "LANGUAGE",
+                    stats::count(repos.iter().map(|repo| repo.language.unwrap_or("-"))),
+                    None,
+                ),
+            ];
+            for (title, counts, limit) in sections {
+                let mut rows = vec![vec![title.to_string(), "REPOS".to_string()]];
+                rows.extend(
+                    counts
+                        .into_iter()
+                        .take(limit.unwrap_or(usize::MAX))
+                        .map(|(key, count)| vec![key.to_string(), count.to_string()]),
+                );
+                println!();
+                output::print_table(&rows);
+            }
+
+            repos.sort_by_key(|repo| std::cmp::Reverse(repo.changes));
+            let mut rows = vec![vec!["DIRTY".to_string(), "CHANGES".to_string()]];
+            rows.extend(
+                repos
+                    .iter()
+                    .filter(|repo| repo.changes > 0)
+                    .take(top)
+                    .map(|repo| vec![repo.name.clone(), repo.changes.to_string()]),
+            );
+            if rows.len() > 1 {
+                println!();
+                output::print_table(&rows);
+            }
+        }
+
+        CliCommand::License { missing, filter } => {
+            let config = open_config(false)?;
+            let profile = Profile::load(&config, profile_name)?;
+            let mut repos = Vec::new();
+            for (root_dir, path) in
+                filtered_repos(&config, &profile, &filter.with_config(&config)?)?
+            {
+                let name = repo_name(&root_dir, &path);
+                match license::detect(&path) {
+                    Ok(licenses) => repos.push((name, licenses)),
+                    Err(err) => warn!("skipped `{name}`: {err:#}"),
+                }
+            }
+
+            if missing {
+                for (name, licenses) in &repos {
+                    if licenses.is_empty() {
+                        println!("{name}");
+                    }
+                }
+                return Ok(());
+            }
+            let licenses: Vec<_> = repos
+                .iter()
+                .map(|(_, licenses)| {
+                    if licenses.is_empty() {
+                        "-".to_string()
+                    } else {
+                        licenses.iter().cloned().collect::<Vec<_>>().join(", ")
+                    }
+                })
+                .collect();
+            let mut rows = vec![vec!["REPO".to_string(), "LICENSE".to_string()]];
+            rows.extend(
+                repos
+                    .iter()
+                    .zip(&licenses)
+                    .map(|((name, _), license)| vec![name.clone(), license.clone()]),
+            );
+            output::print_table(&rows);
+
+            let mut rows = vec![vec!["LICENSE".to_string(), "REPOS".to_string()]];
+            rows.extend(
+                stats::count(licenses.iter().map(String::as_str))
+                    .into_iter()
+                    .map(|(license, count)| vec![license.to_string(), count.to_string()]),
+            );
+            println!();
+            output::print_table(&rows);
+        }
+
+        CliCommand::DiffConfig { fix, filter } => {
+            let config = open_config(false)?;
+            let profile = Profile::load(&config, profile_name)?;
+            let mut rows = vec![["REPO", "KEY", "ACTUAL", "EXPECTED"]
+                .map(String::from)
+                .to_vec()];
+            for (root_dir, path) in
+                filtered_repos(&config, &profile, &filter.with_config(&config)?)?
+            {
+                let name = repo_name(&root_dir, &path);
+                let repository = Repository::open(&path)?;
+                let host = name.split('/').next();
+                let host_profile = match (profile_name, host) {
+                    (None, Some(host)) => Profile::for_host(&config, host)?,
+                    _ => None,
+                };
+                let profile = host_profile.as_ref().unwrap_or(&profile);
+
+                let mut expected = profile.identity(&config, host);
+                expected.extend(profile.signing(&config, host));
+                let origin_url = repository
+                    .find_remote("origin")
+                    .ok()
+                    .and_then(|remote| Url::parse(remote.url()?).ok());
+                if let Some(origin_url) = origin_url {
+                    if let Some(push_url) = get_push_url(&config, &origin_url)? {
+                        expected.push(("remote.origin.pushurl", push_url.to_string()));
+                    }
+                }
+
+                let drifts = drift::compare(&repository, &expected)?;
+                if fix {
+                    for drift in drifts {
+                        profile::set_local(&repository, &[(drift.key, drift.expected.clone())])?;
+                        info!("fixed: {name}: {} = {}", drift.key, drift.expected);
+                    }
+                    continue;
+                }
+                rows.extend(drifts.into_iter().map(|drift| {
+                    vec![
+                        name.clone(),
+                        drift.key.to_string(),
+                        drift.actual.unwrap_or_else(|| "(unset)".into()),
+                        drift.expected,
+                    ]
+                }));
+            }
+            if rows.len() > 1 {
+                output::print_table(&rows);
+            }
+        }
+
+        CliCommand::Verify { filter } => {
+            let config = open_config(false)?;
+            let profile = Profile::load(&config, profile_name)?;
+            let mut checked = 0;
+            let mut broken = 0;
+            for (root_dir, path) in
+                filtered_repos(&config, &profile, &filter.with_config(&config)?)?
+            {
+                let name = repo_name(&root_dir, &path);
+                let problems = match verify::verify(&path) {
+                    Ok(problems) => problems,
+                    Err(err) => {
+                        warn!("failed to verify `{name}`: {err:#}");
+                        broken += 1;
+                        continue;
+                    }
+                };
+                checked += 1;
+                if !problems.is_empty() {
+                    broken += 1;
+                }
+                for problem in problems {
+                    println!("{name}: {problem}");
+                }
+            }
+            ensure!(broken == 0, "found problems in {broken} repositories");
+            info!("verified {checked} repositories");
+        }
+
+        CliCommand::Update {
+            filter,
+            opts,
+            jobs,
+            file,
+            stdin,
+            events,
+        } => {
+            let config = open_config(false)?;
+            let profile = Profile::load(&config, profile_name)?;
+            let roots = get_root_dirs(&config, &profile)?;
+            let jobs = jobs.unwrap_or_else(foreach::default_jobs);
+
+            let mut paths = repo_paths(filtered_repos(
+                &config,
+                &profile,
+                &filter.with_config(&config)?,
+            )?);
+            entry.spec = file.as_ref().map(|file| file.display().to_string());
+            if let Some(specs) = specs::load(file.as_deref(), stdin)? {
+                let listed = specs
+                    .iter()
+                    .map(|repo| Ok(resolve_repo(&config, &profile, repo)?.1))
+                    .collect::<Result<Vec<_>>>()?;
+                paths.retain(|path| listed.contains(path));
+            }
+            let count = paths.len();
+            let summary = update::run(&roots, paths, &opts, jobs, events::Events::new(events));
+            if !summary.failures.is_empty() || !summary.diverged.is_empty() {
+                info!();
+                info!("{}", t!(NeedsAttention));
+                for name in &summary.diverged {
+                    info!("  diverged: {name}");
+                }
+                for (name, err) in &summary.failures {
+                    info!("  failed: {name}: {err:#}");
+                }
+            }
+            if !summary.failures.is_empty() {
+                bail!(t!(ReposFailed, summary.failures.len(), count));
+            }
+        }
+
+        CliCommand::Replicate {
+            repo,
+            target,
+            remote_name,
+            origin_name,
+            ssh,
+            private,
+            force,
+        } => {
+            entry.spec = Some(repo.clone());
+            let config = open_config(false)?;
+            let profile = Profile::load(&config, profile_name)?;
+            let (root_dir, path) = resolve_repo(&config, &profile, &repo)?;
+            entry.path = Some(path.clone());
+            let repository = Repository::open(&path)?;
+
+            let url = match replicate::Target::parse(&target) {
+                replicate::Target::Url(url) => url,
+                replicate::Target::Forge { host, owner } => {
+                    let original = sanitize::original(&repo_name(&root_dir, &path))?;
+                    let name = original
+                        .rsplit('/')
+                        .next()
+                        .context("failed to get the repository name")?;
+                    let name = name.strip_suffix(".git").unwrap_or(name);
+                    let (https, ssh_url) = forge::create_repo(
+                        &config,
+                        &profile,
+                        &host,
+                        owner.as_deref(),
+                        name,
+                        private,
+                    )?;
+                    if ssh {
+                        ssh_url
+                    } else {
+                        https.to_string()
+                    }
+                }
+            };
+            entry.url = Url::parse(&url).ok();
+            replicate::set_remote(&repository, &remote_name, &url)?;
+            info!("{remote_name}: {url}");
+            replicate::push(&path, &origin_name, &remote_name, force)?;
+        }
+
+        CliCommand::SyncFork { repo, all } => {
+            let config = open_config(false)?;
+            let profile = Profile::load(&config, profile_name)?;
+            let paths = match repo {
+                Some(repo) if !all => {
+                    entry.spec = Some(repo.clone());
+                    let (root_dir, path) = resolve_repo(&config, &profile, &repo)?;
+                    entry.path = Some(path.clone());
+                    vec![(root_dir, path)]
+                }
+                _ => all_repos(&config, &profile)?,
+            };
+
+            let count = paths.len();
+            let mut failures = Vec::new();
+            for (root_dir, path) in paths {
+                let name = repo_name(&root_dir, &path);
+                match fork::sync(&path) {
+                    Ok(None) if !all => bail!("`{name}` is not a fork, add the `upstream` remote"),
+                    Ok(None) => {}
+                    Ok(Some(fork::Synced::UpToDate(branch))) => {
+                        info!("{name}: `{branch}` is up to date");
+                    }
+                    Ok(Some(fork::Synced::FastForwarded(branch, from, to))) => {
+                        info!("{name}: `{branch}` fast-forwarded {from:.7}..{to:.7}");
+                    }
+                    Err(err) => {
+                        info!("{name}: {err:#}");
+                        failures.push(name);
+                    }
+                }
+            }
+            if !failures.is_empty() {
+                bail!(t!(ReposFailed, failures.len(), count));
+            }
+        }
+
+        CliCommand::Prune {
+            stale,
+            suggest,
+            permanent,
+            filter,
+        } => {
+            let config = open_config(false)?;
+            let profile = Profile::load(&config, profile_name)?;
+            ensure!(
+                suggest || setup::is_interactive(),
+                "pass --suggest to only print the suggestions without a terminal"
+            );
+
+            let now = std::time::SystemTime::now();
+            let pinned = pinned::list(&config)?;
+            let mut candidates = Vec::new();
+            for (root_dir, path) in
+                filtered_repos(&config, &profile, &filter.with_config(&config)?)?
+            {
+                let name = repo_name(&root_dir, &path);
+                if pinned.contains(&name) {
+                    continue;
+                }
+                let last = match stale::last_activity(&path) {
+                    Ok(last) => last,
+                    Err(err) => {
+                        info!("{name}: {err:#}");
+                        continue;
+                    }
+                };
+                let age = last.and_then(|last| now.duration_since(last).ok());
+                if age.is_some_and(|age| age < stale) {
+                    continue;
+                }
+                let size = match du::Usage::measure(&path) {
+                    Ok(usage) => usage.total(),
+                    Err(err) => {
+                        warn!("skipped `{name}`: {err:#}");
+                        continue;
+                    }
+                };
+                candidates.push((name, root_dir, path, age, size));
+            }
+            candidates.sort_by_key(|(_, _, _, age, _)| std::cmp::Reverse(*age));
+            if candidates.is_empty() {
+                info!("no repositories are stale");
+                return Ok(());
+            }
+
+            let mut rows = vec![["REPO", "UNTOUCHED FOR", "SIZE"].map(String::from).to_vec()];
+            for (name, _, _, age, size) in &candidates {
+                rows.push(vec![
+                    name.clone(),
+                    age.map_or("-".into(), output::format_age),
+                    du::format_size(*size),
+                ]);
+            }
+            output::print_table(&rows);
+            let total = candidates.iter().map(|(_, _, _, _, size)| size).sum();
+            info!(
+                "removing {} repositories would free {}",
+                candidates.len(),
+                du::format_size(total)
+            );
+            if suggest {
+                return Ok(());
+            }
+
+            let trash = if permanent {
+                None
+            } else {
+                Some(trash::Trash::new(&config)?)
+            };
+            let repos = repo_paths(all_repos(&config, &profile)?);
+            let git_dir_root = gitdir::get_git_dir_root(&config)?;
+            let mut freed = 0;
+            for (name, root_dir, path, _, size) in candidates {
+                let answer = setup::ask(&format!("remove {name}?"), "n")?;
+                if !matches!(answer.to_lowercase().as_str(), "y" | "yes") {
+                    continue;
+                }
+                if permanent {
+                    let confirmed = safety::check(&path)
+                        .and_then(|losses| safety::confirm(&name, &losses, true));
+                    if let Err(err) = confirmed {
+                        warn!("{err:#}");
+                        continue;
+                    }
+                }
+                let result = release_dependents(&root_dir, &path, &repos, false)
+                    .and_then(|()| remove_repo(&root_dir, &path, trash.as_ref(), &git_dir_root));
+                match result {
+                    Ok(()) => freed += size,
+                    Err(err) => warn!("{err:#}"),
+                }
+            }
+            if freed > 0 {
+                info!("freed {}", du::format_size(freed));
+            }
+        }
+
+        CliCommand::Gc {
+            repo,
+            all,
+            aggressive,
+            prune,
+            filter,
+        } => {
+            let config = open_config(false)?;
+            let profile = Profile::load(&config, profile_name)?;
+            let paths = match repo {
+                Some(repo) if !all => {
+                    entry.spec = Some(repo.clone());
+                    let (root_dir, path) = resolve_repo(&config, &profile, &repo)?;
+                    entry.path = Some(path.clone());
+                    vec![(root_dir, path)]
+                }
+                _ => filtered_repos(&config, &profile, &filter.with_config(&config)?)?,
+            };
+
+            let count = paths.len();
+            let mut failures = 0;
+            let (mut total_before, mut total_after) = (0, 0);
+            for (root_dir, path) in paths {
+                let name = repo_name(&root_dir, &path);
+                let result = du::Usage::measure(&path).and_then(|before| {
+                    let mut command = git::command(&path);
+                    command.args(["gc", "--quiet"]);
+                    if aggressive {
+                        command.arg("--aggressive");
+                    }
+                    if let Some(prune) = &prune {
+                        command.arg(format!("--prune={prune}"));
+                    }
+                    git::run(&mut command)?;
+                    Ok((before.git, du::Usage::measure(&path)?.git))
+                });
+                let (before, after) = match result {
+                    Ok(sizes) => sizes,
+                    Err(err) => {
+                        warn!("{name}: {err:#}");
+                        failures += 1;
+                        continue;
+                    }
+                };
+                println!(
+                    "{:>10} -> {:>10}  {}",
+                    du::format_size(before),
+                    du::format_size(after),
+                    name,
+                );
+                total_before += before;
+                total_after += after;
+            }
+            println!(
+                "{:>10} -> {:>10}  total ({} saved)",
+                du::format_size(total_before),
+                du::format_size(total_after),
+                du::format_size(total_before.saturating_sub(total_after)),
+            );
+            if failures > 0 {
+                bail!(t!(ReposFailed, failures, count));
+            }
+        }
+
+        CliCommand::Foreach {
+            filter,
+            jobs,
+            fail_fast,
+            keep_going: _,
+            json,
+            events,
+            failed,
+            script,
+            command,
+        } => {
+            let command = match script {
+                Some(script) => vec![
+                    "sh".to_string(),
+                    std::path::absolute(script)?.display().to_string(),
+                ],
+                None => command,
+            };
+            entry.spec = Some(command.join(" "));
+            let config = open_config(false)?;
+            let profile = Profile::load(&config, profile_name)?;
+            let roots = get_root_dirs(&config, &profile)?;
+            let opts = foreach::Options {
+                jobs: jobs.unwrap_or_else(foreach::default_jobs),
+                fail_fast,
+                capture: json,
+                events: events::Events::new(events),
+            };
+
+            let mut paths = repo_paths(filtered_repos(
+                &config,
+                &profile,
+                &filter.with_config(&config)?,
+            )?);
+            if failed {
+                let names = foreach::load_failed()?;
+                paths.retain(|path| names.contains(&root::relative(&roots, path)));
+                if paths.is_empty() {
+                    info!("no repositories failed in the last run");
+                    return Ok(());
+                }
+            }
+            let count = paths.len();
+            let names: Vec<_> = paths
+                .iter()
+                .map(|path| root::relative(&roots, path))
+                .collect();
+            let outcomes = foreach::run(&roots, paths, &command, &opts);
+            let failures: Vec<_> = outcomes
+                .iter()
+                .filter_map(|outcome| Some((outcome.repo.as_str(), outcome.error.as_ref()?)))
+                .collect();
+            // repositories skipped by --fail-fast are retried too
+            let skipped = names
+                .iter()
+                .filter(|name| !outcomes.iter().any(|outcome| &outcome.repo == *name));
+            foreach::save_failed(
+                &failures
+                    .iter()
+                    .map(|(name, _)| *name)
+                    .chain(skipped.map(String::as_str))
+                    .collect::<Vec<_>>(),
+            )?;
+            if json {
+                for outcome in &outcomes {
+                    println!("{}", serde_json::to_string(outcome)?);
+                }
+            }
+            if !failures.is_empty() {
+                info!();
+                for (name, err) in &failures {
+                    info!("failed: {name}: {err}");
+                }
+                if outcomes.len() < count {
+                    info!("skipped: {} repositories", count - outcomes.len());
+                }
+                bail!(t!(ReposFailed, failures.len(), count));
+            }
+        }
+
+        CliCommand::Grep {
+            pattern,
+            ignore_case,
+            fixed_strings,
+            filter,
+            jobs,
+        } => {
+            let config = open_config(false)?;
+            let profile = Profile::load(&config, profile_name)?;
+            let roots = get_root_dirs(&config, &profile)?;
+
+            let pattern = if fixed_strings {
+                regex::escape(&pattern)
+            } else {
+                pattern
+            };
+            let regex = regex::RegexBuilder::new(&pattern)
+                .case_insensitive(ignore_case)
+                .build()?;
+            let jobs = jobs.unwrap_or_else(foreach::default_jobs);
+            if grep::run(
+                &roots,
+                repo_paths(filtered_repos(
+                    &config,
+                    &profile,
+                    &filter.with_config(&config)?,
+                )?),
+                &regex,
+                jobs,
+            ) == 0
+            {
+                return Err(grep::NoMatch.into());
+            }
+        }
+
+        CliCommand::Branches {
+            merged,
+            delete,
+            protect,
+            remote,
+            filter,
+        } => {
+            let config = open_config(false)?;
+            let profile = Profile::load(&config, profile_name)?;
+            let mut protected = branches::get_protected(&config)?;
+            protected.extend(protect);
+
+            let mut rows = vec![["REPO", "BRANCH", "STATUS"].map(String::from).to_vec()];
+            let mut failures = 0;
+            for (root_dir, path) in
+                filtered_repos(&config, &profile, &filter.with_config(&config)?)?
+            {
+                let name = repo_name(&root_dir, &path);
+                let list = match branches::list(&path, &remote, &protected) {
+                    Ok(list) => list,
+                    Err(err) => {
+                        info!("{name}: {err:#}");
+                        continue;
+                    }
+                };
+                for branch in list {
+                    if merged && (!branch.merged || branch.protected) {
+                        continue;
+                    }
+                    let status = if delete {
+                        match branches::delete(&path, &branch.name) {
+                            Ok(()) => "deleted",
+                            Err(err) => {
+                                warn!("{name}: failed to delete `{}`: {err:#}", branch.name);
+                                failures += 1;
+                                continue;
+                            }
+                        }
+                    } else if branch.protected {
+                        "protected"
+                    } else if branch.merged {
+                        "merged"
+                    } else {
+                        "-"
+                    };
+                    rows.push(vec![name.clone(), branch.name, status.into()]);
+                }
+            }
+            output::print_table(&rows);
+            ensure!(failures == 0, "failed to delete {failures} branches");
+        }
+
+        CliCommand::Contributors {
+            repo,
+            months,
+            filter,
+            json,
+        } => {
+            let config = open_config(false)?;
+            let profile = Profile::load(&config, profile_name)?;
+            let since = chrono::Local::now()
+                .checked_sub_months(chrono::Months::new(months))
+                .context("too many months")?
+                .timestamp();
+
+            if let Some(repo) = repo {
+                let (_, path) = resolve_repo(&config, &profile, &repo)?;
+                let summary = contributors::summarize(&path, since)?;
+                if json {
+                    println!("{}", serde_json::to_string(&summary)?);
+                    return Ok(());
+                }
+                let mut rows = vec![["AUTHOR", "EMAIL", "COMMITS"].map(String::from).to_vec()];
+                for author in summary.authors {
+                    rows.push(vec![author.name, author.email, author.commits.to_string()]);
+                }
+                output::print_table(&rows);
+                return Ok(());
+            }
+
+            let mut rows = vec![["REPO", "ROLE", "COMMITS", "YOURS", "TOP AUTHOR"]
+                .map(String::from)
+                .to_vec()];
+            for (root_dir, path) in
+                filtered_repos(&config, &profile, &filter.with_config(&config)?)?
+            {
+                let name = repo_name(&root_dir, &path);
+                let summary = match contributors::summarize(&path, since) {
+                    Ok(summary) => summary,
+                    Err(err) => {
+                        info!("{name}: {err:#}");
+                        continue;
+                    }
+                };
+                if json {
+                    let mut value = serde_json::to_value(&summary)?;
+                    value["repo"] = name.into();
+                    value["role"] = summary.role().into();
+                    println!("{value}");
+                    continue;
+                }
+                rows.push(vec![
+                    name,
+                    summary.role().into(),
+                    summary.commits.to_string(),
+                    summary.yours.to_string(),
+                    summary
+                        .authors
+                        .first()
+                        .map_or("-".into(), |author| author.name.clone()),
+                ]);
+            }
+            if !json {
+                output::print_table(&rows);
+            }
+        }
+
+        CliCommand::Status {
+            all,
+            remote,
+            filter,
+            json,
+        } => {
+            let config = open_config(false)?;
+            let profile = Profile::load(&config, profile_name)?;
+
+            let mut rows = vec![["REPO", "BRANCH", "STATUS"].map(String::from).to_vec()];
+            for (root_dir, path) in
+                filtered_repos(&config, &profile, &filter.with_config(&config)?)?
+            {
+                let name = repo_name(&root_dir, &path);
+                let status = match status::check(&path, &remote) {
+                    Ok(status) => status,
+                    Err(err) => {
+                        info!("{name}: {err:#}");
+                        continue;
+                    }
+                };
+                let issues = status.issues();
+                if issues.is_empty() && !all {
+                    continue;
+                }
+                if json {
+                    let mut value = serde_json::to_value(&status)?;
+                    value["repo"] = name.into();
+                    value["issues"] = issues.into();
+                    println!("{value}");
+                    continue;
+                }
+                rows.push(vec![
+                    name,
+                    status.branch.unwrap_or_else(|| "(detached)".into()),
+                    if issues.is_empty() {
+                        "ok".into()
+                    } else {
+                        issues.join(", ")
+                    },
+                ]);
+            }
+            if !json && rows.len() > 1 {
+                output::print_table(&rows);
+            }
+        }
+
+        CliCommand::Stashes { filter, json } => {
+            let config = open_config(false)?;
+            let profile = Profile::load(&config, profile_name)?;
+
+            let mut stashes = Vec::new();
+            for (root_dir, path) in
+                filtered_repos(&config, &profile, &filter.with_config(&config)?)?
+            {
+                let name = repo_name(&root_dir, &path);
+                match stash::list(&path) {
+                    Ok(list) => stashes.extend(list.into_iter().map(|s| (name.clone(), s))),
+                    Err(err) => info!("{name}: {err:#}"),
+                }
+            }
+            stashes.sort_by_key(|(_, stash)| stash.time);
+            if json {
+                for (name, stash) in &stashes {
+                    let stash = serde_json::json!({
+                        "repo": name,
+                        "stash": format!("stash@{{{}}}", stash.index),
+                        "date": stash.time.to_rfc3339(),
+                        "message": stash.message,
+                    });
+                    println!("{stash}");
+                }
+                return Ok(());
+            }
+            let now = chrono::Local::now();
+            let mut rows = vec![["REPO", "STASH", "AGE", "MESSAGE"]
+                .map(String::from)
+                .to_vec()];
+            for (name, stash) in stashes {
+                let age = (now.fixed_offset() - stash.time)
+                    .to_std()
+                    .unwrap_or_default();
+                rows.push(vec![
+                    name,
+                    format!("stash@{{{}}}", stash.index),
+                    output::format_age(age),
+                    stash.message,
+                ]);
+            }
+            output::print_table(&rows);
+        }
+
+        CliCommand::Todo {
+            kinds,
+            filter,
+            json,
+            jobs,
+        } => {
+            let config = open_config(false)?;
+            let profile = Profile::load(&config, profile_name)?;
+            let roots = get_root_dirs(&config, &profile)?;
+            let jobs = jobs.unwrap_or_else(foreach::default_jobs);
+
+            let paths = repo_paths(filtered_repos(
+                &config,
+                &profile,
+                &filter.with_config(&config)?,
+            )?);
+            let items = todo::run(&roots, paths, &kinds, jobs);
+            if json {
+                for item in &items {
+                    println!("{}", serde_json::to_string(item)?);
+                }
+                return Ok(());
+            }
+            for (i, item) in items.iter().enumerate() {
+                if i == 0 || items[i - 1].repo != item.repo {
+                    let count = items.iter().filter(|other| other.repo == item.repo).count();
+                    println!("{} ({count})", item.repo);
+                }
+                println!(
+                    "  {}:{}: {}: {}",
+                    item.file, item.line, item.kind, item.text
+                );
+            }
+        }
+
+        CliCommand::Note { command } => {
+            let config = open_config(false)?;
+            let profile = Profile::load(&config, profile_name)?;
+            let mut notes = note::Notes::load()?;
+
+            match command {
+                NoteCommand::Set { repo, note } => {
+                    entry.spec = Some(repo.clone());
+                    let (root_dir, path) = resolve_repo(&config, &profile, &repo)?;
+                    entry.path = Some(path.clone());
+                    notes.set(repo_name(&root_dir, &path), note);
+                    notes.save()?;
+                }
+                NoteCommand::Remove { repo } => {
+                    entry.spec = Some(repo.clone());
+                    let (root_dir, path) = resolve_repo(&config, &profile, &repo)?;
+                    entry.path = Some(path.clone());
+                    let name = repo_name(&root_dir, &path);
+                    ensure!(notes.remove(&name), "`{name}` has no note");
+                    notes.save()?;
+                }
+                NoteCommand::List { repo: Some(repo) } => {
+                    let (root_dir, path) = resolve_repo(&config, &profile, &repo)?;
+                    if let Some(note) = notes.get(&repo_name(&root_dir, &path)) {
+                        println!("{note}");
+                    }
+                }
+                NoteCommand::List { repo: None } => {
+                    for (name, note) in notes.iter() {
+                        println!("{name}\t{note}");
+                    }
+                }
+            }
+        }
+
+        CliCommand::Pin { repos } if repos.is_empty() => {
+            let config = open_config(false)?;
+            for name in pinned::list(&config)? {
+                println!("{name}");
+            }
+        }
+
+        CliCommand::Pin { repos } => set_pinned(profile_name, &repos, true)?,
+
+        CliCommand::Unpin { repos } => set_pinned(profile_name, &repos, false)?,
+
+        CliCommand::Tag { command } => {
+            let config = open_config(false)?;
+            let profile = Profile::load(&config, profile_name)?;
+
+            match command {
+                TagCommand::Add { repo, tags } => {
+                    entry.spec = Some(repo.clone());
+                    let (_, path) = resolve_repo(&config, &profile, &repo)?;
+                    entry.path = Some(path.clone());
+                    for t in tags {
+                        tag::add(&path, &t)?;
+                    }
+                }
+                TagCommand::Remove { repo, tags } => {
+                    entry.spec = Some(repo.clone());
+                    let (_, path) = resolve_repo(&config, &profile, &repo)?;
+                    entry.path = Some(path.clone());
+                    for t in tags {
+                        if !tag::remove(&path, &t)? {
+                            warn!("`{repo}` is not tagged with `{t}`");
+                        }
+                    }
+                }
+                TagCommand::List { repo: Some(repo) } => {
+                    let (_, path) = resolve_repo(&config, &profile, &repo)?;
+                    for t in tag::get(&path)? {
+                        println!("{t}");
+                    }
+                }
+                TagCommand::List { repo: None } => {
+                    let mut tags = std::collections::BTreeSet::new();
+                    for (_, path) in all_repos(&config, &profile)? {
+                        tags.extend(tag::get(&path).unwrap_or_default());
+                    }
+                    for t in tags {
+                        println!("{t}");
+                    }
+                }
+            }
+        }
+
+        CliCommand::Init {
+            root,
+            protocol,
+            host,
+        } => {
+            let config = open_config(false)?;
+            let profile = Profile::load(&config, profile_name)?;
+            let interactive = setup::is_interactive();
+            let default_root = get_root_dir(&config, &profile)?;
+            let default_protocol = if prefers_ssh(&config, &profile) {
+                "ssh"
+            } else {
+                "https"
+            };
+            let default_host = get_default_host(&config);
+            let settings = setup::Settings {
+                root: match root {
+                    Some(root) => root,
+                    None if interactive => PathBuf::from(setup::ask(
+                        "root directory",
+                        &default_root.to_string_lossy(),
+                    )?),
+                    None => default_root,
+                },
+                protocol: match protocol {
+                    Some(protocol) => protocol,
+                    None if interactive => setup::ask("protocol (https or ssh)", default_protocol)?,
+                    None => default_protocol.to_string(),
+                },
+                host: match host {
+                    Some(host) => host,
+                    None if interactive => setup::ask("default host", &default_host)?,
+                    None => default_host,
+                },
+            };
+            setup::write(&settings)?;
+            if interactive {
+                setup::store_token(&config, &settings.host)?;
+            }
+            let shell = shell::Shell::detect().unwrap_or(shell::Shell::Bash);
+            println!("{}", shell.rc_line());
+        }
+
+        CliCommand::Watch => {
+            let config = open_config(false)?;
+            let profile = Profile::load(&config, profile_name)?;
+            index::watch(&get_root_dirs(&config, &profile)?, scan::get_depth(&config))?;
+        }
+
+        CliCommand::CompletionData { json, refresh } => {
+            let config = open_config(false)?;
+            let profile = Profile::load(&config, profile_name)?;
+            let max_depth = scan::get_depth(&config);
+            let mut candidates = Vec::new();
+            for root_dir in get_root_dirs(&config, &profile)? {
+                if refresh {
+                    index::refresh(&root_dir, max_depth)?;
+                }
+                for path in completion::repos(&root_dir, max_depth)? {
+                    candidates.push(completion::Candidate::new(
+                        repo_name(&root_dir, &path),
+                        path,
+                    ));
+                }
+            }
+            completion::sort(&mut candidates);
+            for candidate in &candidates {
+                if json {
+                    println!("{}", serde_json::to_string(candidate)?);
+                } else {
+                    println!("{}", candidate.to_line());
+                }
+            }
+        }
+
+        CliCommand::Serve {
+            socket,
+            tcp,
+            allow_remote,
+        } => {
+            let handler = |method: &str, params| rpc(profile_name, read_only, method, params);
+            match tcp {
+                Some(addr) => serve::listen_tcp(&addr, allow_remote, handler)?,
+                #[cfg(unix)]
+                None => {
+                    let socket = match socket {
+                        Some(socket) => socket,
+                        None => {
+                            paths::runtime_dir()?.join(concat!(env!("CARGO_PKG_NAME"), ".sock"))
+                        }
+                    };
+                    serve::listen_unix(&socket, handler)?;
+                }
+                #[cfg(not(unix))]
+                None => {
+                    _ = socket;
+                    bail!("Unix domain sockets are not available; use --tcp");
+                }
+            }
+        }
+
+        CliCommand::Paths { name } => {
+            let config = open_config(false)?;
+            let profile = Profile::load(&config, profile_name)?;
+            let paths = [
+                ("root", get_root_dir(&config, &profile)?),
+                ("config", git2::Config::find_global()?),
+                ("vault", vault::get_vault_path(&config)?),
+                ("history", history::get_history_file(&config)?),
+                ("trash", trash::get_trash_dir(&config)?),
+                ("archive", archive::get_archive_dir(&config)?),
+                ("known_hosts", cert::get_known_hosts_file()?),
+                ("objects", objcache::get_cache_dir()?),
+                ("notes", note::get_notes_file()?),
+                ("cache", paths::cache_dir()?),
+                (
+                    "socket",
+                    paths::runtime_dir()?.join(concat!(env!("CARGO_PKG_NAME"), ".sock")),
+                ),
+            ];
+            match name {
+                Some(name) => {
+                    let (_, path) = paths
+                        .iter()
+                        .find(|(n, _)| *n == name)
+                        .with_context(|| format!("unknown path `{name}`"))?;
+                    println!("{}", path.display());
+                }
+                None => {
+                    let rows: Vec<_> = paths
+                        .iter()
+                        .map(|(name, path)| vec![name.to_string(), path.display().to_string()])
+                        .collect();
+                    output::print_table(&rows);
+                }
+            }
+        }
+
+        CliCommand::ShellInit { shell } => {
+            shell.write_init(&mut Cli::command(), &mut std::io::stdout().lock())?;
+        }
+
+        CliCommand::Open {
+            repo,
+            file,
+            line,
+            commit,
+            remote,
+            print,
+        } => {
+            let config = open_config(false)?;
+            let (path, base) = match repo {
+                Some(repo) => {
+                    let profile = Profile::load(&config, profile_name)?;
+                    let (_, path) = resolve_repo(&config, &profile, &repo)?;
+                    (path.clone(), path)
+                }
+                None => {
+                    let current_dir = std::env::current_dir()?;
+                    let repository = Repository::discover(&current_dir)
+                        .context("not in a repository; give the repository to open")?;
+                    let workdir = repository
+                        .workdir()
+                        .context("cannot open a bare repository")?
+                        .to_path_buf();
+                    (workdir, current_dir)
+                }
+            };
+            let repository = Repository::open(&path)?;
+            let web = get_web_url(&repository, &remote)?;
+
+            let link = match file {
+                None => web.to_string(),
+                Some(file) => {
+                    let relative = get_relative_path(&path, &base, &file)?;
+                    let head = repository.head()?;
+                    let rev = match head.shorthand() {
+                        Some(branch) if head.is_branch() && !commit => {
+                            browse::Rev::Branch(branch.to_string())
+                        }
+                        _ => browse::Rev::Commit(head.peel_to_commit()?.id().to_string()),
+                    };
+                    let host = web.host_str().unwrap_or_default();
+                    let kind = forge::ForgeKind::from_config(&config, host)?;
+                    browse::file_link(kind, &web, &rev, &relative, line)
+                }
+            };
+            if print {
+                println!("{link}");
+            } else {
+                browse::open(&link)?;
+                info!("opened: {link}");
+            }
         }
-    };
 
-    match command {
-        CliCommand::Root => {
+        CliCommand::Url {
+            rev,
+            file,
+            line,
+            remote,
+        } => {
             let config = open_config(false)?;
-            println!("{}", get_root_dir(&config)?.display());
+            let current_dir = std::env::current_dir()?;
+            let repository = Repository::discover(&current_dir).context("not in a repository")?;
+            let workdir = repository
+                .workdir()
+                .context("bare repositories have no files to link to")?;
+            let web = get_web_url(&repository, &remote)?;
+            let id = repository
+                .revparse_single(&rev)?
+                .peel_to_commit()?
+                .id()
+                .to_string();
+            let kind = forge::ForgeKind::from_config(&config, web.host_str().unwrap_or_default())?;
+            let link = match file {
+                Some(file) => {
+                    let relative = get_relative_path(workdir, &current_dir, &file)?;
+                    browse::file_link(kind, &web, &browse::Rev::Commit(id), &relative, line)
+                }
+                None => browse::commit_link(kind, &web, &id),
+            };
+            println!("{link}");
         }
 
-        CliCommand::List { absolute } => {
+        CliCommand::Remote {
+            command:
+                RemoteCommand::SetProtocol {
+                    protocol,
+                    host,
+                    remote,
+                    filter,
+                    dry_run,
+                },
+        } => {
             let config = open_config(false)?;
-            let root_dir = get_root_dir(&config)?;
+            let profile = Profile::load(&config, profile_name)?;
+            let ssh = protocol == Protocol::Ssh;
 
-            let mut walker = walkdir::WalkDir::new(&root_dir).min_depth(1).into_iter();
-            while let Some(Ok(entry)) = walker.next() {
-                let path = entry.path();
-                if Repository::open(path).is_err() {
+            let mut rows = vec![["REPO", "FROM", "TO"].map(String::from).to_vec()];
+            for (root_dir, path) in
+                filtered_repos(&config, &profile, &filter.with_config(&config)?)?
+            {
+                let name = repo_name(&root_dir, &path);
+                let repository = Repository::open(&path)?;
+                let Ok(found) = repository.find_remote(&remote) else {
                     continue;
-                }
-                let path = if absolute {
-                    path
+                };
+                let Some(url) = found.url().and_then(git::parse_remote_url) else {
+                    info!("{name}: skipped the URL of `{remote}` in an unknown form");
+                    continue;
+                };
+                let on_host = host.as_deref().is_none_or(|host| {
+                    url.host_str().is_some_and(|h| h.eq_ignore_ascii_case(host))
+                });
+                let convertible = matches!(url.scheme(), "https" | "http" | "ssh" | "git");
+                let current = if url.scheme() == "ssh" {
+                    Protocol::Ssh
                 } else {
-                    path.strip_prefix(&root_dir).unwrap_or(path)
+                    Protocol::Https
                 };
-                println!("{}", path.display().to_string().replace('\\', "/"));
-                walker.skip_current_dir();
+                if !on_host || !convertible || (current == protocol && url.scheme() != "http") {
+                    continue;
+                }
+                let new_url = with_protocol(&url, ssh)?;
+                if !dry_run {
+                    repository.remote_set_url(&remote, new_url.as_str())?;
+                    if transport::Transport::load(&repository)?
+                        .is_some_and(|transport| transport.remote == remote)
+                    {
+                        transport::Transport::clear(&repository)?;
+                    }
+                }
+                rows.push(vec![name, url.to_string(), new_url.to_string()]);
+            }
+            let count = rows.len() - 1;
+            if count > 0 {
+                output::print_table(&rows);
+            }
+            if dry_run {
+                info!("{count} remotes would be rewritten");
+            } else {
+                info!("{count} remotes rewritten");
             }
         }
 
-        CliCommand::Get { repo, ssh, depth } => {
-            let config = open_config(true)?;
-            let root_dir = get_root_dir(&config)?;
-            let username = get_username(&config)?;
-
-            let origin_url = get_origin_url(&username, ssh, &repo)?;
-            println!("origin: {origin_url}");
-            let path = &get_repo_path(&root_dir, &origin_url)?;
-            println!("path: {}", path.display());
-
-            let mut callbacks = git2::RemoteCallbacks::new();
-            callbacks.credentials(|url, _username_from_url, allowed_types| {
-                use git2::Cred;
-                if allowed_types.is_default() {
-                    Cred::default()
-                } else if allowed_types.is_username() {
-                    Cred::username(&username)
-                } else if allowed_types.is_ssh_key() {
-                    Cred::ssh_key_from_agent(&username)
-                } else {
-                    Cred::credential_helper(&config, url, Some(&username))
-                }
-            });
+        CliCommand::Whoami {
+            hosts,
+            no_ssh,
+            json,
+        } => {
+            let config = open_config(false)?;
+            let hosts = if hosts.is_empty() {
+                identity::hosts(&config, &get_default_host(&config))?
+            } else {
+                hosts
+            };
+            let profile = Profile::load(&config, profile_name)?;
+            let mut profiles = Vec::new();
+            for host in &hosts {
+                profiles.push(match profile_name {
+                    None => Profile::for_host(&config, host)?,
+                    Some(_) => None,
+                });
+            }
+            let profiles: Vec<&Profile> = profiles
+                .iter()
+                .map(|host_profile| host_profile.as_ref().unwrap_or(&profile))
+                .collect();
 
-            let mut fetch_opts = git2::FetchOptions::new();
-            fetch_opts.remote_callbacks(callbacks);
-            fetch_opts.depth(depth);
-            fetch_opts.proxy_options({
-                let mut opts = git2::ProxyOptions::new();
-                opts.auto();
-                opts
+            // API checks share the config, while SSH checks wait for the network in parallel
+            let api: Vec<Option<Result<String>>> = hosts
+                .iter()
+                .zip(&profiles)
+                .map(|(host, profile)| {
+                    let has_token =
+                        profile.token.is_some() || forge::token(&config, host, None).is_some();
+                    has_token.then(|| forge::token_user(&config, profile, host))
+                })
+                .collect();
+            let ssh: Vec<Option<Result<String>>> = std::thread::scope(|s| {
+                let handles: Vec<_> = hosts
+                    .iter()
+                    .zip(&profiles)
+                    .map(|(host, profile)| {
+                        let key = profile.ssh_key.as_deref();
+                        s.spawn(move || (!no_ssh).then(|| identity::ssh_user(host, key)))
+                    })
+                    .collect();
+                handles
+                    .into_iter()
+                    .map(|handle| handle.join().unwrap())
+                    .collect()
             });
 
-            let mut builder = git2::build::RepoBuilder::new();
-            builder.fetch_options(fetch_opts);
+            let mut failed = false;
+            let mut rows = vec![["HOST", "API", "SSH", "KEY"].map(String::from).to_vec()];
+            for (((host, profile), api), ssh) in hosts.iter().zip(&profiles).zip(api).zip(ssh) {
+                let key = profile.ssh_key.as_deref().map(|key| {
+                    identity::key_comment(key).unwrap_or_else(|| key.display().to_string())
+                });
+                let mut errors = Vec::new();
+                let mut show = |kind: &str, result: Option<Result<String>>| match result {
+                    Some(Ok(user)) => Some(user),
+                    Some(Err(err)) => {
+                        errors.push(format!("{kind}: {err:#}"));
+                        None
+                    }
+                    None => None,
+                };
+                let api = show("api", api);
+                let ssh = show("ssh", ssh);
+                failed |= !errors.is_empty();
+                if json {
+                    let value = serde_json::json!({
+                        "host": host,
+                        "api": api,
+                        "ssh": ssh,
+                        "key": key,
+                        "error": (!errors.is_empty()).then(|| errors.join("; ")),
+                    });
+                    println!("{value}");
+                    continue;
+                }
+                for error in &errors {
+                    warn!("{host}: {error}");
+                }
+                let failed_as = |kind: &str| {
+                    if errors.iter().any(|e| e.starts_with(kind)) {
+                        "failed".to_string()
+                    } else {
+                        "-".to_string()
+                    }
+                };
+                rows.push(vec![
+                    host.clone(),
+                    api.unwrap_or_else(|| failed_as("api")),
+                    ssh.unwrap_or_else(|| failed_as("ssh")),
+                    key.unwrap_or_else(|| "-".into()),
+                ]);
+            }
+            if !json {
+                output::print_table(&rows);
+            }
+            ensure!(!failed, "some credentials do not work");
+        }
 
-            builder.clone(origin_url.as_str(), path)?;
+        CliCommand::Token { command } => {
+            let config = open_config(false)?;
+            let exists = vault::get_vault_path(&config)?.exists();
+            let passphrase = vault::passphrase(!exists)?;
+            let mut tokens = vault::load(&config, &passphrase)?;
+            match command {
+                TokenCommand::Set { host, from_env } => {
+                    let token = match from_env {
+                        Some(var) => {
+                            std::env::var(&var).with_context(|| format!("`{var}` is not set"))?
+                        }
+                        None => rpassword::prompt_password(format!("token for {host}: "))?,
+                    };
+                    ensure!(!token.is_empty(), "the token is empty");
+                    tokens.insert(host, token);
+                    vault::save(&config, &tokens, &passphrase)?;
+                }
+                TokenCommand::Remove { host } => {
+                    ensure!(
+                        tokens.remove(&host).is_some(),
+                        "no token is stored for `{host}`"
+                    );
+                    vault::save(&config, &tokens, &passphrase)?;
+                }
+                TokenCommand::List => {
+                    for host in tokens.keys() {
+                        println!("{host}");
+                    }
+                }
+            }
         }
 
-        CliCommand::New { repo, ssh, raw } => {
-            let config = open_config(true)?;
-            let root_dir = get_root_dir(&config)?;
-            let username = get_username(&config)?;
+        CliCommand::Project { command } => match command {
+            ProjectCommand::Add { path, name } => {
+                let path = project::add(&name, &path)?;
+                info!("{name}: {}", path.display());
+            }
+            ProjectCommand::Remove { name } => {
+                ensure!(
+                    project::remove(&name)?,
+                    "project `{name}` is not registered"
+                );
+            }
+            ProjectCommand::List => {
+                let config = open_config(false)?;
+                let rows: Vec<_> = project::list(&config)?
+                    .into_iter()
+                    .map(|(name, path)| vec![name, path.display().to_string()])
+                    .collect();
+                output::print_table(&rows);
+            }
+        },
 
-            let mut opts = git2::RepositoryInitOptions::new();
-            opts.no_reinit(true);
+        CliCommand::Alias { command } => match command {
+            AliasCommand::Set { name, definition } => {
+                ensure!(
+                    Cli::command().find_subcommand(&name).is_none(),
+                    "`{name}` is a built-in command"
+                );
+                alias::set(&name, &definition)?;
+            }
+            AliasCommand::Remove { name } => {
+                ensure!(alias::remove(&name)?, "alias `{name}` is not defined");
+            }
+            AliasCommand::List => {
+                let config = open_config(false)?;
+                let rows: Vec<_> = alias::list(&config)?
+                    .into_iter()
+                    .map(|(name, definition)| vec![name, definition])
+                    .collect();
+                output::print_table(&rows);
+            }
+        },
 
-            let path = if raw {
-                root_dir.join(repo)
-            } else {
-                let origin_url = get_origin_url(&username, ssh, &repo)?;
-                opts.origin_url(origin_url.as_str());
-                println!("origin: {origin_url}");
-                get_repo_path(&root_dir, &origin_url)?
-            };
-            println!("path: {}", path.display());
+        CliCommand::Help { topic, man } => {
+            let mut command = help::augment(Cli::command());
+            if man {
+                help::render_man(command, &mut std::io::stdout().lock())?;
+                return Ok(());
+            }
+            if let [name] = topic.as_slice() {
+                if let Some(topic) = help::topic(name) {
+                    println!("{}", topic.body);
+                    return Ok(());
+                }
+            }
+            command.build();
+            let mut command = &mut command;
+            for name in &topic {
+                command = command
+                    .find_subcommand_mut(name)
+                    .with_context(|| format!("no such command or topic: `{}`", topic.join(" ")))?;
+            }
+            command.print_long_help()?;
+        }
+
+        CliCommand::History { limit } => {
+            let config = open_config(false)?;
+            let entries = history::read(&config)?;
+            let skip = limit.map_or(0, |limit| entries.len().saturating_sub(limit));
 
-            Repository::init_opts(path, &opts)?;
+            let mut rows = vec![["TIME", "OPERATION", "REPO", "OUTCOME"]
+                .map(String::from)
+                .to_vec()];
+            for entry in entries.into_iter().skip(skip) {
+                let time = chrono::DateTime::parse_from_rfc3339(&entry.time)
+                    .map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string())
+                    .unwrap_or(entry.time);
+                let target = match (&entry.path, entry.url, entry.spec) {
+                    (Some(path), _, _) => path.display().to_string(),
+                    (None, Some(url), _) => url.to_string(),
+                    (None, None, Some(spec)) => spec,
+                    (None, None, None) => "-".into(),
+                };
+                let outcome = match entry.error {
+                    Some(err) => format!("{}: {err}", entry.outcome),
+                    None => entry.outcome,
+                };
+                rows.push(vec![time, entry.operation, target, outcome]);
+            }
+            output::print_table(&rows);
         }
     }
 
     Ok(())
 }
 
-fn get_origin_url(username: &str, ssh: bool, repo: &str) -> Result<Url> {
+fn format_repo(template: &template::Template, root_dir: &Path, path: &Path) -> Result<String> {
+    let repo = path
+        .strip_prefix(root_dir)
+        .unwrap_or(path)
+        .display()
+        .to_string()
+        .replace('\\', "/");
+    let (host, owner, name) = template::split_repo(&repo);
+    let head = if template.uses(&["branch", "commit", "date", "subject"]) {
+        Some(head::Head::read(path)?)
+    } else {
+        None
+    };
+    let commit = head.as_ref().and_then(|head| head.commit.as_ref());
+    Ok(template.render(|field| match field {
+        "host" => host.to_string(),
+        "owner" => owner.clone(),
+        "name" => name.to_string(),
+        "repo" => repo.clone(),
+        "path" => path.display().to_string(),
+        "branch" => head
+            .as_ref()
+            .and_then(|head| head.branch.clone())
+            .unwrap_or_default(),
+        "commit" => commit.map(|c| c.short_id.clone()).unwrap_or_default(),
+        "date" => commit
+            .map(|c| c.time.format("%Y-%m-%d").to_string())
+            .unwrap_or_default(),
+        "subject" => commit.map(|c| c.summary.clone()).unwrap_or_default(),
+        "tags" => tag::get(path).unwrap_or_default().join(","),
+        _ => String::new(),
+    }))
+}
+
+/// Print the differences between local repositories and the ones owned by `owner` on the forge
+fn list_remote(
+    config: &git2::Config,
+    profile: &Profile,
+    absolute: bool,
+    owner: &str,
+) -> Result<()> {
+    let default_host = get_default_host(config);
+    let (host, owner) = owner.split_once('/').unwrap_or((&default_host, owner));
+    let root_dir = &get_host_root_dir(config, profile, Some(host))?;
+    let owner_dir = root_dir.join(host).join(owner);
+    let display = |path: &Path| {
+        let path = if absolute {
+            path
+        } else {
+            path.strip_prefix(root_dir).unwrap_or(path)
+        };
+        path.display().to_string().replace('\\', "/")
+    };
+
+    let mut local = scan::repos(&owner_dir, scan::get_depth(config));
+    for repo in forge::list_repos(config, profile, host, owner)? {
+        let path = get_repo_path(config, root_dir, &repo.url)?;
+        let position = local.iter().position(|p| {
+            p.to_string_lossy()
+                .eq_ignore_ascii_case(&path.to_string_lossy())
+        });
+        match position {
+            None if !repo.archived => println!("not-cloned\t{}", display(&path)),
+            Some(i) if repo.archived => println!("archived\t{}", display(&local.remove(i))),
+            Some(i) => _ = local.remove(i),
+            None => {}
+        }
+    }
+    for path in local {
+        println!("deleted\t{}", display(&path));
+    }
+    Ok(())
+}
+
+/// Complete `repo` into a URL, assuming `host` if it has no host
+fn get_origin_url(username: &str, host: &str, ssh: bool, repo: &str) -> Result<Url> {
+    if let Some(url) = provider::complete(repo, ssh) {
+        return url;
+    }
     let slash_count = repo.split('/').count() - 1;
     if slash_count == 0 {
-        return get_origin_url(username, ssh, &format!("{username}/{repo}"));
+        return get_origin_url(username, host, ssh, &format!("{username}/{repo}"));
     }
     if slash_count == 1 {
-        return get_origin_url(username, ssh, &format!("{DEFAULT_HOST}/{repo}"));
+        return get_origin_url(username, host, ssh, &format!("{host}/{repo}"));
     }
     if slash_count == 2 {
         return get_origin_url(
             username,
+            host,
             ssh,
             &if ssh && repo.contains('@') {
                 format!("ssh://{repo}")
@@ -171,33 +3561,664 @@ fn get_origin_url(username: &str, ssh: bool, repo: &str) -> Result<Url> {
             },
         );
     }
-    Ok(Url::parse(repo)?)
+    let mut url = match Url::parse(repo) {
+        // deeper paths without a scheme, such as of Bitbucket Server
+        Err(url::ParseError::RelativeUrlWithoutBase) => {
+            with_protocol(&Url::parse(&format!("https://{repo}"))?, ssh)?
+        }
+        url => url?,
+    };
+    git::normalize_host(&mut url);
+    Ok(url)
+}
+
+/// Ask before cloning a repository larger than `grm.confirmSizeMb` according to the forge
+///
+/// Repositories whose size cannot be fetched are cloned without asking.
+fn confirm_size(config: &git2::Config, profile: &Profile, origin_url: &Url) -> Result<()> {
+    let Ok(threshold) = config.get_i64(concat!(env!("CARGO_PKG_NAME"), ".confirmSizeMb")) else {
+        return Ok(());
+    };
+    let (Some(host), Some(path)) = (origin_url.host_str(), origin_url.path().get(1..)) else {
+        return Ok(());
+    };
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    let size = match forge::repo_size(config, profile, host, path) {
+        Ok(size) => size,
+        Err(err) => {
+            info!("cannot get the size of the repository: {err:#}");
+            return Ok(());
+        }
+    };
+    if size <= threshold.max(0) as u64 * 1024 * 1024 {
+        return Ok(());
+    }
+    let size = du::format_size(size);
+    ensure!(
+        setup::is_interactive(),
+        "the repository is {size}, larger than grm.confirmSizeMb; pass --yes to clone it"
+    );
+    let answer = setup::ask(&format!("the repository is {size}; clone it?"), "n")?;
+    ensure!(
+        matches!(answer.to_lowercase().as_str(), "y" | "yes"),
+        "cancelled"
+    );
+    Ok(())
+}
+
+/// Convert `url` to an SSH URL or an HTTPS URL
+fn with_protocol(url: &Url, ssh: bool) -> Result<Url> {
+    if let Some(url) = provider::with_protocol(url, ssh) {
+        return url;
+    }
+    let host = url
+        .host_str()
+        .with_context(|| format!("cannot find a host name from `{url}`"))?;
+    let path = url.path();
+    Ok(if ssh {
+        Url::parse(&format!("ssh://git@{host}{path}"))?
+    } else {
+        Url::parse(&format!("https://{host}{path}"))?
+    })
+}
+
+/// Set `remote.<name>.pushurl` if `grm.<host>.pushProtocol` is configured
+fn set_push_url(
+    config: &git2::Config,
+    repo: &Repository,
+    remote_name: &str,
+    origin_url: &Url,
+) -> Result<()> {
+    let Some(push_url) = get_push_url(config, origin_url)? else {
+        return Ok(());
+    };
+    repo.remote_set_pushurl(remote_name, Some(push_url.as_str()))?;
+    info!("push: {push_url}");
+    Ok(())
+}
+
+/// The push URL of `origin_url` in the protocol of `grm.<host>.pushProtocol`, if it is set
+fn get_push_url(config: &git2::Config, origin_url: &Url) -> Result<Option<Url>> {
+    let Some(host) = origin_url.host_str() else {
+        return Ok(None);
+    };
+    let key = format!("{}.{host}.pushProtocol", env!("CARGO_PKG_NAME"));
+    let Ok(protocol) = config.get_string(&key) else {
+        return Ok(None);
+    };
+    match protocol.as_str() {
+        "ssh" => with_protocol(origin_url, true).map(Some),
+        "https" => with_protocol(origin_url, false).map(Some),
+        _ => bail!("`{key}` must be `ssh` or `https`, but got `{protocol}`"),
+    }
+}
+
+/// Restrict the working tree to `paths`, then populate it if `checkout` is set
+fn sparse_checkout(path: &Path, paths: &[String], checkout: bool) -> Result<()> {
+    git::run_quiet(
+        git::command(path)
+            .args(["sparse-checkout", "set", "--cone", "--"])
+            .args(paths),
+    )?;
+    if checkout {
+        git::run_quiet(git::command(path).arg("checkout"))?;
+    }
+    info!("sparse: {}", paths.join(" "));
+    Ok(())
 }
 
-fn get_repo_path(root_dir: &Path, origin: &Url) -> Result<PathBuf> {
+/// The name of the repository at `origin` before `grm.invalidNames` applies, like
+/// `github.com/owner/repo`
+fn get_repo_name(origin: &Url) -> Result<String> {
+    if let Some((host, path)) = provider::repo_path(origin) {
+        return Ok(format!("{host}/{path}"));
+    }
     let domain = origin
         .domain()
         .with_context(|| format!("cannot find a domain name from `{origin}`"))?;
-    Ok(root_dir
-        .join(domain)
-        .join(origin.path().trim_start_matches('/')))
+    Ok(format!(
+        "{domain}/{}",
+        origin.path().trim_start_matches('/')
+    ))
+}
+
+fn get_repo_path(config: &git2::Config, root_dir: &Path, origin: &Url) -> Result<PathBuf> {
+    Ok(root_dir.join(sanitize::apply(config, &get_repo_name(origin)?)?))
+}
+
+/// Get the path to create the repository at `origin` in, remembering its original name if it is
+/// saved under another one
+fn get_new_repo_path(config: &git2::Config, root_dir: &Path, origin: &Url) -> Result<PathBuf> {
+    let path = get_repo_path(config, root_dir, origin)?;
+    let (name, original) = (repo_name(root_dir, &path), get_repo_name(origin)?);
+    if name != original {
+        info!("saving `{original}` as `{name}`, which is valid on every filesystem");
+        sanitize::remember(&name, &original)?;
+    }
+    Ok(path)
+}
+
+/// Find the local path of `repo` under any root, returned with the root containing it
+///
+/// `repo` is either a project name, a path relative to a root, trailing components of the path
+/// of a managed repository, or a spec, which is placed under the root of its host.
+fn resolve_repo(
+    config: &git2::Config,
+    profile: &Profile,
+    repo: &str,
+) -> Result<(PathBuf, PathBuf)> {
+    let roots = get_root_dirs(config, profile)?;
+    if let Some(path) = project::get(config, repo) {
+        return Ok((root::containing(&roots, &path).to_path_buf(), path));
+    }
+    for root in &roots {
+        let path = root.join(repo);
+        if path.exists() {
+            return Ok((root.clone(), path));
+        }
+    }
+    let repos = scan_roots(&roots, |root| scan::repos(root, scan::get_depth(config)));
+    let candidates = roots
+        .iter()
+        .flat_map(|root| {
+            let repos = repos
+                .iter()
+                .filter(|(r, _)| r == root)
+                .map(|(_, path)| path);
+            select::find(root, repos.cloned().collect(), repo)
+        })
+        .collect::<Vec<_>>();
+    if !candidates.is_empty() {
+        let path = select::choose(&roots, repo, candidates)?;
+        return Ok((root::containing(&roots, &path).to_path_buf(), path));
+    }
+    let username = get_username(config, profile)?;
+    let origin_url = get_origin_url(&username, &get_default_host(config), false, repo)?;
+    let root_dir = get_host_root_dir(config, profile, origin_url.host_str())?;
+    let path = get_repo_path(config, &root_dir, &origin_url)?;
+    Ok((root_dir, path))
+}
+
+/// Repositories found by `find` under each of `roots`, paired with the innermost root containing
+/// them so that roots inside other roots are not listed twice
+fn scan_roots(
+    roots: &[PathBuf],
+    mut find: impl FnMut(&Path) -> Vec<PathBuf>,
+) -> Vec<(PathBuf, PathBuf)> {
+    let mut repos = Vec::new();
+    for root in roots {
+        for path in find(root) {
+            if root::containing(roots, &path) == root {
+                repos.push((root.clone(), path));
+            }
+        }
+    }
+    repos
+}
+
+/// Drop the roots paired with `repos`
+fn repo_paths(repos: Vec<(PathBuf, PathBuf)>) -> Vec<PathBuf> {
+    repos.into_iter().map(|(_, path)| path).collect()
+}
+
+/// Every repository under any root, paired with its root
+fn all_repos(config: &git2::Config, profile: &Profile) -> Result<Vec<(PathBuf, PathBuf)>> {
+    let roots = get_root_dirs(config, profile)?;
+    Ok(scan_roots(&roots, |root| {
+        scan::repos(root, scan::get_depth(config))
+    }))
+}
+
+/// Repositories under any root narrowed down by `filter`, paired with their roots
+fn filtered_repos(
+    config: &git2::Config,
+    profile: &Profile,
+    filter: &scan::Filter,
+) -> Result<Vec<(PathBuf, PathBuf)>> {
+    let roots = get_root_dirs(config, profile)?;
+    Ok(scan_roots(&roots, |root| filter.repos(root)))
+}
+
+/// Name of the current directory, which must be empty to be used as a repository name
+fn current_dir_name() -> Result<String> {
+    let dir = std::env::current_dir()?;
+    ensure!(
+        dir.read_dir()?.next().is_none(),
+        "`{}` is not empty; give the repository name explicitly",
+        dir.display()
+    );
+    Ok(dir
+        .file_name()
+        .context("the current directory has no name")?
+        .to_string_lossy()
+        .into_owned())
+}
+
+/// Answer a request of `grm serve`
+fn rpc(
+    profile_name: Option<&str>,
+    read_only: bool,
+    method: &str,
+    params: serde_json::Value,
+) -> Result<serde_json::Value> {
+    use serde_json::json;
+
+    let config = open_config(false)?;
+    let profile = Profile::load(&config, profile_name)?;
+    let param = |name: &str| {
+        params
+            .get(name)
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+    };
+    match method {
+        "list" => {
+            let filter = scan::Filter {
+                query: param("query"),
+                ..Default::default()
+            }
+            .with_config(&config)?;
+            let repos: Vec<_> = filtered_repos(&config, &profile, &filter)?
+                .into_iter()
+                .map(
+                    |(root_dir, path)| json!({ "name": repo_name(&root_dir, &path), "path": path }),
+                )
+                .collect();
+            Ok(repos.into())
+        }
+        "resolve" => {
+            let repo = param("repo").context("`repo` is required")?;
+            let roots = get_root_dirs(&config, &profile)?;
+            let candidates = match roots
+                .iter()
+                .map(|root| root.join(&repo))
+                .find(|p| p.exists())
+            {
+                Some(path) => vec![path],
+                None => {
+                    let repos = all_repos(&config, &profile)?;
+                    roots
+                        .iter()
+                        .flat_map(|root| {
+                            let repos = repos.iter().filter(|(r, _)| r == root);
+                            select::find(root, repo_paths(repos.cloned().collect()), &repo)
+                        })
+                        .collect()
+                }
+            };
+            let names: Vec<_> = candidates
+                .iter()
+                .map(|path| root::relative(&roots, path))
+                .collect();
+            Ok(json!({
+                "path": (candidates.len() == 1).then(|| &candidates[0]),
+                "candidates": names,
+            }))
+        }
+        "get" => {
+            let repo = param("repo").context("`repo` is required")?;
+            let mut command = std::process::Command::new(std::env::current_exe()?);
+            if let Some(profile_name) = profile_name {
+                command.arg("--profile").arg(profile_name);
+            }
+            if read_only {
+                command.arg("--read-only");
+            }
+            command.args(["get", "--json"]);
+            if params.get("ssh").and_then(|v| v.as_bool()) == Some(true) {
+                command.arg("--ssh");
+            }
+            let output = command
+                .arg("--")
+                .arg(&repo)
+                .stdin(std::process::Stdio::null())
+                .output()?;
+            ensure!(
+                output.status.success(),
+                "{}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+            Ok(serde_json::from_slice(&output.stdout)?)
+        }
+        "status" => {
+            let path = PathBuf::from(param("path").context("`path` is required")?);
+            let canonical = path.canonicalize()?;
+            let roots = get_root_dirs(&config, &profile)?;
+            ensure!(
+                roots
+                    .iter()
+                    .filter_map(|root| root.canonicalize().ok())
+                    .any(|root| canonical.starts_with(root)),
+                "`{}` is not under a root directory",
+                path.display()
+            );
+            let repository = Repository::open(&path)?;
+            let mut opts = git2::StatusOptions::new();
+            opts.include_untracked(true);
+            let changes = repository.statuses(Some(&mut opts))?.len();
+            let head = head::Head::read(&path)?;
+            Ok(json!({
+                "branch": head.branch,
+                "commit": head.commit.as_ref().map(|c| &c.short_id),
+                "date": head.commit.as_ref().map(|c| c.time.to_rfc3339()),
+                "subject": head.commit.as_ref().map(|c| &c.summary),
+                "changes": changes,
+            }))
+        }
+        _ => Err(serve::MethodNotFound(method.to_string()).into()),
+    }
+}
+
+/// The web page of the repository on the forge `remote` points to
+fn get_web_url(repository: &Repository, remote: &str) -> Result<Url> {
+    let origin = repository
+        .find_remote(remote)?
+        .url()
+        .and_then(git::parse_remote_url)
+        .with_context(|| format!("the URL of `{remote}` is not a forge URL"))?;
+    browse::web_url(&with_protocol(&origin, false)?)
+}
+
+/// The path of `file` relative to `workdir` with slashes, where `file` is relative to `base`
+///
+/// `file` does not have to exist, as it may have been deleted since the commit it is linked at.
+fn get_relative_path(workdir: &Path, base: &Path, file: &Path) -> Result<String> {
+    let mut path = base.canonicalize()?;
+    for component in file.components() {
+        match component {
+            std::path::Component::ParentDir => _ = path.pop(),
+            std::path::Component::CurDir => {}
+            component => path.push(component),
+        }
+    }
+    let relative = path
+        .strip_prefix(workdir.canonicalize()?)
+        .with_context(|| format!("`{}` is outside the repository", file.display()))?;
+    Ok(relative.display().to_string().replace('\\', "/"))
+}
+
+/// Copy `path` to the clipboard for `--copy`, only warning on failure as the work is done
+fn copy_path(path: &Path) {
+    match clipboard::copy(&path.display().to_string()) {
+        Ok(()) => info!("copied: {}", path.display()),
+        Err(err) => warn!("failed to copy the path: {err:#}"),
+    }
 }
 
-fn get_root_dir(config: &git2::Config) -> Result<PathBuf> {
+/// `grm.autoUpstream`
+fn auto_upstream(config: &git2::Config) -> bool {
     config
-        .get_path(concat!(env!("CARGO_PKG_NAME"), ".root"))
-        .ok()
-        .or_else(|| home_dir().map(|p| p.join(env!("CARGO_PKG_NAME"))))
+        .get_bool(concat!(env!("CARGO_PKG_NAME"), ".autoUpstream"))
+        .unwrap_or(false)
+}
+
+/// Add the repository the clone at `path` was forked from as `upstream`, if the forge says it is
+/// a fork
+fn add_fork_upstream(
+    config: &git2::Config,
+    profile: &Profile,
+    path: &Path,
+    origin_url: &Url,
+) -> Result<()> {
+    let host = origin_url.host_str().unwrap_or_default();
+    let repo_path = origin_url.path().trim_matches('/');
+    let repo_path = repo_path.strip_suffix(".git").unwrap_or(repo_path);
+    let Some(parent) = forge::fork_parent(config, profile, host, repo_path)? else {
+        return Ok(());
+    };
+    let parent = if origin_url.scheme() == "ssh" {
+        with_protocol(&parent, true)?
+    } else {
+        parent
+    };
+    fork::add_upstream(path, parent.as_str())?;
+    info!("upstream: {parent}");
+    Ok(())
+}
+
+/// Pin or unpin `repos`
+fn set_pinned(profile_name: Option<&str>, repos: &[String], pin: bool) -> Result<()> {
+    let config = open_config(false)?;
+    let profile = Profile::load(&config, profile_name)?;
+    for repo in repos {
+        let (root_dir, path) = resolve_repo(&config, &profile, repo)?;
+        ensure!(
+            Repository::open(&path).is_ok(),
+            t!(NotRepository, path.display())
+        );
+        let name = repo_name(&root_dir, &path);
+        if pin && !pinned::add(&config, &name)? {
+            info!("`{name}` is already pinned");
+        } else if !pin && !pinned::remove(&config, &name)? {
+            warn!("`{name}` is not pinned");
+        }
+    }
+    Ok(())
+}
+
+/// `path` relative to `root_dir`, separated by `/`
+fn repo_name(root_dir: &Path, path: &Path) -> String {
+    path.strip_prefix(root_dir)
+        .unwrap_or(path)
+        .display()
+        .to_string()
+        .replace('\\', "/")
+}
+
+/// Move the repository at `path` to `trash`, or delete it if there is no trash
+///
+/// A git directory kept apart under `git_dir_root` is moved back into the worktree to be put in
+/// the trash, or deleted together.
+fn remove_repo(
+    root_dir: &Path,
+    path: &Path,
+    trash: Option<&trash::Trash>,
+    git_dir_root: &Path,
+) -> Result<()> {
+    let name = repo_name(root_dir, path);
+    if path.is_symlink() {
+        std::fs::remove_file(path).or_else(|_| std::fs::remove_dir(path))?;
+        remove_empty_parents(path, root_dir);
+        info!("unlinked: {name}");
+        return Ok(());
+    }
+    ensure!(
+        Repository::open(path).is_ok(),
+        t!(NotRepository, path.display())
+    );
+    let _lock = lock::Lock::acquire(path, false)?;
+
+    if let Some(git_dir) = gitdir::find_separated(path, git_dir_root) {
+        match trash {
+            None => std::fs::remove_dir_all(&git_dir)?,
+            Some(_) => gitdir::join(path, &git_dir)?,
+        }
+        remove_empty_parents(&git_dir, git_dir_root);
+    }
+    match trash {
+        None => {
+            std::fs::remove_dir_all(path)?;
+            info!("removed: {name}");
+        }
+        Some(trash) => {
+            let item = trash.put(path, &name)?;
+            info!("moved to the trash: {name} ({})", item.id);
+        }
+    }
+    remove_empty_parents(path, root_dir);
+    Ok(())
+}
+
+/// Make sure no repository among `repos` borrows objects from the one at `path`, copying the
+/// objects into them if `dissociate`
+fn release_dependents(
+    root_dir: &Path,
+    path: &Path,
+    repos: &[PathBuf],
+    dissociate: bool,
+) -> Result<()> {
+    let dependents = alternates::dependents(path, repos);
+    if dependents.is_empty() {
+        return Ok(());
+    }
+    if !dissociate {
+        let mut message = format!(
+            "`{}` lends objects to other repositories; pass --dissociate to copy them first:",
+            repo_name(root_dir, path)
+        );
+        for dependent in &dependents {
+            message.push_str(&format!("\n  {}", repo_name(root_dir, dependent)));
+        }
+        bail!(message);
+    }
+    for dependent in &dependents {
+        alternates::dissociate(dependent)?;
+        info!("dissociated: {}", repo_name(root_dir, dependent));
+    }
+    Ok(())
+}
+
+/// Remove empty directories between `path` and `root_dir`
+fn remove_empty_parents(path: &Path, root_dir: &Path) {
+    for dir in path.ancestors().skip(1) {
+        if dir == root_dir || std::fs::remove_dir(dir).is_err() {
+            break;
+        }
+    }
+}
+
+fn symlink_dir(original: &Path, link: &Path) -> std::io::Result<()> {
+    #[cfg(unix)]
+    return std::os::unix::fs::symlink(original, link);
+    #[cfg(windows)]
+    return std::os::windows::fs::symlink_dir(original, link);
+}
+
+fn get_root_dir(config: &git2::Config, profile: &Profile) -> Result<PathBuf> {
+    get_host_root_dir(config, profile, None)
+}
+
+/// Get the root directory for repositories on `host`
+fn get_host_root_dir(
+    config: &git2::Config,
+    profile: &Profile,
+    host: Option<&str>,
+) -> Result<PathBuf> {
+    let candidates = root::Candidates::new(config, profile).with_host(config, host);
+    root::check_once(&candidates);
+    candidates
+        .active()
+        .map(Path::to_path_buf)
         .context("failed to get root dir")
 }
 
-fn get_username(config: &git2::Config) -> Result<String> {
+/// The active root followed by the roots of hosts, unless `GRM_ROOT` or the profile overrides them
+fn get_root_dirs(config: &git2::Config, profile: &Profile) -> Result<Vec<PathBuf>> {
+    let candidates = root::Candidates::new(config, profile);
+    let mut roots = vec![get_root_dir(config, profile)?];
+    if candidates.env.is_none() && candidates.profile.is_none() {
+        for root in root::host_roots(config)? {
+            if !roots.contains(&root) {
+                roots.push(root);
+            }
+        }
+    }
+    Ok(roots)
+}
+
+fn get_username(config: &git2::Config, profile: &Profile) -> Result<String> {
+    if let Ok(user) = std::env::var("GRM_USER") {
+        return Ok(user);
+    }
+    if let Some(user) = &profile.user {
+        return Ok(user.clone());
+    }
     config
         .get_string("user.name")
         .or_else(|_| whoami::fallible::username())
         .context("failed to get username")
 }
 
+/// Resolve the origin URL of `repo` and the profile to use for it
+///
+/// Unless a profile is given explicitly, the one associated with the host of the URL is used.
+fn resolve_origin(
+    config: &git2::Config,
+    profile_name: Option<&str>,
+    ssh: bool,
+    repo: &str,
+) -> Result<(Profile, Url)> {
+    let profile = Profile::load(config, profile_name)?;
+    let default_host = get_default_host(config);
+    let origin_url = get_origin_url(
+        &get_username(config, &profile)?,
+        &default_host,
+        ssh || prefers_ssh(config, &profile),
+        repo,
+    )?;
+    if profile_name.is_some() {
+        return Ok((profile, origin_url));
+    }
+    match origin_url.host_str() {
+        Some(host) => match Profile::for_host(config, host)? {
+            Some(profile) => {
+                let username = get_username(config, &profile)?;
+                let origin_url = get_origin_url(
+                    &username,
+                    &default_host,
+                    ssh || prefers_ssh(config, &profile),
+                    repo,
+                )?;
+                Ok((profile, origin_url))
+            }
+            None => Ok((profile, origin_url)),
+        },
+        None => Ok((profile, origin_url)),
+    }
+}
+
+/// Read `GRM_PROTOCOL`, falling back to the protocol of the profile and `grm.protocol`
+fn prefers_ssh(config: &git2::Config, profile: &Profile) -> bool {
+    match std::env::var("GRM_PROTOCOL") {
+        Ok(protocol) => protocol == "ssh",
+        Err(_) => match &profile.protocol {
+            Some(_) => profile.ssh(),
+            None => config
+                .get_string(concat!(env!("CARGO_PKG_NAME"), ".protocol"))
+                .is_ok_and(|protocol| protocol == "ssh"),
+        },
+    }
+}
+
+/// `grm.host`, the host assumed for `OWNER/NAME` specs
+fn get_default_host(config: &git2::Config) -> String {
+    config
+        .get_string(concat!(env!("CARGO_PKG_NAME"), ".host"))
+        .unwrap_or_else(|_| DEFAULT_HOST.to_string())
+}
+
+fn get_default_branch(config: &git2::Config) -> String {
+    config
+        .get_string("init.defaultBranch")
+        .or_else(|_| config.get_string(concat!(env!("CARGO_PKG_NAME"), ".defaultBranch")))
+        .unwrap_or_else(|_| "master".to_string())
+}
+
+fn get_owners(config: &git2::Config, profile: &Profile) -> Result<Vec<String>> {
+    let mut owners = Vec::new();
+    if let Ok(entries) = config.multivar(concat!(env!("CARGO_PKG_NAME"), ".owner"), None) {
+        entries.for_each(|entry| {
+            if let Some(value) = entry.value() {
+                owners.push(value.to_string());
+            }
+        })?;
+    }
+    if owners.is_empty() {
+        owners.push(get_username(config, profile)?);
+    }
+    Ok(owners)
+}
+
 fn open_config(current_dir: bool) -> Result<git2::Config> {
     if current_dir {
         if let Ok(config) = Repository::discover(".").and_then(|r| r.config()) {
@@ -207,6 +4228,22 @@ fn open_config(current_dir: bool) -> Result<git2::Config> {
     Ok(git2::Config::open_default()?)
 }
 
+#[cfg(test)]
+mod test_help {
+    use super::*;
+
+    #[test]
+    fn every_command_has_examples() {
+        for command in Cli::command().get_subcommands() {
+            assert!(
+                help::examples(command.get_name()).is_some(),
+                "`{}` has no examples",
+                command.get_name()
+            );
+        }
+    }
+}
+
 #[cfg(test)]
 mod test_get_origin_url {
     use super::*;
@@ -215,7 +4252,7 @@ mod test_get_origin_url {
     fn return_parsed_url() -> Result<()> {
         assert_eq!(
             Url::parse("https://github.com/foo/bar")?,
-            get_origin_url("foo", false, "https://github.com/foo/bar")?,
+            get_origin_url("foo", DEFAULT_HOST, false, "https://github.com/foo/bar")?,
         );
         Ok(())
     }
@@ -224,7 +4261,7 @@ mod test_get_origin_url {
     fn complete_scheme() -> Result<()> {
         assert_eq!(
             Url::parse("https://github.com/foo/bar")?,
-            get_origin_url("foo", false, "github.com/foo/bar")?,
+            get_origin_url("foo", DEFAULT_HOST, false, "github.com/foo/bar")?,
         );
         Ok(())
     }
@@ -233,7 +4270,7 @@ mod test_get_origin_url {
     fn complete_remote_host() -> Result<()> {
         assert_eq!(
             Url::parse("https://github.com/foo/bar")?,
-            get_origin_url("foo", false, "foo/bar")?,
+            get_origin_url("foo", DEFAULT_HOST, false, "foo/bar")?,
         );
         Ok(())
     }
@@ -242,7 +4279,66 @@ mod test_get_origin_url {
     fn complete_username() -> Result<()> {
         assert_eq!(
             Url::parse("https://github.com/foo/bar")?,
-            get_origin_url("foo", false, "bar")?
+            get_origin_url("foo", DEFAULT_HOST, false, "bar")?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn complete_provider_specific() -> Result<()> {
+        assert_eq!(
+            Url::parse("ssh://git@ssh.dev.azure.com/v3/org/proj/repo")?,
+            get_origin_url(
+                "foo",
+                DEFAULT_HOST,
+                true,
+                "dev.azure.com/org/proj/_git/repo"
+            )?
+        );
+        assert_eq!(
+            Url::parse("ssh://git@git.corp:7999/proj/repo.git")?,
+            get_origin_url("foo", DEFAULT_HOST, true, "git.corp/scm/proj/repo.git")?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn idn() -> Result<()> {
+        let expected = Url::parse("ssh://git@xn--bcher-kva.example/foo/bar")?;
+        assert_eq!(
+            expected,
+            get_origin_url("foo", DEFAULT_HOST, true, "bücher.example/foo/bar")?
+        );
+        assert_eq!(
+            expected,
+            get_origin_url("foo", DEFAULT_HOST, true, "xn--bcher-kva.example/foo/bar")?
+        );
+        assert_eq!(
+            Url::parse("https://xn--bcher-kva.example/foo/bar")?,
+            get_origin_url("foo", DEFAULT_HOST, false, "BÜCHER.example/foo/bar")?
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test_with_protocol {
+    use super::*;
+
+    #[test]
+    fn https_to_ssh() -> Result<()> {
+        assert_eq!(
+            Url::parse("ssh://git@github.com/foo/bar")?,
+            with_protocol(&Url::parse("https://github.com/foo/bar")?, true)?,
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn ssh_to_https() -> Result<()> {
+        assert_eq!(
+            Url::parse("https://github.com/foo/bar")?,
+            with_protocol(&Url::parse("ssh://git@github.com/foo/bar")?, false)?,
         );
         Ok(())
     }
@@ -0,0 +1,54 @@
+//! Freeform notes on repositories, kept in the data directory rather than in the repositories
+
+use std::{collections::BTreeMap, path::PathBuf};
+
+use anyhow::Result;
+
+use crate::paths;
+
+pub fn get_notes_file() -> Result<PathBuf> {
+    Ok(paths::data_dir()?.join("notes.json"))
+}
+
+/// Notes by repository names like `github.com/owner/name`
+pub struct Notes {
+    file: PathBuf,
+    notes: BTreeMap<String, String>,
+}
+
+impl Notes {
+    pub fn load() -> Result<Self> {
+        let file = get_notes_file()?;
+        let notes = match std::fs::read_to_string(&file) {
+            Ok(content) => serde_json::from_str(&content)?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(err) => return Err(err.into()),
+        };
+        Ok(Self { file, notes })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.notes.get(name).map(String::as_str)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.notes.iter()
+    }
+
+    pub fn set(&mut self, name: String, note: String) {
+        self.notes.insert(name, note);
+    }
+
+    /// Return whether there was a note
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.notes.remove(name).is_some()
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.file.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.file, serde_json::to_string_pretty(&self.notes)?)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,74 @@
+use std::{
+    process::{Command, Stdio},
+    time::Duration,
+};
+
+use anyhow::{ensure, Result};
+
+/// Notify that `operation` finished if `forced` or it took longer than `grm.notifyAfter` seconds
+pub fn finished<T>(
+    config: &git2::Config,
+    forced: bool,
+    operation: &str,
+    elapsed: Duration,
+    result: &Result<T>,
+) {
+    let threshold = config
+        .get_i64(concat!(env!("CARGO_PKG_NAME"), ".notifyAfter"))
+        .ok()
+        .and_then(|secs| u64::try_from(secs).ok())
+        .map(Duration::from_secs);
+    if !forced && threshold.is_none_or(|threshold| elapsed < threshold) {
+        return;
+    }
+
+    let summary = format!("{} {operation}", env!("CARGO_PKG_NAME"));
+    let body = match result {
+        Ok(_) => format!("finished in {elapsed:.1?}"),
+        Err(err) => format!("failed after {elapsed:.1?}: {err:#}"),
+    };
+    if let Err(err) = send(config, &summary, &body, result.is_ok()) {
+        warn!("failed to send a notification: {err:#}");
+    }
+}
+
+/// Run `grm.notifyCommand` with the message in environment variables, or show a desktop
+/// notification
+fn send(config: &git2::Config, summary: &str, body: &str, success: bool) -> Result<()> {
+    if let Ok(command) = config.get_string(concat!(env!("CARGO_PKG_NAME"), ".notifyCommand")) {
+        let status = shell(&command)
+            .env("GRM_SUMMARY", summary)
+            .env("GRM_BODY", body)
+            .env("GRM_STATUS", if success { "success" } else { "failure" })
+            .stdin(Stdio::null())
+            .status()?;
+        ensure!(status.success(), "`{command}` exited with {status}");
+        return Ok(());
+    }
+
+    #[cfg(feature = "desktop-notify")]
+    {
+        _ = notify_rust::Notification::new()
+            .summary(summary)
+            .body(body)
+            .show()?;
+        Ok(())
+    }
+    #[cfg(not(feature = "desktop-notify"))]
+    anyhow::bail!(
+        "set `{}.notifyCommand` or build with the `desktop-notify` feature",
+        env!("CARGO_PKG_NAME")
+    )
+}
+
+pub fn shell(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C").arg(command);
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c").arg(command);
+        shell
+    }
+}
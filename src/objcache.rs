@@ -0,0 +1,48 @@
+//! Object caches per host that clones borrow objects from, so that related repositories such as
+//! the ones of an organization are downloaded once
+
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::Result;
+use url::Url;
+
+use crate::{git, paths};
+
+/// Read `grm.<host>.objectCache`, falling back to `grm.objectCache`
+pub fn is_enabled(config: &git2::Config, host: &str) -> bool {
+    config
+        .get_bool(&format!("{}.{host}.objectCache", env!("CARGO_PKG_NAME")))
+        .or_else(|_| config.get_bool(concat!(env!("CARGO_PKG_NAME"), ".objectCache")))
+        .unwrap_or(false)
+}
+
+/// Kept in the data directory since clones break without it
+pub fn get_cache_dir() -> Result<PathBuf> {
+    Ok(paths::data_dir()?.join("objects"))
+}
+
+pub fn get_cache_path(host: &str) -> Result<PathBuf> {
+    Ok(get_cache_dir()?.join(format!("{host}.git")))
+}
+
+/// Fetch branches of `url` into the cache under `refs/remotes/<name>/`, creating the cache
+pub fn update(cache: &Path, name: &str, url: &Url) -> Result<()> {
+    if !cache.exists() {
+        std::fs::create_dir_all(cache)?;
+        git::run_quiet(
+            Command::new("git")
+                .args(["init", "--bare", "--quiet"])
+                .arg(cache),
+        )?;
+    }
+    git::run_quiet(git::command(cache).args([
+        "fetch",
+        "--quiet",
+        "--no-tags",
+        url.as_str(),
+        &format!("+refs/heads/*:refs/remotes/{name}/*"),
+    ]))
+}
@@ -0,0 +1,161 @@
+//! stdout is reserved for data such as paths so that commands can be composed in pipelines,
+//! and messages for humans go to stderr through these macros.
+
+use std::collections::BTreeMap;
+
+/// Print an informational message to stderr
+macro_rules! info {
+    ($($arg:tt)*) => {
+        eprintln!($($arg)*)
+    };
+}
+
+/// Print a warning to stderr
+macro_rules! warn {
+    ($($arg:tt)*) => {
+        eprintln!("warning: {}", format_args!($($arg)*))
+    };
+}
+
+/// Print rows to stdout with each column left-aligned
+pub fn print_table(rows: &[Vec<String>]) {
+    let mut widths = Vec::new();
+    for row in rows {
+        widths.resize(widths.len().max(row.len()), 0);
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    for row in rows {
+        let mut line = String::new();
+        for (i, cell) in row.iter().enumerate() {
+            if i + 1 == row.len() {
+                line.push_str(cell);
+            } else {
+                line.push_str(&format!("{cell:<width$}  ", width = widths[i]));
+            }
+        }
+        println!("{line}");
+    }
+}
+
+/// Draw repository names like `host/owner/name` as a tree grouped by host and then by owner,
+/// with the number of repositories in each group
+pub fn format_tree<'a>(names: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let mut hosts: BTreeMap<&str, BTreeMap<&str, Vec<&str>>> = BTreeMap::new();
+    for name in names {
+        let mut parts = name.splitn(3, '/');
+        let host = parts.next().unwrap_or_default();
+        let (owner, rest) = match (parts.next(), parts.next()) {
+            (Some(owner), Some(rest)) => (owner, rest),
+            // directly under the host
+            (Some(rest), None) => ("", rest),
+            (None, _) => ("", ""),
+        };
+        hosts
+            .entry(host)
+            .or_default()
+            .entry(owner)
+            .or_default()
+            .push(rest);
+    }
+    let mut lines = Vec::new();
+    for (host, mut owners) in hosts {
+        owners.values_mut().for_each(|repos| repos.sort_unstable());
+        let count: usize = owners.values().map(Vec::len).sum();
+        lines.push(format!("{host} ({count})"));
+        let mut children: Vec<(String, Option<&Vec<&str>>)> = Vec::new();
+        for (owner, repos) in &owners {
+            if owner.is_empty() {
+                children.extend(repos.iter().map(|repo| (repo.to_string(), None)));
+            } else {
+                children.push((format!("{owner} ({})", repos.len()), Some(repos)));
+            }
+        }
+        for (i, (label, repos)) in children.iter().enumerate() {
+            let last = i + 1 == children.len();
+            lines.push(format!("{}{label}", branch(last)));
+            let indent = if last { "    " } else { "│   " };
+            let repos = repos.map_or(&[][..], Vec::as_slice);
+            for (j, repo) in repos.iter().enumerate() {
+                lines.push(format!("{indent}{}{repo}", branch(j + 1 == repos.len())));
+            }
+        }
+    }
+    lines
+}
+
+fn branch(last: bool) -> &'static str {
+    if last {
+        "└── "
+    } else {
+        "├── "
+    }
+}
+
+/// Format an elapsed time roughly, like `3 days` or `2 months`
+pub fn format_age(elapsed: std::time::Duration) -> String {
+    const UNITS: &[(&str, u64)] = &[
+        ("year", 365 * 24 * 60 * 60),
+        ("month", 30 * 24 * 60 * 60),
+        ("week", 7 * 24 * 60 * 60),
+        ("day", 24 * 60 * 60),
+        ("hour", 60 * 60),
+        ("minute", 60),
+    ];
+    let seconds = elapsed.as_secs();
+    for (unit, length) in UNITS {
+        let n = seconds / length;
+        if n > 0 {
+            return format!("{n} {unit}{}", if n == 1 { "" } else { "s" });
+        }
+    }
+    "just now".to_string()
+}
+
+#[cfg(test)]
+mod test_format_age {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn units() {
+        assert_eq!(format_age(Duration::from_secs(30)), "just now");
+        assert_eq!(format_age(Duration::from_secs(60)), "1 minute");
+        assert_eq!(format_age(Duration::from_secs(3 * 86400)), "3 days");
+        assert_eq!(format_age(Duration::from_secs(100 * 86400)), "3 months");
+    }
+}
+
+#[cfg(test)]
+mod test_format_tree {
+    use super::*;
+
+    #[test]
+    fn group_by_host_and_owner() {
+        let names = [
+            "github.com/alice/b",
+            "github.com/alice/a",
+            "github.com/bob/group/c",
+            "gitlab.com/carol/d",
+            "localhost/e",
+        ];
+        assert_eq!(
+            format_tree(names),
+            [
+                "github.com (3)",
+                "├── alice (2)",
+                "│   ├── a",
+                "│   └── b",
+                "└── bob (1)",
+                "    └── group/c",
+                "gitlab.com (1)",
+                "└── carol (1)",
+                "    └── d",
+                "localhost (1)",
+                "└── e",
+            ]
+        );
+    }
+}
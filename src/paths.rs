@@ -0,0 +1,41 @@
+//! Platform directories of grm: XDG base directories on Linux, `~/Library` on macOS and
+//! `AppData` on Windows
+
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+
+/// Settings that are not stored in git config, such as the token vault
+pub fn config_dir() -> Result<PathBuf> {
+    dirs::config_dir()
+        .map(|p| p.join(env!("CARGO_PKG_NAME")))
+        .context("failed to get config dir")
+}
+
+/// Data worth keeping, such as archived and removed repositories
+pub fn data_dir() -> Result<PathBuf> {
+    dirs::data_dir()
+        .map(|p| p.join(env!("CARGO_PKG_NAME")))
+        .context("failed to get data dir")
+}
+
+/// Logs of what grm did, falling back to the data directory where there is no such directory
+pub fn state_dir() -> Result<PathBuf> {
+    dirs::state_dir()
+        .map(|p| p.join(env!("CARGO_PKG_NAME")))
+        .map_or_else(data_dir, Ok)
+}
+
+/// Sockets, falling back to the cache directory where there is no such directory
+pub fn runtime_dir() -> Result<PathBuf> {
+    dirs::runtime_dir()
+        .map(|p| p.join(env!("CARGO_PKG_NAME")))
+        .map_or_else(cache_dir, Ok)
+}
+
+/// Files that can be deleted at any time, such as locks
+pub fn cache_dir() -> Result<PathBuf> {
+    dirs::cache_dir()
+        .map(|p| p.join(env!("CARGO_PKG_NAME")))
+        .context("failed to get cache dir")
+}
@@ -0,0 +1,37 @@
+use anyhow::Result;
+
+use crate::setup;
+
+/// Names of pinned repositories, which `list` shows first and `prune --stale` never suggests,
+/// are stored as `grm.pinned` in the global config (multi-valued)
+const KEY: &str = concat!(env!("CARGO_PKG_NAME"), ".pinned");
+
+pub fn list(config: &git2::Config) -> Result<Vec<String>> {
+    let mut names = Vec::new();
+    if let Ok(entries) = config.multivar(KEY, None) {
+        entries.for_each(|entry| {
+            if let Some(value) = entry.value() {
+                names.push(value.to_string());
+            }
+        })?;
+    }
+    Ok(names)
+}
+
+/// Returns `false` if the repository is already pinned
+pub fn add(config: &git2::Config, name: &str) -> Result<bool> {
+    if list(config)?.iter().any(|n| n == name) {
+        return Ok(false);
+    }
+    setup::global_config()?.set_multivar(KEY, "^$", name)?;
+    Ok(true)
+}
+
+/// Returns `false` if the repository is not pinned
+pub fn remove(config: &git2::Config, name: &str) -> Result<bool> {
+    if !list(config)?.iter().any(|n| n == name) {
+        return Ok(false);
+    }
+    setup::global_config()?.remove_multivar(KEY, &format!("^{}$", regex::escape(name)))?;
+    Ok(true)
+}
@@ -0,0 +1,166 @@
+use std::path::PathBuf;
+
+use anyhow::{ensure, Result};
+use git2::Repository;
+
+/// Settings grouped under `grm.profile.<name>.*`, which take precedence over the global ones
+#[derive(Default)]
+pub struct Profile {
+    /// `None` for the global settings
+    pub name: Option<String>,
+    pub root: Option<PathBuf>,
+    pub user: Option<String>,
+    /// `ssh` or `https`
+    pub protocol: Option<String>,
+    pub token: Option<String>,
+    pub ssh_key: Option<PathBuf>,
+    /// `user.name` written to repositories
+    pub commit_name: Option<String>,
+    /// `user.email` written to repositories
+    pub commit_email: Option<String>,
+    /// `user.signingKey` written to repositories
+    pub signing_key: Option<String>,
+}
+
+impl Profile {
+    /// Load the profile `name`, or an empty one if no profile is selected
+    pub fn load(config: &git2::Config, name: Option<&str>) -> Result<Self> {
+        let Some(name) = name else {
+            return Ok(Self::default());
+        };
+        let key = |key: &str| format!("{}.profile.{name}.{key}", env!("CARGO_PKG_NAME"));
+        let mut entries = config.entries(Some(&format!(
+            "^{}\\.profile\\.{}\\.",
+            env!("CARGO_PKG_NAME"),
+            regex::escape(name)
+        )))?;
+        ensure!(
+            entries.next().is_some(),
+            "profile `{name}` is not configured"
+        );
+
+        Ok(Self {
+            name: Some(name.to_string()),
+            root: config.get_path(&key("root")).ok(),
+            user: config.get_string(&key("user")).ok(),
+            protocol: config.get_string(&key("protocol")).ok(),
+            token: config.get_string(&key("token")).ok(),
+            ssh_key: config.get_path(&key("sshKey")).ok(),
+            commit_name: config.get_string(&key("name")).ok(),
+            commit_email: config.get_string(&key("email")).ok(),
+            signing_key: config.get_string(&key("signingKey")).ok(),
+        })
+    }
+
+    /// Find the profile whose `grm.profile.<name>.host` is `host`
+    pub fn for_host(config: &git2::Config, host: &str) -> Result<Option<Self>> {
+        let pattern = format!("^{}\\.profile\\..+\\.host$", env!("CARGO_PKG_NAME"));
+        let mut entries = config.entries(Some(&pattern))?;
+        while let Some(entry) = entries.next() {
+            let entry = entry?;
+            if !entry.value().is_some_and(|v| v.eq_ignore_ascii_case(host)) {
+                continue;
+            }
+            let Some(name) = entry.name().and_then(|name| {
+                name.strip_prefix(concat!(env!("CARGO_PKG_NAME"), ".profile."))?
+                    .strip_suffix(".host")
+            }) else {
+                continue;
+            };
+            return Self::load(config, Some(name)).map(Some);
+        }
+        Ok(None)
+    }
+
+    pub fn ssh(&self) -> bool {
+        self.protocol.as_deref() == Some("ssh")
+    }
+
+    /// The commit identity written into repositories of `host`
+    ///
+    /// `grm.<host>.user` and `grm.<host>.email` are used if the profile does not set them.
+    pub fn identity(
+        &self,
+        config: &git2::Config,
+        host: Option<&str>,
+    ) -> Vec<(&'static str, String)> {
+        let host_value = |key: &str| {
+            let host = host?;
+            config
+                .get_string(&format!("{}.{host}.{key}", env!("CARGO_PKG_NAME")))
+                .ok()
+        };
+        let name = self.commit_name.clone().or_else(|| host_value("user"));
+        let email = self.commit_email.clone().or_else(|| host_value("email"));
+        name.map(|name| ("user.name", name))
+            .into_iter()
+            .chain(email.map(|email| ("user.email", email)))
+            .collect()
+    }
+
+    /// Write the commit identity into the local config of `repo`
+    pub fn apply_identity(
+        &self,
+        config: &git2::Config,
+        host: Option<&str>,
+        repo: &Repository,
+    ) -> Result<()> {
+        set_local(repo, &self.identity(config, host))
+    }
+
+    /// The key of the profile, or `grm.<host>.signingKey`
+    pub fn signing_key(&self, config: &git2::Config, host: Option<&str>) -> Option<String> {
+        self.signing_key.clone().or_else(|| {
+            let host = host?;
+            config
+                .get_string(&format!("{}.{host}.signingKey", env!("CARGO_PKG_NAME")))
+                .ok()
+        })
+    }
+
+    /// Settings signing every commit with the signing key, if any
+    ///
+    /// SSH keys, either a public key or a path to one, set `gpg.format` to `ssh`.
+    pub fn signing(
+        &self,
+        config: &git2::Config,
+        host: Option<&str>,
+    ) -> Vec<(&'static str, String)> {
+        let Some(key) = self.signing_key(config, host) else {
+            return Vec::new();
+        };
+        let mut values = vec![("user.signingKey", key.clone())];
+        if is_ssh_key(&key) {
+            values.push(("gpg.format", "ssh".to_string()));
+        }
+        values.push(("commit.gpgsign", "true".to_string()));
+        values.push(("tag.gpgsign", "true".to_string()));
+        values
+    }
+
+    pub fn apply_signing(
+        &self,
+        config: &git2::Config,
+        host: Option<&str>,
+        repo: &Repository,
+    ) -> Result<()> {
+        set_local(repo, &self.signing(config, host))
+    }
+}
+
+/// Write `values` into the local config of `repo`
+pub fn set_local(repo: &Repository, values: &[(&str, String)]) -> Result<()> {
+    let mut local = repo.config()?.open_level(git2::ConfigLevel::Local)?;
+    for (key, value) in values {
+        local.set_str(key, value)?;
+    }
+    Ok(())
+}
+
+fn is_ssh_key(key: &str) -> bool {
+    key.starts_with("ssh-")
+        || key.starts_with("ecdsa-")
+        || key.starts_with("sk-")
+        || key.starts_with("key::")
+        || key.ends_with(".pub")
+}
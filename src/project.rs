@@ -0,0 +1,50 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{ensure, Context, Result};
+use git2::Repository;
+
+use crate::setup;
+
+/// Projects, subdirectories of repositories with names, are stored as `grm.project.<name>` in
+/// the global config
+fn key(name: &str) -> String {
+    format!("{}.project.{name}", env!("CARGO_PKG_NAME"))
+}
+
+pub fn list(config: &git2::Config) -> Result<Vec<(String, PathBuf)>> {
+    let prefix = key("");
+    let mut projects = Vec::new();
+    config
+        .entries(Some(&format!("^{}", regex::escape(&prefix))))?
+        .for_each(|entry| {
+            if let (Some(name), Some(value)) = (entry.name(), entry.value()) {
+                projects.push((name[prefix.len()..].to_string(), PathBuf::from(value)));
+            }
+        })?;
+    Ok(projects)
+}
+
+pub fn get(config: &git2::Config, name: &str) -> Option<PathBuf> {
+    config.get_path(&key(name)).ok()
+}
+
+/// Register the directory at `path`, which must be inside a repository
+pub fn add(name: &str, path: &Path) -> Result<PathBuf> {
+    ensure!(
+        !name.is_empty() && !name.contains(['/', '\\']),
+        "`{name}` cannot be a project name"
+    );
+    let path = path
+        .canonicalize()
+        .with_context(|| format!("`{}` does not exist", path.display()))?;
+    ensure!(path.is_dir(), "`{}` is not a directory", path.display());
+    Repository::discover(&path)
+        .with_context(|| format!("`{}` is not in a repository", path.display()))?;
+    setup::global_config()?.set_str(&key(name), &path.display().to_string())?;
+    Ok(path)
+}
+
+/// Returns `false` if the project is not registered
+pub fn remove(name: &str) -> Result<bool> {
+    setup::remove_global(&key(name))
+}
@@ -0,0 +1,169 @@
+//! URLs of providers not following the `host/owner/repo` model
+//!
+//! Azure DevOps repositories are at `dev.azure.com/<org>/<project>/_git/<repo>` over HTTPS and
+//! `ssh.dev.azure.com:v3/<org>/<project>/<repo>` over SSH, and Bitbucket Server (Data Center)
+//! serves them at `<host>/scm/<project>/<repo>.git` over HTTPS and on port 7999 over SSH. Both
+//! land in `<host>/<org>/<project>/<repo>` and `<host>/<project>/<repo>.git` under the root,
+//! whichever protocol they are cloned with.
+
+use anyhow::{Context, Result};
+use url::Url;
+
+const AZURE_HOST: &str = "dev.azure.com";
+const AZURE_SSH_HOST: &str = "ssh.dev.azure.com";
+const BITBUCKET_SERVER_SSH_PORT: u16 = 7999;
+
+#[derive(Debug, PartialEq, Eq)]
+enum Provider {
+    AzureDevOps,
+    BitbucketServer,
+    Other,
+}
+
+fn detect(url: &Url) -> Provider {
+    match url.host_str() {
+        Some(AZURE_HOST | AZURE_SSH_HOST) => Provider::AzureDevOps,
+        _ if url.scheme() == "ssh" && url.port() == Some(BITBUCKET_SERVER_SSH_PORT) => {
+            Provider::BitbucketServer
+        }
+        _ if url.scheme().starts_with("http") && url.path().starts_with("/scm/") => {
+            Provider::BitbucketServer
+        }
+        _ => Provider::Other,
+    }
+}
+
+fn segments(url: &Url) -> Vec<&str> {
+    url.path_segments()
+        .map(|segments| segments.filter(|s| !s.is_empty()).collect())
+        .unwrap_or_default()
+}
+
+/// Complete a spec like `dev.azure.com/org/project/repo`, where `project` defaults to `repo`
+pub fn complete(spec: &str, ssh: bool) -> Option<Result<Url>> {
+    let rest = spec.strip_prefix(AZURE_HOST)?.strip_prefix('/')?;
+    let parts: Vec<&str> = rest.split('/').filter(|s| *s != "_git").collect();
+    let (org, project, repo) = match parts.as_slice() {
+        [org, repo] => (*org, *repo, *repo),
+        [org, project, repo] => (*org, *project, *repo),
+        _ => {
+            return Some(Err(anyhow::anyhow!(
+                "expected `{AZURE_HOST}/<org>/<project>/<repo>`, got `{spec}`"
+            )))
+        }
+    };
+    Some(azure_url(org, project, repo, ssh))
+}
+
+fn azure_url(org: &str, project: &str, repo: &str, ssh: bool) -> Result<Url> {
+    Ok(if ssh {
+        Url::parse(&format!(
+            "ssh://git@{AZURE_SSH_HOST}/v3/{org}/{project}/{repo}"
+        ))?
+    } else {
+        Url::parse(&format!("https://{AZURE_HOST}/{org}/{project}/_git/{repo}"))?
+    })
+}
+
+/// The host directory and the path under it of `url`, or `None` for other providers
+pub fn repo_path(url: &Url) -> Option<(String, String)> {
+    let segments = segments(url);
+    match detect(url) {
+        Provider::AzureDevOps => {
+            let path = match segments.as_slice() {
+                ["v3", rest @ ..] => rest.join("/"),
+                segments => segments
+                    .iter()
+                    .filter(|s| **s != "_git")
+                    .copied()
+                    .collect::<Vec<_>>()
+                    .join("/"),
+            };
+            Some((AZURE_HOST.to_string(), path))
+        }
+        Provider::BitbucketServer => {
+            let path = match segments.as_slice() {
+                ["scm", rest @ ..] => rest.join("/"),
+                segments => segments.join("/"),
+            };
+            Some((url.host_str()?.to_string(), path))
+        }
+        Provider::Other => None,
+    }
+}
+
+/// Convert `url` to an SSH URL or an HTTPS URL, or `None` for other providers
+pub fn with_protocol(url: &Url, ssh: bool) -> Option<Result<Url>> {
+    let provider = detect(url);
+    if provider == Provider::Other {
+        return None;
+    }
+    let (host, path) = repo_path(url)?;
+    Some(match provider {
+        Provider::AzureDevOps => match path.split('/').collect::<Vec<_>>().as_slice() {
+            [org, project, repo] => azure_url(org, project, repo, ssh),
+            _ => Err(anyhow::anyhow!("unexpected Azure DevOps URL `{url}`")),
+        },
+        _ if ssh => Url::parse(&format!(
+            "ssh://git@{host}:{BITBUCKET_SERVER_SSH_PORT}/{path}"
+        ))
+        .context("invalid URL"),
+        _ => Url::parse(&format!("https://{host}/scm/{path}")).context("invalid URL"),
+    })
+}
+
+#[cfg(test)]
+mod test_repo_path {
+    use super::*;
+
+    fn path(url: &str) -> Option<(String, String)> {
+        repo_path(&Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn azure_devops() {
+        let expected = Some(("dev.azure.com".into(), "org/proj/repo".into()));
+        assert_eq!(path("https://dev.azure.com/org/proj/_git/repo"), expected);
+        assert_eq!(
+            path("https://org@dev.azure.com/org/proj/_git/repo"),
+            expected
+        );
+        assert_eq!(
+            path("ssh://git@ssh.dev.azure.com/v3/org/proj/repo"),
+            expected
+        );
+    }
+
+    #[test]
+    fn bitbucket_server() {
+        let expected = Some(("git.corp".into(), "proj/repo.git".into()));
+        assert_eq!(path("https://git.corp/scm/proj/repo.git"), expected);
+        assert_eq!(path("ssh://git@git.corp:7999/proj/repo.git"), expected);
+        assert_eq!(path("https://bitbucket.org/owner/repo"), None);
+    }
+}
+
+#[cfg(test)]
+mod test_with_protocol {
+    use super::*;
+
+    #[test]
+    fn conversions() {
+        let convert = |url: &str, ssh| {
+            with_protocol(&Url::parse(url).unwrap(), ssh).map(|url| url.unwrap().to_string())
+        };
+        assert_eq!(
+            convert("https://dev.azure.com/org/proj/_git/repo", true).as_deref(),
+            Some("ssh://git@ssh.dev.azure.com/v3/org/proj/repo")
+        );
+        assert_eq!(
+            convert("ssh://git@git.corp:7999/proj/repo.git", false).as_deref(),
+            Some("https://git.corp/scm/proj/repo.git")
+        );
+        assert_eq!(convert("https://github.com/a/b", true), None);
+        assert_eq!(
+            complete("dev.azure.com/org/repo", false).map(|url| url.unwrap().to_string()),
+            Some("https://dev.azure.com/org/repo/_git/repo".into())
+        );
+    }
+}
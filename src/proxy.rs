@@ -0,0 +1,116 @@
+use std::process::Command;
+
+use url::Url;
+
+/// How to reach a remote host
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Proxy {
+    Direct,
+    Url(String),
+}
+
+/// Resolve the proxy for `url` in the following order:
+///
+/// 1. `grm.<host>.proxy` (an empty value means no proxy)
+/// 2. `NO_PROXY` / `no_proxy`
+/// 3. `http.proxy`
+/// 4. `https_proxy` / `http_proxy` / `all_proxy` and their upper case variants
+pub fn resolve(config: &git2::Config, url: &Url) -> Proxy {
+    if !matches!(url.scheme(), "http" | "https") {
+        return Proxy::Direct;
+    }
+    let Some(host) = url.host_str() else {
+        return Proxy::Direct;
+    };
+
+    let key = format!("{}.{host}.proxy", env!("CARGO_PKG_NAME"));
+    if let Ok(proxy) = config.get_string(&key) {
+        return from_value(proxy);
+    }
+
+    if let Some(no_proxy) = env_var(&["no_proxy", "NO_PROXY"]) {
+        if is_no_proxy(&no_proxy, host) {
+            return Proxy::Direct;
+        }
+    }
+
+    if let Ok(proxy) = config.get_string("http.proxy") {
+        return from_value(proxy);
+    }
+
+    let vars: &[&str] = if url.scheme() == "https" {
+        &["https_proxy", "HTTPS_PROXY", "all_proxy", "ALL_PROXY"]
+    } else {
+        &["http_proxy", "HTTP_PROXY", "all_proxy", "ALL_PROXY"]
+    };
+    env_var(vars).map_or(Proxy::Direct, from_value)
+}
+
+/// Make a `git` command talking to the remote at `url` go through the proxy resolved for it,
+/// leaving the other remotes of the command alone
+pub fn apply(command: &mut Command, config: &git2::Config, url: &str) {
+    let Ok(parsed) = Url::parse(url) else {
+        return;
+    };
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return;
+    }
+    // git disables proxying for an empty value
+    let proxy = match resolve(config, &parsed) {
+        Proxy::Direct => String::new(),
+        Proxy::Url(proxy) => proxy,
+    };
+    command.arg("-c").arg(format!("http.{url}.proxy={proxy}"));
+}
+
+fn from_value(value: String) -> Proxy {
+    if value.is_empty() {
+        Proxy::Direct
+    } else {
+        Proxy::Url(value)
+    }
+}
+
+fn env_var(names: &[&str]) -> Option<String> {
+    names
+        .iter()
+        .find_map(|name| std::env::var(name).ok().filter(|v| !v.is_empty()))
+}
+
+/// Whether `host` matches one of the comma-separated entries in `no_proxy`
+fn is_no_proxy(no_proxy: &str, host: &str) -> bool {
+    let host = host.to_ascii_lowercase();
+    no_proxy
+        .split(',')
+        .map(|entry| entry.trim().to_ascii_lowercase())
+        .any(|entry| {
+            if entry == "*" {
+                return true;
+            }
+            let entry = entry.trim_start_matches("*.").trim_start_matches('.');
+            !entry.is_empty() && (host == entry || host.ends_with(&format!(".{entry}")))
+        })
+}
+
+#[cfg(test)]
+mod test_is_no_proxy {
+    use super::*;
+
+    #[test]
+    fn match_exact_host() {
+        assert!(is_no_proxy("localhost,github.com", "github.com"));
+    }
+
+    #[test]
+    fn match_subdomain() {
+        assert!(is_no_proxy(".example.com", "git.example.com"));
+        assert!(is_no_proxy("example.com", "git.example.com"));
+        assert!(!is_no_proxy("example.com", "notexample.com"));
+    }
+
+    #[test]
+    fn match_wildcard() {
+        assert!(is_no_proxy("*", "github.com"));
+        assert!(!is_no_proxy("", "github.com"));
+    }
+}
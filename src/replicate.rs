@@ -0,0 +1,119 @@
+//! Mirroring managed repositories to a second remote, e.g. from GitHub to an internal Gitea
+
+use std::path::Path;
+
+use anyhow::{ensure, Result};
+use git2::Repository;
+
+use crate::{git, proxy};
+
+/// Where `grm replicate` pushes to
+#[derive(Debug, PartialEq, Eq)]
+pub enum Target {
+    /// A URL or a path of an existing repository
+    Url(String),
+    /// A forge to create the repository on, owned by `owner` or by the user of the token
+    Forge { host: String, owner: Option<String> },
+}
+
+impl Target {
+    /// Parse `host`, `host/owner`, or a URL such as `ssh://...`, `git@host:owner/repo` or a path
+    pub fn parse(target: &str) -> Self {
+        let is_url = target.contains("://")
+            || target.starts_with(['/', '.', '~'])
+            // `git@host:owner/repo`, but not `host:port/owner`
+            || target.split_once(':').is_some_and(|(host, path)| {
+                !host.contains('/') && !path.starts_with(|c: char| c.is_ascii_digit())
+            });
+        if is_url {
+            return Self::Url(target.to_string());
+        }
+        match target.trim_end_matches('/').split_once('/') {
+            Some((host, owner)) => Self::Forge {
+                host: host.to_string(),
+                owner: Some(owner.to_string()),
+            },
+            None => Self::Forge {
+                host: target.to_string(),
+                owner: None,
+            },
+        }
+    }
+}
+
+/// Point the remote `name` to `url`, adding it if missing
+pub fn set_remote(repo: &Repository, name: &str, url: &str) -> Result<()> {
+    match repo.find_remote(name) {
+        Ok(_) => repo.remote_set_url(name, url)?,
+        Err(_) => {
+            repo.remote(name, url)?;
+        }
+    }
+    Ok(())
+}
+
+/// Push every branch of `origin_name` and every tag of the repository at `path` to `remote`
+///
+/// Branches are taken from the remote-tracking ones, so that the mirror has the branches of the
+/// origin rather than the ones checked out locally.
+pub fn push(path: &Path, origin_name: &str, remote: &str, force: bool) -> Result<()> {
+    let repo = Repository::open(path)?;
+    let prefix = format!("refs/remotes/{origin_name}/");
+    let mut refspecs = Vec::new();
+    for reference in repo.references_glob(&format!("{prefix}*"))? {
+        let reference = reference?;
+        // `<origin>/HEAD` only points to the default branch
+        if reference.kind() == Some(git2::ReferenceType::Symbolic) {
+            continue;
+        }
+        let Some(name) = reference.name() else {
+            continue;
+        };
+        let branch = &name[prefix.len()..];
+        refspecs.push(format!("{name}:refs/heads/{branch}"));
+    }
+    ensure!(
+        !refspecs.is_empty(),
+        "`{origin_name}` has no branches to push"
+    );
+    refspecs.push("refs/tags/*:refs/tags/*".into());
+
+    let mut command = git::command(path);
+    if let Some(url) = repo.find_remote(remote)?.url() {
+        proxy::apply(&mut command, &repo.config()?, url);
+    }
+    command.arg("push");
+    if force {
+        command.arg("--force");
+    }
+    git::run(command.arg(remote).args(refspecs))
+}
+
+#[cfg(test)]
+mod test_target {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let forge = |host: &str, owner: Option<&str>| Target::Forge {
+            host: host.into(),
+            owner: owner.map(String::from),
+        };
+        assert_eq!(Target::parse("gitea.local"), forge("gitea.local", None));
+        assert_eq!(
+            Target::parse("gitea.local:3000/me"),
+            forge("gitea.local:3000", Some("me"))
+        );
+        assert_eq!(
+            Target::parse("gitea.local/mirrors/"),
+            forge("gitea.local", Some("mirrors"))
+        );
+        for url in [
+            "https://gitea.local/a/b.git",
+            "git@gitea.local:a/b.git",
+            "/srv/git/b.git",
+        ] {
+            assert_eq!(Target::parse(url), Target::Url(url.into()));
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use anyhow::Result;
+use dirs::home_dir;
+
+use crate::profile::Profile;
+
+/// File placed at the top of a root directory created by grm
+pub const MARKER: &str = concat!(".", env!("CARGO_PKG_NAME"), "-root");
+
+/// Root directories grm may use, in the order of precedence
+pub struct Candidates {
+    /// `GRM_ROOT`
+    pub env: Option<PathBuf>,
+    /// `grm.profile.<name>.root`
+    pub profile: Option<PathBuf>,
+    /// `grm.<host>.root`
+    pub host: Option<PathBuf>,
+    /// `grm.root`
+    pub configured: Option<PathBuf>,
+    /// `~/grm`
+    pub fallback: Option<PathBuf>,
+}
+
+impl Candidates {
+    pub fn new(config: &git2::Config, profile: &Profile) -> Self {
+        Self {
+            env: std::env::var_os("GRM_ROOT")
+                .filter(|v| !v.is_empty())
+                .map(PathBuf::from),
+            profile: profile.root.clone(),
+            host: None,
+            configured: config
+                .get_path(concat!(env!("CARGO_PKG_NAME"), ".root"))
+                .ok(),
+            fallback: home_dir().map(|p| p.join(env!("CARGO_PKG_NAME"))),
+        }
+    }
+
+    /// Use the root of `host` if it is configured
+    pub fn with_host(mut self, config: &git2::Config, host: Option<&str>) -> Self {
+        self.host = host.and_then(|host| config.get_path(&host_key(host)).ok());
+        self
+    }
+
+    pub fn active(&self) -> Option<&Path> {
+        self.env
+            .as_deref()
+            .or(self.profile.as_deref())
+            .or(self.host.as_deref())
+            .or(self.configured.as_deref())
+            .or(self.fallback.as_deref())
+    }
+}
+
+fn host_key(host: &str) -> String {
+    format!("{}.{host}.root", env!("CARGO_PKG_NAME"))
+}
+
+/// Subsections of `grm` that are not hosts
+const SECTIONS: &[&str] = &["alias", "profile", "project", "transport"];
+
+/// The host of a `grm.<host>.root` key, `None` for other keys such as `grm.project.root`
+fn key_host(name: &str) -> Option<&str> {
+    let host = name
+        .strip_prefix(concat!(env!("CARGO_PKG_NAME"), "."))?
+        .strip_suffix(".root")?;
+    let section = host.split('.').next().unwrap_or(host);
+    if SECTIONS.contains(&host) || (section == "profile" && host != section) {
+        return None;
+    }
+    url::Host::parse(host.split(':').next().unwrap_or(host))
+        .is_ok()
+        .then_some(host)
+}
+
+/// Roots configured with `grm.<host>.root` for any host
+pub fn host_roots(config: &git2::Config) -> Result<Vec<PathBuf>> {
+    let pattern = format!(r"^{}\..+\.root$", env!("CARGO_PKG_NAME"));
+    let mut names = Vec::new();
+    config.entries(Some(&pattern))?.for_each(|entry| {
+        if let Some(name) = entry.name() {
+            if key_host(name).is_some() {
+                names.push(name.to_string());
+            }
+        }
+    })?;
+    Ok(names
+        .iter()
+        .filter_map(|name| config.get_path(name).ok())
+        .collect())
+}
+
+/// The innermost of `roots` containing `path`, or the first one if none does
+pub fn containing<'a>(roots: &'a [PathBuf], path: &Path) -> &'a Path {
+    roots
+        .iter()
+        .filter(|root| path.starts_with(root))
+        .max_by_key(|root| root.components().count())
+        .unwrap_or(&roots[0])
+}
+
+/// `path` relative to the innermost of `roots` containing it, separated by `/`
+pub fn relative(roots: &[PathBuf], path: &Path) -> String {
+    path.strip_prefix(containing(roots, path))
+        .unwrap_or(path)
+        .display()
+        .to_string()
+        .replace('\\', "/")
+}
+
+pub fn is_marked(root: &Path) -> bool {
+    root.join(MARKER).is_file()
+}
+
+/// Print warnings if the active root looks like it is not the one grm has been using
+pub fn check(candidates: &Candidates) {
+    let Some(root) = candidates.active() else {
+        return;
+    };
+    let is_empty = root
+        .read_dir()
+        .map_or(true, |mut entries| entries.next().is_none());
+    if !is_empty && !is_marked(root) {
+        warn!(
+            "`{}` is not marked as a root directory (`{MARKER}` is missing); check `grm.root` or run `grm doctor --mark`",
+            root.display()
+        );
+    }
+    if candidates.env.is_some() || candidates.profile.is_some() || candidates.host.is_some() {
+        return;
+    }
+    if let (Some(configured), Some(fallback)) = (&candidates.configured, &candidates.fallback) {
+        if configured != fallback && !is_marked(configured) && is_marked(fallback) {
+            warn!(
+                "`grm.root` points to `{}`, but repositories were managed under `{}`",
+                configured.display(),
+                fallback.display()
+            );
+        }
+    }
+}
+
+/// [`check`] each active root only once, however many times the root is looked up
+pub fn check_once(candidates: &Candidates) {
+    static CHECKED: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+    let Some(root) = candidates.active() else {
+        return;
+    };
+    let mut checked = CHECKED.lock().unwrap_or_else(|err| err.into_inner());
+    if !checked.iter().any(|checked| checked == root) {
+        checked.push(root.to_path_buf());
+        check(candidates);
+    }
+}
+
+/// Create the root directory with its marker if it does not exist yet
+pub fn prepare(root: &Path) -> Result<()> {
+    if root.exists() {
+        return Ok(());
+    }
+    std::fs::create_dir_all(root)?;
+    mark(root)
+}
+
+pub fn mark(root: &Path) -> Result<()> {
+    std::fs::write(
+        root.join(MARKER),
+        concat!("# managed by ", env!("CARGO_PKG_NAME"), "\n"),
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test_key_host {
+    use super::*;
+
+    #[test]
+    fn hosts_only() {
+        assert_eq!(key_host("grm.github.com.root"), Some("github.com"));
+        assert_eq!(key_host("grm.localhost:8080.root"), Some("localhost:8080"));
+        assert_eq!(key_host("grm.project.root"), None);
+        assert_eq!(key_host("grm.alias.root"), None);
+        assert_eq!(key_host("grm.profile.work.root"), None);
+        assert_eq!(key_host("grm.root"), None);
+        assert_eq!(key_host("grm.github.com.token"), None);
+    }
+}
@@ -0,0 +1,131 @@
+//! Work that would be lost by deleting or archiving a repository, checked by destructive commands
+//! before they run
+
+use std::path::Path;
+
+use anyhow::{bail, ensure, Result};
+use git2::{BranchType, Repository, StatusOptions};
+
+use crate::setup;
+
+/// Files listed by name before the rest are counted
+const MAX_FILES: usize = 5;
+
+/// Uncommitted changes, commits on no remote and stashes of the repository at `path`
+pub fn check(path: &Path) -> Result<Vec<String>> {
+    let mut repo = Repository::open(path)?;
+    let mut losses = Vec::new();
+    if !repo.is_bare() {
+        let mut options = StatusOptions::new();
+        options.include_untracked(true).exclude_submodules(true);
+        let files: Vec<String> = repo
+            .statuses(Some(&mut options))?
+            .iter()
+            .filter_map(|entry| entry.path().map(String::from))
+            .collect();
+        if !files.is_empty() {
+            losses.push(describe_files(&files));
+        }
+    }
+    for (branch, count) in unpushed(&repo)? {
+        losses.push(t!(UnpushedCommits, count, branch));
+    }
+    let stashes = count_stashes(&mut repo)?;
+    if stashes > 0 {
+        losses.push(t!(Stashes, stashes));
+    }
+    Ok(losses)
+}
+
+/// What an archive cannot hold: every stash but the latest one, as the bundle has no reflogs
+pub fn check_archive(path: &Path) -> Result<Vec<String>> {
+    let stashes = count_stashes(&mut Repository::open(path)?)?;
+    Ok(if stashes > 1 {
+        vec![t!(OlderStashes, stashes - 1)]
+    } else {
+        Vec::new()
+    })
+}
+
+/// Show `losses` of the repository `name`, then fail unless `force` is set and the name is typed
+/// on the terminal
+pub fn confirm(name: &str, losses: &[String], force: bool) -> Result<()> {
+    if losses.is_empty() {
+        return Ok(());
+    }
+    let mut message = t!(WorkWouldBeLost, name);
+    for loss in losses {
+        message.push_str(&format!("\n  {loss}"));
+    }
+    if !force {
+        bail!("{message}\n{}", t!(PassForce));
+    }
+    warn!("{message}");
+    ensure!(setup::is_interactive(), t!(ConfirmNeedsTerminal));
+    let answer = setup::ask(&t!(TypeNameToLose, name), "")?;
+    ensure!(answer == name, t!(Cancelled));
+    Ok(())
+}
+
+fn describe_files(files: &[String]) -> String {
+    let mut description = t!(
+        UncommittedFiles,
+        files.len(),
+        files[..files.len().min(MAX_FILES)].join(", ")
+    );
+    if files.len() > MAX_FILES {
+        description.push_str(&t!(AndMore, files.len() - MAX_FILES));
+    }
+    description
+}
+
+/// Local branches with commits not reachable from any remote-tracking branch, and their counts
+fn unpushed(repo: &Repository) -> Result<Vec<(String, usize)>> {
+    let mut remote_tips = Vec::new();
+    for reference in repo.references_glob("refs/remotes/*")? {
+        if let Some(target) = reference?.target() {
+            remote_tips.push(target);
+        }
+    }
+    let mut branches = Vec::new();
+    for branch in repo.branches(Some(BranchType::Local))? {
+        let (branch, _) = branch?;
+        let (Some(name), Some(tip)) = (branch.name()?, branch.get().target()) else {
+            continue;
+        };
+        let mut walk = repo.revwalk()?;
+        walk.push(tip)?;
+        for tip in &remote_tips {
+            walk.hide(*tip)?;
+        }
+        let count = walk.count();
+        if count > 0 {
+            branches.push((name.to_string(), count));
+        }
+    }
+    Ok(branches)
+}
+
+fn count_stashes(repo: &mut Repository) -> Result<usize> {
+    let mut count = 0;
+    repo.stash_foreach(|_, _, _| {
+        count += 1;
+        true
+    })?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod test_describe_files {
+    use super::*;
+
+    #[test]
+    fn truncated() {
+        let files: Vec<String> = (1..=7).map(|i| format!("f{i}")).collect();
+        assert_eq!(describe_files(&files[..2]), "2 uncommitted files: f1, f2");
+        assert_eq!(
+            describe_files(&files),
+            "7 uncommitted files: f1, f2, f3, f4, f5 and 2 more"
+        );
+    }
+}
@@ -0,0 +1,153 @@
+//! Repository names that are not valid paths on every filesystem, such as `aux` or `a:b` on
+//! Windows, and the original names of the ones saved under a replaced name
+
+use std::{collections::BTreeMap, path::PathBuf};
+
+use anyhow::{bail, ensure, Result};
+
+use crate::paths;
+
+/// `grm.invalidNames`
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Mode {
+    /// Use the names as they are, the default except on Windows
+    Keep,
+    /// Fail to create such repositories, the default on Windows
+    Refuse,
+    /// Replace the offending parts with `grm.invalidNameReplacement`, `_` by default
+    Replace,
+}
+
+impl Mode {
+    pub fn from_config(config: &git2::Config) -> Result<Self> {
+        let key = concat!(env!("CARGO_PKG_NAME"), ".invalidNames");
+        let Ok(name) = config.get_string(key) else {
+            return Ok(if cfg!(windows) {
+                Self::Refuse
+            } else {
+                Self::Keep
+            });
+        };
+        <Self as clap::ValueEnum>::from_str(&name, true)
+            .map_err(|_| anyhow::anyhow!("unknown value `{name}` in `{key}`"))
+    }
+}
+
+const RESERVED: &[&str] = &[
+    "con", "prn", "aux", "nul", "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8",
+    "com9", "lpt1", "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
+];
+
+fn is_invalid_char(c: char) -> bool {
+    c.is_control() || matches!(c, '<' | '>' | ':' | '"' | '\\' | '|' | '?' | '*')
+}
+
+/// Why `component` cannot be a file name on Windows
+fn problem(component: &str) -> Option<&'static str> {
+    let stem = component.split('.').next().unwrap_or_default();
+    if RESERVED.contains(&stem.trim_end().to_lowercase().as_str()) {
+        Some("it is a reserved device name")
+    } else if component.contains(is_invalid_char) {
+        Some("it contains a reserved character")
+    } else if component.ends_with(['.', ' ']) {
+        Some("it ends with a dot or a space")
+    } else {
+        None
+    }
+}
+
+fn replace(component: &str, replacement: &str) -> String {
+    let mut replaced: String = component
+        .chars()
+        .map(|c| {
+            if is_invalid_char(c) {
+                replacement.to_string()
+            } else {
+                c.to_string()
+            }
+        })
+        .collect();
+    let kept = replaced.trim_end_matches(['.', ' ']).len();
+    let trailing = replaced.len() - kept;
+    replaced.truncate(kept);
+    replaced.push_str(&replacement.repeat(trailing));
+    let stem = replaced.find('.').unwrap_or(replaced.len());
+    if RESERVED.contains(&replaced[..stem].trim_end().to_lowercase().as_str()) {
+        replaced.insert_str(stem, replacement);
+    }
+    replaced
+}
+
+/// Check `name`, a path relative to the root like `github.com/owner/repo`, as `grm.invalidNames`
+/// says, returning the name to save the repository as
+pub fn apply(config: &git2::Config, name: &str) -> Result<String> {
+    let mode = Mode::from_config(config)?;
+    if mode == Mode::Keep {
+        return Ok(name.to_string());
+    }
+    let replacement = config
+        .get_string(concat!(env!("CARGO_PKG_NAME"), ".invalidNameReplacement"))
+        .unwrap_or_else(|_| "_".into());
+    ensure!(
+        !replacement.contains(is_invalid_char) && !replacement.contains(['/', '.', ' ']),
+        "`{replacement}` cannot replace invalid characters"
+    );
+    let mut components = Vec::new();
+    for component in name.split('/') {
+        match problem(component) {
+            None => components.push(component.to_string()),
+            Some(reason) if mode == Mode::Refuse => bail!(
+                "`{name}` is not a valid path on every filesystem because {reason}; set \
+                 grm.invalidNames to replace to save it under another name"
+            ),
+            Some(_) => components.push(replace(component, &replacement)),
+        }
+    }
+    Ok(components.join("/"))
+}
+
+pub fn get_names_file() -> Result<PathBuf> {
+    Ok(paths::data_dir()?.join("names.json"))
+}
+
+fn load() -> Result<BTreeMap<String, String>> {
+    match std::fs::read_to_string(get_names_file()?) {
+        Ok(content) => Ok(serde_json::from_str(&content)?),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Record that the repository named `original` is saved as `name`
+pub fn remember(name: &str, original: &str) -> Result<()> {
+    let mut names = load()?;
+    names.insert(name.to_string(), original.to_string());
+    let file = get_names_file()?;
+    if let Some(parent) = file.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&file, serde_json::to_string_pretty(&names)?)?;
+    Ok(())
+}
+
+/// The name the repository saved as `name` has on its forge
+pub fn original(name: &str) -> Result<String> {
+    Ok(load()?.remove(name).unwrap_or_else(|| name.to_string()))
+}
+
+#[cfg(test)]
+mod test_replace {
+    use super::*;
+
+    #[test]
+    fn windows_names() {
+        assert_eq!(problem("repo"), None);
+        assert_eq!(problem("aux.rs"), Some("it is a reserved device name"));
+        assert_eq!(replace("aux", "_"), "aux_");
+        assert_eq!(replace("AUX.git", "_"), "AUX_.git");
+        assert_eq!(replace("con.", "_"), "con_");
+        assert_eq!(replace("a:b", "-"), "a-b");
+        assert_eq!(replace("dots..", "_"), "dots__");
+        assert_eq!(replace("auxiliary", "_"), "auxiliary");
+    }
+}
@@ -0,0 +1,211 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use git2::Repository;
+
+use crate::{glob, pinned, staging, tag};
+
+/// Result of walking a root directory
+#[derive(Default)]
+pub struct Walk {
+    pub repos: Vec<PathBuf>,
+    /// Directories repositories are being cloned into
+    pub temp_dirs: Vec<PathBuf>,
+}
+
+/// Depth of `<host>/<owner>/<name>`, plus two levels of nested groups such as GitLab subgroups
+const DEFAULT_DEPTH: usize = 5;
+
+/// `grm.scanDepth`, where 0 means no limit
+pub fn get_depth(config: &git2::Config) -> Option<usize> {
+    match config.get_i64(concat!(env!("CARGO_PKG_NAME"), ".scanDepth")) {
+        Ok(depth) => usize::try_from(depth).ok().filter(|&depth| depth > 0),
+        Err(_) => Some(DEFAULT_DEPTH),
+    }
+}
+
+/// Walk `root` up to `max_depth` levels, descending into repositories only if `nested` is set
+pub fn walk(root: &Path, max_depth: Option<usize>, nested: bool) -> Walk {
+    let mut walk = Walk::default();
+    let mut walker = walkdir::WalkDir::new(root)
+        .min_depth(1)
+        .max_depth(max_depth.unwrap_or(usize::MAX))
+        .into_iter();
+    while let Some(entry) = walker.next() {
+        let entry = match entry {
+            Ok(entry) => entry,
+            // a root that does not exist yet has no repositories
+            Err(err)
+                if err.depth() == 0
+                    && err.io_error().map(std::io::Error::kind)
+                        == Some(std::io::ErrorKind::NotFound) =>
+            {
+                continue
+            }
+            Err(err) => {
+                warn!("{err}");
+                continue;
+            }
+        };
+        let path = entry.path();
+        if staging::is_temp(entry.file_name()) {
+            walk.temp_dirs.push(path.to_path_buf());
+            walker.skip_current_dir();
+            continue;
+        }
+        if entry.file_name() == ".git" {
+            if entry.file_type().is_dir() {
+                walker.skip_current_dir();
+            }
+            continue;
+        }
+        if !path.join(".git").exists() || Repository::open(path).is_err() {
+            continue;
+        }
+        walk.repos.push(path.to_path_buf());
+        // symlinks are not descended into, so skipping would skip their siblings instead
+        if !nested && entry.file_type().is_dir() {
+            walker.skip_current_dir();
+        }
+    }
+    walk
+}
+
+/// Find repositories under `root` without descending into them
+pub fn repos(root: &Path, max_depth: Option<usize>) -> Vec<PathBuf> {
+    walk(root, max_depth, false).repos
+}
+
+/// The innermost of `repos` containing `path`
+pub fn parent<'a>(repos: &'a [PathBuf], path: &Path) -> Option<&'a PathBuf> {
+    repos
+        .iter()
+        .filter(|repo| repo.as_path() != path && path.starts_with(repo))
+        .max_by_key(|repo| repo.components().count())
+}
+
+/// Options to narrow down the repositories a command works on
+#[derive(clap::Args, Clone, Debug, Default)]
+pub struct Filter {
+    /// Only include repositories whose path contains the string, or matches the glob pattern
+    /// like `github.com/myorg/*` or `*/infra-*`
+    #[arg(long, short)]
+    pub query: Option<String>,
+    /// Only include repositories with the tag (can be repeated)
+    #[arg(long = "tag", short, value_name = "TAG")]
+    pub tags: Vec<String>,
+    /// Skip repositories matching the gitignore-style pattern (can be repeated), in addition
+    /// to `grm.exclude`
+    #[arg(long, value_name = "PATTERN")]
+    pub exclude: Vec<String>,
+    /// Don't look for repositories deeper than this under the root, overriding `grm.scanDepth`
+    #[arg(long, value_name = "DEPTH")]
+    pub max_depth: Option<usize>,
+    /// Also include repositories inside other repositories, like `grm.nested`
+    #[arg(long, default_value_t = false)]
+    pub nested: bool,
+    /// Only include repositories pinned with `grm pin`
+    #[arg(long, default_value_t = false)]
+    pub pinned: bool,
+    /// `grm.pinned`, read by `with_config` if `pinned` is set
+    #[arg(skip)]
+    pub pinned_names: Vec<String>,
+}
+
+impl Filter {
+    /// Put the patterns of `grm.exclude` before the ones given on the command line, so that the
+    /// latter can re-include repositories with `!`
+    pub fn with_config(mut self, config: &git2::Config) -> Result<Self> {
+        let mut exclude = Vec::new();
+        if let Ok(entries) = config.multivar(concat!(env!("CARGO_PKG_NAME"), ".exclude"), None) {
+            entries.for_each(|entry| {
+                if let Some(value) = entry.value() {
+                    exclude.push(value.to_string());
+                }
+            })?;
+        }
+        exclude.append(&mut self.exclude);
+        self.exclude = exclude;
+        self.max_depth = self.max_depth.or_else(|| get_depth(config));
+        self.nested |= config
+            .get_bool(concat!(env!("CARGO_PKG_NAME"), ".nested"))
+            .unwrap_or(false);
+        if self.pinned {
+            self.pinned_names = pinned::list(config)?;
+        }
+        Ok(self)
+    }
+
+    /// `name` is the path of the repository relative to the root directory
+    pub fn matches(&self, name: &Path) -> bool {
+        let name = name.to_string_lossy().replace('\\', "/");
+        !self.is_excluded(&name)
+            && (!self.pinned || self.pinned_names.contains(&name))
+            && self.query.as_ref().is_none_or(|query| {
+                if glob::is_pattern(query) {
+                    glob::matches(query, &name)
+                } else {
+                    name.to_lowercase().contains(&query.to_lowercase())
+                }
+            })
+    }
+
+    /// Find repositories under `root` that match the filter
+    pub fn repos(&self, root: &Path) -> Vec<PathBuf> {
+        self.apply(root, walk(root, self.depth(), self.nested).repos)
+    }
+
+    /// Depth to walk, where `None` means no limit
+    pub fn depth(&self) -> Option<usize> {
+        self.max_depth.filter(|&depth| depth > 0)
+    }
+
+    /// Narrow down `repos` found under `root`
+    pub fn apply(&self, root: &Path, repos: Vec<PathBuf>) -> Vec<PathBuf> {
+        repos
+            .into_iter()
+            .filter(|path| self.matches(path.strip_prefix(root).unwrap_or(path)))
+            .filter(|path| self.matches_tags(path))
+            .collect()
+    }
+
+    /// Later patterns take precedence, and `!pattern` re-includes what earlier ones excluded
+    fn is_excluded(&self, name: &str) -> bool {
+        let mut excluded = false;
+        for pattern in &self.exclude {
+            match pattern.strip_prefix('!') {
+                Some(pattern) => excluded &= !glob::excludes(pattern, name),
+                None => excluded |= glob::excludes(pattern, name),
+            }
+        }
+        excluded
+    }
+
+    fn matches_tags(&self, path: &Path) -> bool {
+        if self.tags.is_empty() {
+            return true;
+        }
+        let tags = tag::get(path).unwrap_or_default();
+        self.tags.iter().all(|t| tags.contains(t))
+    }
+}
+
+#[cfg(test)]
+mod test_filter {
+    use super::*;
+
+    #[test]
+    fn match_everything_by_default() {
+        assert!(Filter::default().matches(Path::new("github.com/foo/bar")));
+    }
+
+    #[test]
+    fn match_query_case_insensitively() {
+        let filter = Filter {
+            query: Some("Foo/B".into()),
+            ..Default::default()
+        };
+        assert!(filter.matches(Path::new("github.com/foo/bar")));
+        assert!(!filter.matches(Path::new("github.com/bar/foo")));
+    }
+}
@@ -0,0 +1,113 @@
+use std::{
+    fmt,
+    io::{BufRead, IsTerminal, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+
+use crate::root;
+
+/// Exit code used when a query matches several repositories in a non-interactive session
+pub const AMBIGUOUS_EXIT_CODE: u8 = 3;
+
+/// Several repositories match a query
+#[derive(Debug)]
+pub struct Ambiguous {
+    query: String,
+    candidates: Vec<String>,
+}
+
+impl fmt::Display for Ambiguous {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", t!(MatchesMultiple, self.query))?;
+        for candidate in &self.candidates {
+            write!(f, "\n  {candidate}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Ambiguous {}
+
+/// Find repositories whose path ends with the components of `query`
+pub fn find(root_dir: &Path, repos: Vec<PathBuf>, query: &str) -> Vec<PathBuf> {
+    let query: Vec<_> = query.split('/').filter(|c| !c.is_empty()).collect();
+    repos
+        .into_iter()
+        .filter(|path| {
+            let name = path.strip_prefix(root_dir).unwrap_or(path);
+            let components: Vec<_> = name
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect();
+            components.len() >= query.len()
+                && components[components.len() - query.len()..]
+                    .iter()
+                    .zip(&query)
+                    .all(|(a, b)| a.eq_ignore_ascii_case(b))
+        })
+        .collect()
+}
+
+/// Let the user pick one of `candidates`, or fail with [`Ambiguous`] if the session is not
+/// interactive
+pub fn choose(roots: &[PathBuf], query: &str, mut candidates: Vec<PathBuf>) -> Result<PathBuf> {
+    if candidates.len() == 1 {
+        return Ok(candidates.remove(0));
+    }
+    let names: Vec<_> = candidates
+        .iter()
+        .map(|path| root::relative(roots, path))
+        .collect();
+    if !std::io::stdin().is_terminal() || !std::io::stderr().is_terminal() {
+        return Err(Ambiguous {
+            query: query.to_string(),
+            candidates: names,
+        }
+        .into());
+    }
+
+    for (i, name) in names.iter().enumerate() {
+        info!("{:>3}) {name}", i + 1);
+    }
+    loop {
+        eprint!("{}", t!(SelectRepository, names.len()));
+        std::io::stderr().flush()?;
+        let mut line = String::new();
+        if std::io::stdin().lock().read_line(&mut line)? == 0 {
+            anyhow::bail!(t!(NoRepositorySelected));
+        }
+        if let Some(i) = line
+            .trim()
+            .parse::<usize>()
+            .ok()
+            .filter(|i| (1..=names.len()).contains(i))
+        {
+            return Ok(candidates.swap_remove(i - 1));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_find {
+    use super::*;
+
+    #[test]
+    fn match_trailing_components() {
+        let root = Path::new("/root");
+        let repos = vec![
+            root.join("github.com/a/foo"),
+            root.join("gitlab.com/b/foo"),
+            root.join("github.com/a/foobar"),
+        ];
+        assert_eq!(
+            vec![root.join("github.com/a/foo"), root.join("gitlab.com/b/foo")],
+            find(root, repos.clone(), "foo"),
+        );
+        assert_eq!(
+            vec![root.join("gitlab.com/b/foo")],
+            find(root, repos, "B/foo"),
+        );
+    }
+}
@@ -0,0 +1,167 @@
+//! JSON-RPC 2.0 over a local socket for editor plugins, one request or response per line
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    path::Path,
+};
+
+use anyhow::Result;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const SERVER_ERROR: i64 = -32000;
+
+/// Returned by handlers for methods they do not know
+#[derive(Debug)]
+pub struct MethodNotFound(pub String);
+
+impl std::fmt::Display for MethodNotFound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "method `{}` is not found", self.0)
+    }
+}
+
+impl std::error::Error for MethodNotFound {}
+
+#[derive(Deserialize)]
+struct Request {
+    #[serde(default)]
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+/// Answer one line of a request
+fn respond(line: &str, handler: &(impl Fn(&str, Value) -> Result<Value> + Sync)) -> Value {
+    let request: Request = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(err) => {
+            return json!({
+                "jsonrpc": "2.0",
+                "id": null,
+                "error": { "code": PARSE_ERROR, "message": err.to_string() },
+            })
+        }
+    };
+    match handler(&request.method, request.params) {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": request.id, "result": result }),
+        Err(err) => {
+            let code = if err.is::<MethodNotFound>() {
+                METHOD_NOT_FOUND
+            } else {
+                SERVER_ERROR
+            };
+            json!({
+                "jsonrpc": "2.0",
+                "id": request.id,
+                "error": { "code": code, "message": format!("{err:#}") },
+            })
+        }
+    }
+}
+
+fn handle_connection(
+    stream: impl Read + Write,
+    handler: &(impl Fn(&str, Value) -> Result<Value> + Sync),
+) -> Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 {
+        if !line.trim().is_empty() {
+            let response = respond(&line, handler);
+            let stream = reader.get_mut();
+            writeln!(stream, "{response}")?;
+            stream.flush()?;
+        }
+        line.clear();
+    }
+    Ok(())
+}
+
+/// Serve on a Unix domain socket at `path`, replacing a stale socket file
+#[cfg(unix)]
+pub fn listen_unix(
+    path: &Path,
+    handler: impl Fn(&str, Value) -> Result<Value> + Sync,
+) -> Result<()> {
+    use std::os::unix::net::{UnixListener, UnixStream};
+
+    if path.exists() {
+        anyhow::ensure!(
+            UnixStream::connect(path).is_err(),
+            "another server is listening on `{}`",
+            path.display()
+        );
+        std::fs::remove_file(path)?;
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    info!("listening on {}", path.display());
+    std::thread::scope(|scope| {
+        for stream in listener.incoming() {
+            let stream = stream?;
+            let handler = &handler;
+            scope.spawn(move || {
+                if let Err(err) = handle_connection(stream, handler) {
+                    warn!("connection closed: {err:#}");
+                }
+            });
+        }
+        Ok(())
+    })
+}
+
+/// Serve on a TCP address such as `127.0.0.1:7878`, for platforms without Unix domain sockets
+///
+/// Requests are not authenticated, so addresses other than loopback ones are refused unless
+/// `allow_remote` is set.
+pub fn listen_tcp(
+    addr: &str,
+    allow_remote: bool,
+    handler: impl Fn(&str, Value) -> Result<Value> + Sync,
+) -> Result<()> {
+    let addrs: Vec<_> = std::net::ToSocketAddrs::to_socket_addrs(addr)?.collect();
+    anyhow::ensure!(
+        allow_remote || addrs.iter().all(|addr| addr.ip().is_loopback()),
+        "`{addr}` is reachable from other hosts, which could use the server without \
+         authentication; listen on a loopback address or pass --allow-remote"
+    );
+    let listener = std::net::TcpListener::bind(&addrs[..])?;
+    info!("listening on {}", listener.local_addr()?);
+    std::thread::scope(|scope| {
+        for stream in listener.incoming() {
+            let stream = stream?;
+            let handler = &handler;
+            scope.spawn(move || {
+                if let Err(err) = handle_connection(stream, handler) {
+                    warn!("connection closed: {err:#}");
+                }
+            });
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod test_respond {
+    use super::*;
+
+    #[test]
+    fn errors() {
+        let handler = |method: &str, params: Value| match method {
+            "echo" => Ok(params),
+            _ => Err(MethodNotFound(method.to_string()).into()),
+        };
+        let response = respond(r#"{"id":1,"method":"echo","params":[2]}"#, &handler);
+        assert_eq!(response["result"], json!([2]));
+        let response = respond(r#"{"id":2,"method":"nope"}"#, &handler);
+        assert_eq!(response["error"]["code"], METHOD_NOT_FOUND);
+        let response = respond("{", &handler);
+        assert_eq!(response["error"]["code"], PARSE_ERROR);
+    }
+}
@@ -0,0 +1,85 @@
+//! `grm init`, which asks for the basic settings and writes them into the global config
+
+use std::{
+    io::{BufRead, IsTerminal, Write},
+    path::PathBuf,
+};
+
+use anyhow::{ensure, Result};
+
+use crate::{root, vault};
+
+pub struct Settings {
+    pub root: PathBuf,
+    /// `ssh` or `https`
+    pub protocol: String,
+    pub host: String,
+}
+
+/// Whether questions can be asked on the terminal
+pub fn is_interactive() -> bool {
+    std::io::stdin().is_terminal() && std::io::stderr().is_terminal()
+}
+
+/// Ask `question` on the terminal, returning `default` for an empty answer
+pub fn ask(question: &str, default: &str) -> Result<String> {
+    eprint!("{question} [{default}]: ");
+    std::io::stderr().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer)?;
+    let answer = answer.trim();
+    Ok(if answer.is_empty() { default } else { answer }.to_string())
+}
+
+/// The user's global config, where the commands managing settings write
+pub fn global_config() -> Result<git2::Config> {
+    Ok(git2::Config::open_default()?.open_global()?)
+}
+
+/// Remove `key` from the global config, returns `false` if it is not set
+pub fn remove_global(key: &str) -> Result<bool> {
+    match global_config()?.remove(key) {
+        Ok(()) => Ok(true),
+        Err(err) if err.code() == git2::ErrorCode::NotFound => Ok(false),
+        Err(err) => Err(err.into()),
+    }
+}
+
+pub fn write(settings: &Settings) -> Result<()> {
+    ensure!(
+        matches!(settings.protocol.as_str(), "ssh" | "https"),
+        "the protocol must be `ssh` or `https`, but got `{}`",
+        settings.protocol
+    );
+    let mut config = global_config()?;
+    config.set_str(
+        concat!(env!("CARGO_PKG_NAME"), ".root"),
+        &settings.root.to_string_lossy(),
+    )?;
+    config.set_str(
+        concat!(env!("CARGO_PKG_NAME"), ".protocol"),
+        &settings.protocol,
+    )?;
+    config.set_str(concat!(env!("CARGO_PKG_NAME"), ".host"), &settings.host)?;
+    root::prepare(&settings.root)?;
+    info!("root: {}", settings.root.display());
+    Ok(())
+}
+
+/// Ask for a token of `host` and store it in the vault, unless the answer is empty
+pub fn store_token(config: &git2::Config, host: &str) -> Result<()> {
+    let token = rpassword::prompt_password(format!("token for {host} (empty to skip): "))?;
+    if token.is_empty() {
+        return Ok(());
+    }
+    let exists = vault::get_vault_path(config)?.exists();
+    let passphrase = vault::passphrase(!exists)?;
+    let mut tokens = vault::load(config, &passphrase)?;
+    tokens.insert(host.to_string(), token);
+    vault::save(config, &tokens, &passphrase)?;
+    info!(
+        "stored the token in {}",
+        vault::get_vault_path(config)?.display()
+    );
+    Ok(())
+}
@@ -0,0 +1,96 @@
+//! Shell code printed by `grm shell-init` to be evaluated in rc files
+
+use std::io::Write;
+
+use anyhow::Result;
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    Powershell,
+}
+
+const BASH: &str = r#"gcd() {
+    local dir
+    if command -v fzf >/dev/null 2>&1; then
+        dir="$(grm list -l --projects | fzf --query="$*" --select-1 --exit-0)"
+    else
+        dir="$(grm list -l --projects -q "$*" | head -n 1)"
+    fi
+    [ -n "$dir" ] && cd "$dir"
+}
+"#;
+
+const BASH_BINDINGS: &str = r#"[[ $- == *i* ]] && bind -x '"\C-g": gcd'
+"#;
+
+const ZSH_BINDINGS: &str = r#"_grm_gcd_widget() { gcd; zle reset-prompt; }
+zle -N _grm_gcd_widget
+bindkey '^G' _grm_gcd_widget
+"#;
+
+const FISH: &str = r#"function gcd
+    if command -q fzf
+        set dir (grm list -l --projects | fzf --query="$argv" --select-1 --exit-0)
+    else
+        set dir (grm list -l --projects -q "$argv" | head -n 1)
+    end
+    test -n "$dir"; and cd $dir
+end
+bind \cg 'gcd; commandline -f repaint'
+"#;
+
+const POWERSHELL: &str = r#"function gcd {
+    if (Get-Command fzf -ErrorAction SilentlyContinue) {
+        $dir = grm list -l --projects | fzf --query="$args" --select-1 --exit-0
+    } else {
+        $dir = grm list -l --projects -q "$args" | Select-Object -First 1
+    }
+    if ($dir) { Set-Location $dir }
+}
+Set-PSReadLineKeyHandler -Chord Ctrl+g -ScriptBlock {
+    gcd
+    [Microsoft.PowerShell.PSConsoleReadLine]::InvokePrompt()
+}
+"#;
+
+impl Shell {
+    /// Guess the shell from `SHELL`
+    pub fn detect() -> Option<Self> {
+        let shell = std::env::var("SHELL").ok()?;
+        match shell.rsplit(['/', '\\']).next()? {
+            "bash" => Some(Self::Bash),
+            "zsh" => Some(Self::Zsh),
+            "fish" => Some(Self::Fish),
+            "pwsh" | "powershell" => Some(Self::Powershell),
+            _ => None,
+        }
+    }
+
+    /// The line to add to the rc file
+    pub fn rc_line(self) -> &'static str {
+        match self {
+            Self::Bash => "# ~/.bashrc\neval \"$(grm shell-init bash)\"",
+            Self::Zsh => "# ~/.zshrc\neval \"$(grm shell-init zsh)\"",
+            Self::Fish => "# ~/.config/fish/config.fish\ngrm shell-init fish | source",
+            Self::Powershell => {
+                "# $PROFILE\ngrm shell-init powershell | Out-String | Invoke-Expression"
+            }
+        }
+    }
+
+    /// Write the functions, key bindings and completions of `command`
+    pub fn write_init(self, command: &mut clap::Command, w: &mut dyn Write) -> Result<()> {
+        let (functions, shell) = match self {
+            Self::Bash => ([BASH, BASH_BINDINGS].concat(), clap_complete::Shell::Bash),
+            Self::Zsh => ([BASH, ZSH_BINDINGS].concat(), clap_complete::Shell::Zsh),
+            Self::Fish => (FISH.to_string(), clap_complete::Shell::Fish),
+            Self::Powershell => (POWERSHELL.to_string(), clap_complete::Shell::PowerShell),
+        };
+        w.write_all(functions.as_bytes())?;
+        clap_complete::generate(shell, command, env!("CARGO_PKG_NAME"), w);
+        Ok(())
+    }
+}
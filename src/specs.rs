@@ -0,0 +1,85 @@
+//! Lists of repository specs given with `--file` or `--stdin`, one per line
+
+use std::{io::Read, path::Path};
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+
+/// Fields of a JSON object taken as the spec, in order of preference
+const FIELDS: &[&str] = &["origin", "repo", "name", "path"];
+
+/// Read the specs in `file`, or in stdin if `stdin` is set
+///
+/// Returns `None` when neither is given.
+pub fn load(file: Option<&Path>, stdin: bool) -> Result<Option<Vec<String>>> {
+    let text = match file {
+        Some(file) => std::fs::read_to_string(file)
+            .with_context(|| format!("failed to read `{}`", file.display()))?,
+        None if stdin => {
+            let mut text = String::new();
+            std::io::stdin()
+                .lock()
+                .read_to_string(&mut text)
+                .context("failed to read stdin")?;
+            text
+        }
+        None => return Ok(None),
+    };
+    parse(&text).map(Some)
+}
+
+/// Specs in `text`, skipping blank lines and comments starting with `#`
+///
+/// A `#` inside a spec, like in the fragment of a URL, doesn't start a comment. Lines may also
+/// be JSON strings, or JSON objects such as the ones printed by `grm list --json`.
+pub fn parse(text: &str) -> Result<Vec<String>> {
+    let mut specs = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.starts_with(['{', '"']) {
+            let value: Value = serde_json::from_str(line)
+                .with_context(|| format!("invalid JSON on line {}", i + 1))?;
+            specs.push(from_json(&value).with_context(|| format!("no spec on line {}", i + 1))?);
+            continue;
+        }
+        let line = match line.find(" #").or_else(|| line.find("\t#")) {
+            Some(i) => line[..i].trim(),
+            None => line,
+        };
+        if !line.is_empty() && !line.starts_with('#') {
+            specs.push(line.to_string());
+        }
+    }
+    Ok(specs)
+}
+
+fn from_json(value: &Value) -> Option<String> {
+    let spec = value.as_str().or_else(|| {
+        FIELDS
+            .iter()
+            .find_map(|field| value.get(field).and_then(Value::as_str))
+    })?;
+    Some(spec.to_string())
+}
+
+#[cfg(test)]
+mod test_parse {
+    use super::*;
+
+    #[test]
+    fn comments() {
+        let text = "# repos\n\nalice/src\r\n  bob/src  # fork\nhttps://example.com/a#b\n";
+        assert_eq!(
+            parse(text).unwrap(),
+            ["alice/src", "bob/src", "https://example.com/a#b"]
+        );
+    }
+
+    #[test]
+    fn json_lines() {
+        let text = "\"alice/src\"\n{\"name\":\"example.com/bob/src\",\"path\":\"/x\"}\n";
+        assert_eq!(parse(text).unwrap(), ["alice/src", "example.com/bob/src"]);
+        assert!(parse("{\"parent\":null}").is_err());
+        assert!(parse("{").is_err());
+    }
+}
@@ -0,0 +1,73 @@
+//! Repositories are cloned into a hidden sibling directory and renamed on success, so that a
+//! half-populated directory is never mistaken for a repository.
+
+use std::{
+    ffi::OsStr,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+
+use crate::lock::Lock;
+
+const MARKER: &str = ".tmp-";
+
+/// A unique temporary path next to `path`
+pub fn temp_path(path: &Path) -> Result<PathBuf> {
+    let name = path.file_name().context("invalid repository path")?;
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.subsec_nanos());
+    Ok(path.with_file_name(format!(
+        ".{}{MARKER}{:x}{nanos:x}",
+        name.to_string_lossy(),
+        std::process::id()
+    )))
+}
+
+/// The path a temporary directory is going to be renamed to
+pub fn final_path(temp: &Path) -> Option<PathBuf> {
+    let name = temp.file_name()?.to_str()?.strip_prefix('.')?;
+    let (name, _) = name.rsplit_once(MARKER)?;
+    Some(temp.with_file_name(name))
+}
+
+pub fn is_temp(name: &OsStr) -> bool {
+    name.to_str()
+        .is_some_and(|name| name.starts_with('.') && name.contains(MARKER))
+}
+
+/// Remove temporary directories whose clone is not in progress anymore
+pub fn clean(temp_dirs: Vec<PathBuf>) -> Vec<PathBuf> {
+    temp_dirs
+        .into_iter()
+        .filter(|temp| {
+            let Some(path) = final_path(temp) else {
+                return false;
+            };
+            let Ok(_lock) = Lock::acquire(&path, false) else {
+                return false;
+            };
+            std::fs::remove_dir_all(temp).is_ok()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test_final_path {
+    use super::*;
+
+    #[test]
+    fn restore_name() -> Result<()> {
+        let path = Path::new("/root/github.com/foo/bar.tmp");
+        assert_eq!(Some(path.to_path_buf()), final_path(&temp_path(path)?));
+        Ok(())
+    }
+
+    #[test]
+    fn ignore_other_names() {
+        assert_eq!(None, final_path(Path::new("/root/github.com/foo/bar")));
+        assert_eq!(None, final_path(Path::new("/root/github.com/foo/.bar")));
+    }
+}
@@ -0,0 +1,74 @@
+//! Repositories left untouched for a while, suggested for removal by `grm prune --stale`
+
+use std::{
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{bail, Context, Result};
+use git2::Repository;
+use walkdir::WalkDir;
+
+/// Parse a period like `180d`, `6w` or `1y`, where a bare number means days
+pub fn parse_period(s: &str) -> Result<Duration> {
+    let (number, unit) = match s.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => s.split_at(i),
+        None => (s, "d"),
+    };
+    let number: u64 = number
+        .parse()
+        .with_context(|| format!("invalid period `{s}`"))?;
+    let days = match unit {
+        "d" => 1,
+        "w" => 7,
+        "y" => 365,
+        _ => bail!("invalid unit of `{s}`, expected d, w or y"),
+    };
+    Ok(Duration::from_secs(number * days * 24 * 60 * 60))
+}
+
+/// The latest of the last reflog entry of `HEAD`, the last write of `HEAD` such as by a clone,
+/// the last fetch and the last modification of a file in the worktree
+pub fn last_activity(path: &Path) -> Result<Option<SystemTime>> {
+    let repo = Repository::open(path)?;
+    let git_dir = repo.path();
+    let mut times = Vec::new();
+    if let Ok(reflog) = repo.reflog("HEAD") {
+        if let Some(entry) = reflog.get(0) {
+            let seconds = entry.committer().when().seconds();
+            times.push(SystemTime::UNIX_EPOCH + Duration::from_secs(seconds.max(0) as u64));
+        }
+    }
+    for file in ["HEAD", "FETCH_HEAD"] {
+        if let Ok(modified) = git_dir.join(file).metadata().and_then(|m| m.modified()) {
+            times.push(modified);
+        }
+    }
+    if let Some(workdir) = repo.workdir() {
+        let modified = WalkDir::new(workdir)
+            .into_iter()
+            .filter_entry(|entry| entry.path() != git_dir && entry.file_name() != ".git")
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_file())
+            .filter_map(|entry| entry.metadata().ok()?.modified().ok())
+            .max();
+        times.extend(modified);
+    }
+    Ok(times.into_iter().max())
+}
+
+#[cfg(test)]
+mod test_parse_period {
+    use super::*;
+
+    #[test]
+    fn units() {
+        let days = |n: u64| Duration::from_secs(n * 24 * 60 * 60);
+        assert_eq!(parse_period("180d").unwrap(), days(180));
+        assert_eq!(parse_period("2w").unwrap(), days(14));
+        assert_eq!(parse_period("1y").unwrap(), days(365));
+        assert_eq!(parse_period("30").unwrap(), days(30));
+        assert!(parse_period("3h").is_err());
+        assert!(parse_period("d").is_err());
+    }
+}
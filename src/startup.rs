@@ -0,0 +1,56 @@
+//! Lazily initialized state of the process and timings of its startup for `--profile-startup`
+
+use std::{
+    cell::OnceCell,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+
+/// The default git config, opened when it is first needed
+#[derive(Default)]
+pub struct LazyConfig(OnceCell<git2::Config>);
+
+impl LazyConfig {
+    pub fn get(&self) -> Result<&git2::Config> {
+        if let Some(config) = self.0.get() {
+            return Ok(config);
+        }
+        let config = git2::Config::open_default()?;
+        Ok(self.0.get_or_init(|| config))
+    }
+}
+
+/// Time spent in each phase until the command runs
+pub struct Timings {
+    last: Instant,
+    phases: Vec<(&'static str, Duration)>,
+}
+
+impl Timings {
+    pub fn new() -> Self {
+        Self {
+            last: Instant::now(),
+            phases: Vec::new(),
+        }
+    }
+
+    /// End the current phase as `name`
+    pub fn mark(&mut self, name: &'static str) {
+        let now = Instant::now();
+        self.phases.push((name, now - self.last));
+        self.last = now;
+    }
+
+    pub fn print(&self) {
+        let total: Duration = self.phases.iter().map(|(_, elapsed)| *elapsed).sum();
+        for (name, elapsed) in &self.phases {
+            eprintln!("{name:>10}: {}", format(*elapsed));
+        }
+        eprintln!("{:>10}: {}", "total", format(total));
+    }
+}
+
+fn format(elapsed: Duration) -> String {
+    format!("{:.3}ms", elapsed.as_secs_f64() * 1000.0)
+}
@@ -0,0 +1,36 @@
+//! Stashes left in managed repositories, listed by `grm stashes`
+
+use std::path::Path;
+
+use anyhow::Result;
+use chrono::{DateTime, FixedOffset};
+use git2::Repository;
+
+use crate::head;
+
+pub struct Stash {
+    pub index: usize,
+    pub message: String,
+    pub time: DateTime<FixedOffset>,
+}
+
+/// Stashes of the repository at `path`, the latest first
+pub fn list(path: &Path) -> Result<Vec<Stash>> {
+    let mut repo = Repository::open(path)?;
+    let mut stashes = Vec::new();
+    repo.stash_foreach(|index, message, oid| {
+        stashes.push((index, message.to_string(), *oid));
+        true
+    })?;
+    stashes
+        .into_iter()
+        .map(|(index, message, oid)| {
+            let time = repo.find_commit(oid)?.time();
+            Ok(Stash {
+                index,
+                message,
+                time: head::to_datetime(time),
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,134 @@
+use std::{collections::BTreeMap, path::Path, time::SystemTime};
+
+use anyhow::Result;
+use git2::{Repository, StatusOptions};
+
+use crate::du;
+
+/// File extensions counted as each language, checked against tracked files
+const LANGUAGES: &[(&str, &[&str])] = &[
+    ("C", &["c", "h"]),
+    ("C++", &["cc", "cpp", "cxx", "hpp", "hh"]),
+    ("C#", &["cs"]),
+    ("Go", &["go"]),
+    ("Haskell", &["hs"]),
+    ("Java", &["java"]),
+    ("JavaScript", &["js", "jsx", "mjs", "cjs"]),
+    ("Kotlin", &["kt", "kts"]),
+    ("Lua", &["lua"]),
+    ("Nix", &["nix"]),
+    ("PHP", &["php"]),
+    ("Python", &["py"]),
+    ("Ruby", &["rb"]),
+    ("Rust", &["rs"]),
+    ("Shell", &["sh", "bash", "zsh", "fish"]),
+    ("Swift", &["swift"]),
+    ("TypeScript", &["ts", "tsx", "mts", "cts"]),
+    ("Zig", &["zig"]),
+];
+
+/// What `grm stats` reports about a repository
+pub struct RepoStats {
+    /// Path relative to the root directory
+    pub name: String,
+    pub language: Option<&'static str>,
+    pub size: u64,
+    /// When `FETCH_HEAD` was last written, `None` if the repository was never fetched
+    pub fetched: Option<SystemTime>,
+    /// Number of changed or untracked files
+    pub changes: usize,
+}
+
+impl RepoStats {
+    pub fn collect(name: String, path: &Path) -> Result<Self> {
+        let repo = Repository::open(path)?;
+        let fetched = std::fs::metadata(repo.path().join("FETCH_HEAD"))
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        let changes = if repo.is_bare() {
+            0
+        } else {
+            let mut opts = StatusOptions::new();
+            opts.include_untracked(true).include_ignored(false);
+            repo.statuses(Some(&mut opts))?.len()
+        };
+        // a repository whose size cannot be measured still counts for the other figures
+        let size = match du::Usage::measure(path) {
+            Ok(usage) => usage.total(),
+            Err(err) => {
+                warn!("{name}: failed to measure the size: {err:#}");
+                0
+            }
+        };
+        Ok(Self {
+            name,
+            language: language(&repo),
+            size,
+            fetched,
+            changes,
+        })
+    }
+
+    /// The first component of the name
+    pub fn host(&self) -> &str {
+        self.name.split('/').next().unwrap_or_default()
+    }
+
+    /// The first two components of the name
+    pub fn owner(&self) -> &str {
+        match self.name.match_indices('/').nth(1) {
+            Some((i, _)) => &self.name[..i],
+            None => &self.name,
+        }
+    }
+}
+
+/// The language with the most tracked files
+fn language(repo: &Repository) -> Option<&'static str> {
+    let index = repo.index().ok()?;
+    let mut counts = BTreeMap::<&str, usize>::new();
+    for entry in index.iter() {
+        if let Some(language) = language_of(&String::from_utf8_lossy(&entry.path)) {
+            *counts.entry(language).or_default() += 1;
+        }
+    }
+    counts
+        .into_iter()
+        .max_by_key(|&(_, count)| count)
+        .map(|(language, _)| language)
+}
+
+fn language_of(path: &str) -> Option<&'static str> {
+    let (_, extension) = path.rsplit_once('.')?;
+    if extension.contains('/') {
+        return None;
+    }
+    LANGUAGES
+        .iter()
+        .find(|(_, extensions)| extensions.contains(&extension.to_lowercase().as_str()))
+        .map(|(language, _)| *language)
+}
+
+/// Count `keys`, sorted by the count in descending order
+pub fn count<'a>(keys: impl Iterator<Item = &'a str>) -> Vec<(&'a str, usize)> {
+    let mut counts = BTreeMap::<&str, usize>::new();
+    for key in keys {
+        *counts.entry(key).or_default() += 1;
+    }
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    counts.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+    counts
+}
+
+#[cfg(test)]
+mod test_language_of {
+    use super::*;
+
+    #[test]
+    fn extensions() {
+        assert_eq!(language_of("src/main.rs"), Some("Rust"));
+        assert_eq!(language_of("web/App.TSX"), Some("TypeScript"));
+        assert_eq!(language_of("README"), None);
+        assert_eq!(language_of(".config/nvim"), None);
+    }
+}
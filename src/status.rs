@@ -0,0 +1,149 @@
+//! States of managed repositories where work is easily lost, reported by `grm status`
+
+use std::path::Path;
+
+use anyhow::Result;
+use git2::{BranchType, Repository, StatusOptions};
+use serde::Serialize;
+
+use crate::fork;
+
+#[derive(Serialize)]
+pub struct Status {
+    /// `None` if HEAD is detached
+    pub branch: Option<String>,
+    /// The short ID of the commit HEAD is detached at
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detached_at: Option<String>,
+    /// On a branch other than the default one that does not track any remote branch
+    pub no_upstream: bool,
+    /// Commits not pushed to the upstream of the branch
+    pub ahead: usize,
+    /// Commits of the default branch of the repository a fork was made from that the local one
+    /// does not have yet
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub behind_fork_upstream: Option<usize>,
+    /// Uncommitted changes, including untracked files
+    pub dirty: bool,
+}
+
+impl Status {
+    /// Short descriptions of what needs attention, empty if nothing does
+    pub fn issues(&self) -> Vec<String> {
+        let mut issues = Vec::new();
+        if let Some(id) = &self.detached_at {
+            issues.push(format!("detached at {id}"));
+        }
+        if self.no_upstream {
+            issues.push("no upstream".into());
+        }
+        if self.ahead > 0 {
+            issues.push(format!("{} unpushed", self.ahead));
+        }
+        if self.dirty {
+            issues.push("uncommitted changes".into());
+        }
+        if let Some(behind) = self.behind_fork_upstream.filter(|&behind| behind > 0) {
+            issues.push(format!("{behind} behind upstream"));
+        }
+        issues
+    }
+}
+
+/// Check the repository at `path`, comparing branches with the default branch of `remote`
+pub fn check(path: &Path, remote: &str) -> Result<Status> {
+    let repo = Repository::open(path)?;
+    let mut status = Status {
+        branch: None,
+        detached_at: None,
+        no_upstream: false,
+        ahead: 0,
+        behind_fork_upstream: None,
+        dirty: false,
+    };
+    status.behind_fork_upstream = behind_fork_upstream(&repo);
+    if repo.head_detached()? {
+        let head = repo.head()?.peel_to_commit()?;
+        status.detached_at = head.as_object().short_id()?.as_str().map(String::from);
+    } else if let Some(name) = repo
+        .find_reference("HEAD")?
+        .symbolic_target()
+        .and_then(|target| target.strip_prefix("refs/heads/"))
+    {
+        status.branch = Some(name.to_string());
+        // an unborn branch has nothing to lose yet
+        if let Ok(branch) = repo.find_branch(name, BranchType::Local) {
+            match branch.upstream() {
+                Ok(upstream) => {
+                    if let (Some(local), Some(upstream)) =
+                        (branch.get().target(), upstream.get().target())
+                    {
+                        status.ahead = repo.graph_ahead_behind(local, upstream)?.0;
+                    }
+                }
+                Err(_) => {
+                    let default = fork::default_branch(&repo, remote);
+                    status.no_upstream = default.as_deref() != Some(name);
+                }
+            }
+        }
+    }
+    if !repo.is_bare() {
+        let mut options = StatusOptions::new();
+        options.include_untracked(true).exclude_submodules(true);
+        status.dirty = !repo.statuses(Some(&mut options))?.is_empty();
+    }
+    Ok(status)
+}
+
+/// Compare the local default branch with the one of the recorded upstream of a fork
+fn behind_fork_upstream(repo: &Repository) -> Option<usize> {
+    let upstream = fork::upstream_remote(repo)?;
+    let branch = fork::default_branch(repo, &upstream)?;
+    let theirs = repo
+        .refname_to_id(&format!("refs/remotes/{upstream}/{branch}"))
+        .ok()?;
+    let ours = repo.refname_to_id(&format!("refs/heads/{branch}")).ok()?;
+    repo.graph_ahead_behind(ours, theirs)
+        .ok()
+        .map(|(_, behind)| behind)
+}
+
+#[cfg(test)]
+mod test_issues {
+    use super::*;
+
+    #[test]
+    fn issues() {
+        let status = Status {
+            branch: None,
+            detached_at: Some("abc1234".into()),
+            no_upstream: false,
+            ahead: 0,
+            behind_fork_upstream: None,
+            dirty: true,
+        };
+        assert_eq!(
+            status.issues(),
+            ["detached at abc1234", "uncommitted changes"]
+        );
+        let status = Status {
+            branch: Some("feature".into()),
+            detached_at: None,
+            no_upstream: true,
+            ahead: 0,
+            behind_fork_upstream: Some(0),
+            dirty: false,
+        };
+        assert_eq!(status.issues(), ["no upstream"]);
+        let status = Status {
+            branch: Some("main".into()),
+            detached_at: None,
+            no_upstream: false,
+            ahead: 1,
+            behind_fork_upstream: Some(3),
+            dirty: false,
+        };
+        assert_eq!(status.issues(), ["1 unpushed", "3 behind upstream"]);
+    }
+}
@@ -0,0 +1,42 @@
+use std::path::Path;
+
+use anyhow::Result;
+use git2::{ConfigLevel, Repository};
+
+/// Tags are stored in the local config of each repository
+const KEY: &str = concat!(env!("CARGO_PKG_NAME"), ".tag");
+
+fn local_config(path: &Path) -> Result<git2::Config> {
+    Ok(Repository::open(path)?
+        .config()?
+        .open_level(ConfigLevel::Local)?)
+}
+
+pub fn get(path: &Path) -> Result<Vec<String>> {
+    let config = local_config(path)?;
+    let mut tags = Vec::new();
+    if let Ok(entries) = config.multivar(KEY, None) {
+        entries.for_each(|entry| {
+            if let Some(value) = entry.value() {
+                tags.push(value.to_string());
+            }
+        })?;
+    }
+    Ok(tags)
+}
+
+pub fn add(path: &Path, tag: &str) -> Result<()> {
+    let mut config = local_config(path)?;
+    config.set_multivar(KEY, &format!("^{}$", regex::escape(tag)), tag)?;
+    Ok(())
+}
+
+/// Return whether the tag existed
+pub fn remove(path: &Path, tag: &str) -> Result<bool> {
+    if !get(path)?.iter().any(|t| t == tag) {
+        return Ok(false);
+    }
+    let mut config = local_config(path)?;
+    config.remove_multivar(KEY, &format!("^{}$", regex::escape(tag)))?;
+    Ok(true)
+}
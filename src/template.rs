@@ -0,0 +1,196 @@
+use std::str::FromStr;
+
+use anyhow::{bail, Result};
+
+/// Placeholders accepted by `grm list --format`
+pub const PLACEHOLDERS: &[&str] = &[
+    "host", "owner", "name", "repo", "path", "branch", "commit", "date", "subject", "tags",
+];
+
+/// A format string like `{host}/{owner}/{name}\t{path}`
+///
+/// `{{` and `}}` are literal braces, and `\t`, `\n` and `\\` are escapes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Template {
+    pieces: Vec<Piece>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Piece {
+    Text(String),
+    Field(String),
+}
+
+impl FromStr for Template {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut pieces = Vec::new();
+        let mut text = String::new();
+        let mut chars = s.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => match chars.peek() {
+                    Some('t') => text.push('\t'),
+                    Some('n') => text.push('\n'),
+                    Some('\\') => text.push('\\'),
+                    _ => {
+                        text.push('\\');
+                        continue;
+                    }
+                },
+                '{' if chars.peek() == Some(&'{') => text.push('{'),
+                '}' if chars.peek() == Some(&'}') => text.push('}'),
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => bail!("unterminated placeholder `{{{name}`"),
+                        }
+                    }
+                    if !PLACEHOLDERS.contains(&name.as_str()) {
+                        bail!(
+                            "unknown placeholder `{{{name}}}`, expected one of {}",
+                            PLACEHOLDERS.join(", ")
+                        );
+                    }
+                    if !text.is_empty() {
+                        pieces.push(Piece::Text(std::mem::take(&mut text)));
+                    }
+                    pieces.push(Piece::Field(name));
+                    continue;
+                }
+                '}' => bail!("unmatched `}}`, use `}}}}` for a literal brace"),
+                c => {
+                    text.push(c);
+                    continue;
+                }
+            }
+            chars.next();
+        }
+        if !text.is_empty() {
+            pieces.push(Piece::Text(text));
+        }
+        Ok(Self { pieces })
+    }
+}
+
+impl Template {
+    /// Whether any of the placeholders is used
+    pub fn uses(&self, names: &[&str]) -> bool {
+        self.pieces
+            .iter()
+            .any(|piece| matches!(piece, Piece::Field(name) if names.contains(&name.as_str())))
+    }
+
+    pub fn render(&self, mut field: impl FnMut(&str) -> String) -> String {
+        let mut output = String::new();
+        for piece in &self.pieces {
+            match piece {
+                Piece::Text(text) => output.push_str(text),
+                Piece::Field(name) => output.push_str(&field(name)),
+            }
+        }
+        output
+    }
+}
+
+/// Split a repository name like `host/owner/name` into the host, the owner and the name
+///
+/// Owners may have several components, e.g. GitLab subgroups.
+pub fn split_repo(repo: &str) -> (&str, String, &str) {
+    let components: Vec<&str> = repo.split('/').collect();
+    match components.as_slice() {
+        [name] => ("", String::new(), *name),
+        [host, owner @ .., name] => (*host, owner.join("/"), *name),
+        [] => ("", String::new(), ""),
+    }
+}
+
+/// Replace `{key}` in `text` with each value, leaving other braces as they are
+///
+/// Unlike [`Template`], this accepts any text such as `find -exec {} ;` in commands.
+///
+/// Values are inserted as they are, even if they contain placeholders themselves.
+pub fn expand(text: &str, values: &[(&str, String)]) -> String {
+    let mut expanded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('{') {
+        expanded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let value = rest.find('}').and_then(|end| {
+            let (_, value) = values.iter().find(|(key, _)| *key == &rest[1..end])?;
+            Some((value, end))
+        });
+        match value {
+            Some((value, end)) => {
+                expanded.push_str(value);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                expanded.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+#[cfg(test)]
+mod test_template {
+    use super::*;
+
+    fn render(template: &str) -> String {
+        template
+            .parse::<Template>()
+            .unwrap()
+            .render(|name| name.to_uppercase())
+    }
+
+    #[test]
+    fn placeholders() {
+        assert_eq!(render("{host}/{owner}/{name}"), "HOST/OWNER/NAME");
+        assert_eq!(render("- {repo} -"), "- REPO -");
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(render(r"{name}\t{path}\n"), "NAME\tPATH\n");
+        assert_eq!(render(r"{{{name}}} \\ \x"), r"{NAME} \ \x");
+    }
+
+    #[test]
+    fn unknown() {
+        assert!("{foo}".parse::<Template>().is_err());
+        assert!("{name".parse::<Template>().is_err());
+        assert!("name}".parse::<Template>().is_err());
+    }
+
+    #[test]
+    fn expand_known_only() {
+        let values = [("name", "grm".to_string())];
+        assert_eq!(expand("echo {name} {} {foo}", &values), "echo grm {} {foo}");
+    }
+
+    #[test]
+    fn expand_values_as_they_are() {
+        let values = [
+            ("name", "{branch}".to_string()),
+            ("branch", "main".to_string()),
+        ];
+        assert_eq!(
+            expand("{name} {branch} {{name}", &values),
+            "{branch} main {{branch}"
+        );
+    }
+
+    #[test]
+    fn uses() {
+        let template = "{name} {branch}".parse::<Template>().unwrap();
+        assert!(template.uses(&["branch", "commit"]));
+        assert!(!template.uses(&["commit"]));
+    }
+}
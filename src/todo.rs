@@ -0,0 +1,82 @@
+//! TODO, FIXME and HACK comments left in tracked files, found by `grm todo`
+
+use std::{
+    path::PathBuf,
+    sync::{LazyLock, Mutex},
+};
+
+use regex::Regex;
+use serde::Serialize;
+
+use crate::{foreach, grep, root};
+
+/// Keywords after a comment marker such as `//`, `#`, `--` or `<!--`, with an optional
+/// `(author)` and `:`
+static COMMENT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?://+|#+|/\*+|^\s*\*|--|;+|<!--)\s*(TODO|FIXME|HACK)\b(?:\([^)]*\))?:?\s*(.*)")
+        .unwrap()
+});
+
+#[derive(Serialize)]
+pub struct Item {
+    pub repo: String,
+    pub file: String,
+    pub line: usize,
+    pub kind: String,
+    pub text: String,
+}
+
+/// Search tracked files of each repository, sorted by repository, file and line
+pub fn run(roots: &[PathBuf], paths: Vec<PathBuf>, kinds: &[String], jobs: usize) -> Vec<Item> {
+    let items = Mutex::new(Vec::new());
+    foreach::parallel(paths, jobs, |path| {
+        let name = root::relative(roots, &path);
+        let matches = match grep::search(&path, &COMMENT) {
+            Ok(matches) => matches,
+            Err(err) => {
+                info!("{name}: {err}");
+                return;
+            }
+        };
+        let found = matches.into_iter().filter_map(|(file, line, content)| {
+            let (kind, text) = parse(&content)?;
+            (kinds.is_empty() || kinds.iter().any(|k| k.eq_ignore_ascii_case(kind))).then(|| Item {
+                repo: name.clone(),
+                file,
+                line,
+                kind: kind.to_string(),
+                text: text.to_string(),
+            })
+        });
+        items.lock().unwrap().extend(found);
+    });
+    let mut items = items.into_inner().unwrap();
+    items.sort_by(|a, b| (&a.repo, &a.file, a.line).cmp(&(&b.repo, &b.file, b.line)));
+    items
+}
+
+/// The keyword and the rest of a comment in `line`
+fn parse(line: &str) -> Option<(&str, &str)> {
+    let captures = COMMENT.captures(line)?;
+    let text = captures.get(2).map_or("", |m| m.as_str());
+    let text = text.trim_end_matches("-->").trim_end_matches("*/").trim();
+    Some((captures.get(1)?.as_str(), text))
+}
+
+#[cfg(test)]
+mod test_parse {
+    use super::*;
+
+    #[test]
+    fn comments() {
+        assert_eq!(
+            parse("    // TODO: handle errors"),
+            Some(("TODO", "handle errors"))
+        );
+        assert_eq!(parse("x = 1  # FIXME(alice) slow"), Some(("FIXME", "slow")));
+        assert_eq!(parse("/* HACK */"), Some(("HACK", "")));
+        assert_eq!(parse("<!-- TODO: docs -->"), Some(("TODO", "docs")));
+        assert_eq!(parse("let TODO = 1;"), None);
+        assert_eq!(parse("// TODOS"), None);
+    }
+}
@@ -0,0 +1,110 @@
+//! The protocol and credentials a repository was cloned with, recorded in its config so that
+//! fetching it keeps authenticating the same way after the global settings change
+
+use std::{path::PathBuf, process::Command};
+
+use anyhow::Result;
+use git2::{ConfigLevel, Repository};
+
+use crate::{forge, git, profile::Profile};
+
+fn key(key: &str) -> String {
+    format!("{}.transport.{key}", env!("CARGO_PKG_NAME"))
+}
+
+/// `grm.transport.*` of a repository
+pub struct Transport {
+    /// The remote cloned from
+    pub remote: String,
+    /// The scheme of its URL, `https` or `ssh`
+    pub protocol: String,
+    /// The profile the clone was made with
+    pub profile: Option<String>,
+    pub ssh_key: Option<PathBuf>,
+    /// Whether the clone authenticated with a token
+    pub token: bool,
+}
+
+impl Transport {
+    pub fn record(&self, repo: &Repository) -> Result<()> {
+        let mut config = repo.config()?.open_level(ConfigLevel::Local)?;
+        config.set_str(&key("remote"), &self.remote)?;
+        config.set_str(&key("protocol"), &self.protocol)?;
+        if let Some(profile) = &self.profile {
+            config.set_str(&key("profile"), profile)?;
+        }
+        if let Some(ssh_key) = &self.ssh_key {
+            config.set_str(&key("sshKey"), &ssh_key.to_string_lossy())?;
+        }
+        config.set_bool(&key("token"), self.token)?;
+        Ok(())
+    }
+
+    /// Read the transport of `repo`, `None` if it was not cloned by grm or has been cleared
+    pub fn load(repo: &Repository) -> Result<Option<Self>> {
+        let config = repo.config()?.open_level(ConfigLevel::Local)?;
+        let (Ok(remote), Ok(protocol)) = (
+            config.get_string(&key("remote")),
+            config.get_string(&key("protocol")),
+        ) else {
+            return Ok(None);
+        };
+        Ok(Some(Self {
+            remote,
+            protocol,
+            profile: config.get_string(&key("profile")).ok(),
+            ssh_key: config.get_path(&key("sshKey")).ok(),
+            token: config.get_bool(&key("token")).unwrap_or(false),
+        }))
+    }
+
+    /// Forget the transport, so that fetches use the current settings
+    pub fn clear(repo: &Repository) -> Result<()> {
+        let mut config = repo.config()?.open_level(ConfigLevel::Local)?;
+        for name in ["remote", "protocol", "profile", "sshKey", "token"] {
+            _ = config.remove(&key(name));
+        }
+        Ok(())
+    }
+
+    /// Make `command`, which fetches from or pushes to the recorded remote, authenticate as the
+    /// clone did
+    ///
+    /// Nothing is applied if the remote no longer uses the recorded protocol.
+    pub fn apply(&self, repo: &Repository, command: &mut Command) -> Result<()> {
+        let remote = repo.find_remote(&self.remote)?;
+        let Some(url) = remote.url().and_then(git::parse_remote_url) else {
+            return Ok(());
+        };
+        if url.scheme() != self.protocol {
+            warn!(
+                "{}: `{}` was cloned over {} but now uses {}; fetching with the current settings",
+                repo.workdir().unwrap_or(repo.path()).display(),
+                self.remote,
+                self.protocol,
+                url.scheme()
+            );
+            return Ok(());
+        }
+        if let Some(ssh_key) = &self.ssh_key {
+            git::use_ssh_key(command, ssh_key);
+        }
+        if self.token {
+            let config = repo.config()?;
+            let profile = Profile::load(&config, self.profile.as_deref())?;
+            let host = url.host_str().unwrap_or_default();
+            let owner = url.path_segments().and_then(|mut s| s.next());
+            match profile.token.or_else(|| forge::token(&config, host, owner)) {
+                Some(token) => {
+                    git::use_token(command, &token, Some(&format!("{}://{host}", url.scheme())));
+                }
+                None => warn!(
+                    "{}: the token it was cloned with is no longer configured, set `{}.{host}.token`",
+                    repo.workdir().unwrap_or(repo.path()).display(),
+                    env!("CARGO_PKG_NAME"),
+                ),
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,303 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{ensure, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::paths;
+
+const INFO_FILE: &str = "info.json";
+const REPO_DIR: &str = "repo";
+const DEFAULT_RETENTION_DAYS: i64 = 30;
+
+/// Removed repositories kept in `<trash dir>/<id>/repo` with an `info.json` beside them
+pub struct Trash {
+    dir: PathBuf,
+}
+
+pub struct Item {
+    pub id: String,
+    pub info: Info,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Info {
+    /// Path relative to the root directory
+    pub name: String,
+    /// Original absolute path
+    pub path: PathBuf,
+    pub removed_at: String,
+}
+
+impl Item {
+    pub fn removed_at(&self) -> Option<chrono::DateTime<chrono::FixedOffset>> {
+        chrono::DateTime::parse_from_rfc3339(&self.info.removed_at).ok()
+    }
+}
+
+/// `grm.trashDir`, defaulting to the data directory
+pub fn get_trash_dir(config: &git2::Config) -> Result<PathBuf> {
+    config
+        .get_path(concat!(env!("CARGO_PKG_NAME"), ".trashDir"))
+        .or_else(|_| Ok(paths::data_dir()?.join("trash")))
+}
+
+impl Trash {
+    pub fn new(config: &git2::Config) -> Result<Self> {
+        Ok(Self {
+            dir: get_trash_dir(config)?,
+        })
+    }
+
+    /// Move the repository at `path` into the trash
+    pub fn put(&self, path: &Path, name: &str) -> Result<Item> {
+        std::fs::create_dir_all(&self.dir)?;
+        let now = chrono::Local::now();
+        let base = now.format("%Y%m%d%H%M%S").to_string();
+        let mut id = base.clone();
+        let mut n = 1;
+        while self.dir.join(&id).exists() {
+            n += 1;
+            id = format!("{base}-{n}");
+        }
+        let item_dir = self.dir.join(&id);
+        std::fs::create_dir(&item_dir)?;
+
+        let info = Info {
+            name: name.to_string(),
+            path: path.to_path_buf(),
+            removed_at: now.to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+        };
+        std::fs::write(item_dir.join(INFO_FILE), serde_json::to_string(&info)?)?;
+        if let Err(err) = std::fs::rename(path, item_dir.join(REPO_DIR)) {
+            _ = std::fs::remove_dir_all(&item_dir);
+            return Err(err).with_context(|| {
+                t!(
+                    TrashOnOtherFilesystem,
+                    path.display(),
+                    self.dir.display(),
+                    concat!(env!("CARGO_PKG_NAME"), ".trashDir"),
+                )
+            });
+        }
+        Ok(Item { id, info })
+    }
+
+    /// Every item in the trash, oldest first
+    pub fn items(&self) -> Result<Vec<Item>> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+        let mut items = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            let Ok(info) = std::fs::read_to_string(entry.path().join(INFO_FILE)) else {
+                continue;
+            };
+            match serde_json::from_str(&info) {
+                Ok(info) => items.push(Item {
+                    id: entry.file_name().to_string_lossy().into_owned(),
+                    info,
+                }),
+                Err(err) => warn!("{}", t!(Skipped, entry.path().display(), err)),
+            }
+        }
+        items.sort_by(|a, b| id_order(&a.id).cmp(&id_order(&b.id)));
+        Ok(items)
+    }
+
+    /// Find an item by its ID, or the latest one removed from `name`
+    pub fn find(&self, query: &str) -> Result<Item> {
+        let query = query.trim_end_matches('/');
+        self.items()?
+            .into_iter()
+            .rev()
+            .find(|item| item.id == query || item.info.name == query)
+            .with_context(|| t!(NotInTrash, query))
+    }
+
+    /// Move the item back to its original path
+    pub fn restore(&self, item: &Item) -> Result<()> {
+        let path = &item.info.path;
+        ensure!(!path.exists(), t!(AlreadyExists, path.display()));
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let item_dir = self.dir.join(&item.id);
+        std::fs::rename(item_dir.join(REPO_DIR), path)?;
+        std::fs::remove_dir_all(item_dir)?;
+        Ok(())
+    }
+
+    /// Delete items removed longer than `retention` ago, or every item if `retention` is `None`
+    pub fn empty(&self, retention: Option<chrono::Duration>) -> Result<Vec<Item>> {
+        let now = chrono::Local::now().fixed_offset();
+        let mut deleted = Vec::new();
+        for item in self.items()? {
+            if retention.is_some_and(|retention| !is_expired(&item, retention, now)) {
+                continue;
+            }
+            std::fs::remove_dir_all(self.dir.join(&item.id))?;
+            deleted.push(item);
+        }
+        Ok(deleted)
+    }
+}
+
+/// The timestamp and the counter of an ID like `20240101120000-2`, as the counter is not padded
+/// and `-10` would sort before `-2` as a string
+fn id_order(id: &str) -> (&str, u32) {
+    match id.split_once('-') {
+        Some((base, n)) => (base, n.parse().unwrap_or(u32::MAX)),
+        None => (id, 1),
+    }
+}
+
+/// How long removed repositories are kept, `None` means forever
+pub fn get_retention(config: &git2::Config) -> Option<chrono::Duration> {
+    let days = config
+        .get_i64(concat!(env!("CARGO_PKG_NAME"), ".trashRetentionDays"))
+        .unwrap_or(DEFAULT_RETENTION_DAYS);
+    (days > 0).then(|| chrono::Duration::days(days))
+}
+
+pub fn is_expired(
+    item: &Item,
+    retention: chrono::Duration,
+    now: chrono::DateTime<chrono::FixedOffset>,
+) -> bool {
+    item.removed_at()
+        .is_some_and(|removed_at| now - removed_at > retention)
+}
+
+#[cfg(test)]
+mod test_is_expired {
+    use super::*;
+
+    fn item(removed_at: &str) -> Item {
+        Item {
+            id: String::new(),
+            info: Info {
+                name: String::new(),
+                path: PathBuf::new(),
+                removed_at: removed_at.to_string(),
+            },
+        }
+    }
+
+    #[test]
+    fn expired() {
+        let now = chrono::DateTime::parse_from_rfc3339("2024-02-01T00:00:00+00:00").unwrap();
+        let retention = chrono::Duration::days(30);
+        assert!(is_expired(
+            &item("2024-01-01T00:00:00+00:00"),
+            retention,
+            now
+        ));
+        assert!(!is_expired(
+            &item("2024-01-03T00:00:00+00:00"),
+            retention,
+            now
+        ));
+    }
+
+    #[test]
+    fn malformed() {
+        let now = chrono::DateTime::parse_from_rfc3339("2024-02-01T00:00:00+00:00").unwrap();
+        assert!(!is_expired(
+            &item("yesterday"),
+            chrono::Duration::days(1),
+            now
+        ));
+    }
+}
+
+#[cfg(test)]
+mod test_id_order {
+    use super::*;
+
+    #[test]
+    fn counter_as_number() {
+        let mut ids = vec!["20240101000000-10", "20240101000000-2", "20240101000000"];
+        ids.sort_by_key(|id| id_order(id));
+        assert_eq!(
+            ids,
+            ["20240101000000", "20240101000000-2", "20240101000000-10"]
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_trash {
+    use super::*;
+
+    /// A trash in a fresh temporary directory, and a directory standing in for a repository
+    fn setup(name: &str) -> (PathBuf, Trash, PathBuf) {
+        let temp = std::env::temp_dir().join(format!("grm-trash-{name}-{}", std::process::id()));
+        _ = std::fs::remove_dir_all(&temp);
+        let repo = temp.join("root/owner/repo");
+        std::fs::create_dir_all(&repo).unwrap();
+        std::fs::write(repo.join("file"), "content").unwrap();
+        let trash = Trash {
+            dir: temp.join("trash"),
+        };
+        (temp, trash, repo)
+    }
+
+    #[test]
+    fn round_trip() {
+        let (temp, trash, repo) = setup("round-trip");
+        let put = trash.put(&repo, "owner/repo").unwrap();
+        assert!(!repo.exists());
+
+        let item = trash.find("owner/repo/").unwrap();
+        assert_eq!(item.id, put.id);
+        assert_eq!(item.info.path, repo);
+        trash.restore(&item).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(repo.join("file")).unwrap(),
+            "content"
+        );
+        assert!(trash.items().unwrap().is_empty());
+
+        _ = std::fs::remove_dir_all(&temp);
+    }
+
+    #[test]
+    fn restore_onto_existing() {
+        let (temp, trash, repo) = setup("restore-onto-existing");
+        let item = trash.put(&repo, "owner/repo").unwrap();
+        std::fs::create_dir_all(&repo).unwrap();
+
+        assert!(trash.restore(&item).is_err());
+        assert_eq!(trash.find(&item.id).unwrap().id, item.id);
+
+        _ = std::fs::remove_dir_all(&temp);
+    }
+
+    #[test]
+    fn empty_expired() {
+        let (temp, trash, repo) = setup("empty-expired");
+        let old = trash.put(&repo, "owner/repo").unwrap();
+        let info = Info {
+            removed_at: "2000-01-01T00:00:00+00:00".to_string(),
+            ..old.info
+        };
+        std::fs::write(
+            trash.dir.join(&old.id).join(INFO_FILE),
+            serde_json::to_string(&info).unwrap(),
+        )
+        .unwrap();
+        std::fs::create_dir_all(&repo).unwrap();
+        let new = trash.put(&repo, "owner/repo").unwrap();
+
+        let deleted = trash.empty(Some(chrono::Duration::days(30))).unwrap();
+        assert_eq!(deleted.len(), 1);
+        assert_eq!(deleted[0].id, old.id);
+        let ids: Vec<String> = trash.items().unwrap().into_iter().map(|i| i.id).collect();
+        assert_eq!(ids, [new.id]);
+
+        _ = std::fs::remove_dir_all(&temp);
+    }
+}
@@ -0,0 +1,346 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use anyhow::Result;
+use git2::{BranchType, Repository};
+use url::Url;
+
+use crate::{
+    events::{Event, Events},
+    foreach, fork, git, headers, proxy, root,
+    transport::Transport,
+};
+
+#[derive(clap::Args)]
+pub struct Options {
+    /// Remote to fetch, defaults to `checkout.defaultRemote`, the remote of the current branch
+    /// and the upstream of a fork
+    #[arg(long, value_name = "NAME", conflicts_with = "all_remotes")]
+    pub remote: Vec<String>,
+    /// Fetch every remote
+    #[arg(long, default_value_t = false)]
+    pub all_remotes: bool,
+    /// Remove remote-tracking branches that no longer exist on the remote
+    #[arg(long, default_value_t = false)]
+    pub prune: bool,
+    /// Rebase the current branch onto its upstream when it has diverged
+    #[arg(long, default_value_t = false)]
+    pub rebase: bool,
+    /// Stash local changes before fast-forwarding or rebasing and apply them afterwards
+    #[arg(long, default_value_t = false)]
+    pub autostash: bool,
+}
+
+/// Repositories that need manual attention after updating
+#[derive(Default)]
+pub struct Summary {
+    pub failures: Vec<(String, anyhow::Error)>,
+    pub diverged: Vec<String>,
+}
+
+/// Fetch each repository and fast-forward its current branch
+pub fn run(
+    roots: &[PathBuf],
+    paths: Vec<PathBuf>,
+    opts: &Options,
+    jobs: usize,
+    events: Events,
+) -> Summary {
+    let summary = Mutex::new(Summary::default());
+    foreach::parallel(paths, jobs, |path| {
+        let name = root::relative(roots, &path);
+        events.emit(&Event::Started { repo: &name });
+        match update(&name, &path, opts, events) {
+            Ok(report) => {
+                let message = match report.head {
+                    Head::UpToDate => t!(UpToDate),
+                    Head::FastForwarded(from, to) => {
+                        let message = t!(FastForwarded, format!("{from:.7}"), format!("{to:.7}"));
+                        info!("{name}: {message}");
+                        message
+                    }
+                    Head::Rebased(from, to) => {
+                        let message = t!(Rebased, format!("{from:.7}"), format!("{to:.7}"));
+                        info!("{name}: {message}");
+                        message
+                    }
+                    Head::Diverged { ahead, behind } => {
+                        let message = t!(Diverged, ahead, behind);
+                        warn!("{name}: {message}");
+                        summary.lock().unwrap().diverged.push(name.clone());
+                        message
+                    }
+                };
+                for branch in report.gone {
+                    warn!("{name}: {}", t!(UpstreamGone, branch));
+                }
+                events.emit(&Event::Finished {
+                    repo: &name,
+                    path: Some(&path),
+                    message: Some(&message),
+                });
+            }
+            Err(err) => {
+                events.emit(&Event::Failed {
+                    repo: &name,
+                    error: &format!("{err:#}"),
+                });
+                summary.lock().unwrap().failures.push((name, err));
+            }
+        }
+    });
+    summary.into_inner().unwrap()
+}
+
+struct Report {
+    head: Head,
+    /// Local branches whose upstream no longer exists
+    gone: Vec<String>,
+}
+
+/// What happened to the current branch
+enum Head {
+    UpToDate,
+    FastForwarded(git2::Oid, git2::Oid),
+    Rebased(git2::Oid, git2::Oid),
+    Diverged { ahead: usize, behind: usize },
+}
+
+fn update(name: &str, path: &Path, opts: &Options, events: Events) -> Result<Report> {
+    let repo = Repository::open(path)?;
+    let upstream_remote = fork::detect(&repo)?;
+
+    // `None` means every remote
+    let remotes = if opts.all_remotes {
+        None
+    } else if opts.remote.is_empty() {
+        Some(default_remotes(&repo, upstream_remote))
+    } else {
+        // skip the remotes this repository does not have
+        let remotes = opts
+            .remote
+            .iter()
+            .filter(|name| repo.find_remote(name).is_ok());
+        Some(remotes.cloned().collect::<Vec<_>>())
+    };
+    if remotes.as_ref().is_none_or(|remotes| !remotes.is_empty()) {
+        let names = match &remotes {
+            None => repo.remotes()?.iter().flatten().map(String::from).collect(),
+            Some(remotes) => remotes.clone(),
+        };
+        let config = repo.config()?;
+        let mut command = git::command(path);
+        if let Some(transport) = Transport::load(&repo)? {
+            if names.contains(&transport.remote) {
+                transport.apply(&repo, &mut command)?;
+            }
+        }
+        for name in &names {
+            if let Some(url) = repo.find_remote(name)?.url() {
+                proxy::apply(&mut command, &config, url);
+            }
+        }
+        command.args(["fetch", "--quiet"]);
+        if opts.prune {
+            command.arg("--prune");
+        }
+        match &remotes {
+            None => command.arg("--all"),
+            Some(remotes) => command.arg("--multiple").args(remotes),
+        };
+        for name in names {
+            let remote = repo.find_remote(&name)?;
+            let Some(url) = remote.url().and_then(|url| Url::parse(url).ok()) else {
+                continue;
+            };
+            let host = url.host_str().unwrap_or_default();
+            headers::set_env(&mut command, &url, &headers::generated(&config, host)?);
+        }
+        git::run_quiet(&mut command)?;
+        events.emit(&Event::Progress {
+            repo: name,
+            message: "fetched",
+        });
+    }
+
+    let autostash = if opts.autostash {
+        "--autostash"
+    } else {
+        "--no-autostash"
+    };
+    let mut head = Head::UpToDate;
+    if let Some((local, upstream)) = head_and_upstream(&repo) {
+        if local == upstream || repo.graph_descendant_of(local, upstream)? {
+            // nothing to pull
+        } else if repo.graph_descendant_of(upstream, local)? {
+            git::run_quiet(git::command(path).args([
+                "merge",
+                "--ff-only",
+                "--quiet",
+                autostash,
+                "@{upstream}",
+            ]))?;
+            head = Head::FastForwarded(local, upstream);
+        } else if opts.rebase {
+            let result = git::run_quiet(git::command(path).args([
+                "rebase",
+                "--quiet",
+                autostash,
+                "@{upstream}",
+            ]));
+            if let Err(err) = result {
+                _ = git::run_quiet(git::command(path).args(["rebase", "--abort"]));
+                return Err(err.context(t!(RebaseAborted)));
+            }
+            head = Head::Rebased(local, upstream);
+        } else {
+            let (ahead, behind) = repo.graph_ahead_behind(local, upstream)?;
+            head = Head::Diverged { ahead, behind };
+        }
+    }
+    Ok(Report {
+        head,
+        gone: gone_branches(&repo)?,
+    })
+}
+
+fn default_remotes(repo: &Repository, upstream_remote: Option<String>) -> Vec<String> {
+    let default_remote = repo
+        .config()
+        .and_then(|config| config.get_string("checkout.defaultRemote"))
+        .ok();
+    let head_remote = repo
+        .head()
+        .ok()
+        .and_then(|head| repo.branch_upstream_remote(head.name()?).ok())
+        .and_then(|name| name.as_str().map(String::from));
+    let fallback = (default_remote.is_none() && head_remote.is_none()).then(|| "origin".into());
+
+    let mut remotes: Vec<String> = Vec::new();
+    for name in [default_remote, head_remote, fallback, upstream_remote]
+        .into_iter()
+        .flatten()
+    {
+        if !remotes.contains(&name) && repo.find_remote(&name).is_ok() {
+            remotes.push(name);
+        }
+    }
+    remotes
+}
+
+fn head_and_upstream(repo: &Repository) -> Option<(git2::Oid, git2::Oid)> {
+    let head = repo.head().ok()?;
+    if !head.is_branch() {
+        return None;
+    }
+    let upstream = git2::Branch::wrap(head).upstream().ok()?;
+    Some((repo.head().ok()?.target()?, upstream.get().target()?))
+}
+
+fn gone_branches(repo: &Repository) -> Result<Vec<String>> {
+    let mut gone = Vec::new();
+    for branch in repo.branches(Some(BranchType::Local))? {
+        let (branch, _) = branch?;
+        let Some(refname) = branch.get().name() else {
+            continue;
+        };
+        if repo.branch_upstream_name(refname).is_ok() && branch.upstream().is_err() {
+            gone.push(branch.name()?.unwrap_or(refname).to_string());
+        }
+    }
+    Ok(gone)
+}
+
+#[cfg(test)]
+mod test_update {
+    use super::*;
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = std::process::Command::new("git")
+            .args(["-c", "user.name=grm", "-c", "user.email=grm@example.test"])
+            .args(["-c", "commit.gpgSign=false"])
+            .args(args)
+            .current_dir(dir)
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+
+    fn head(path: &Path) -> git2::Oid {
+        Repository::open(path)
+            .unwrap()
+            .head()
+            .unwrap()
+            .target()
+            .unwrap()
+    }
+
+    /// A clone to update, another clone that pushed a commit after it was made, and the
+    /// temporary directory holding them and their bare remote
+    fn setup(name: &str) -> (PathBuf, PathBuf, PathBuf) {
+        let temp = std::env::temp_dir().join(format!("grm-update-{name}-{}", std::process::id()));
+        _ = std::fs::remove_dir_all(&temp);
+        std::fs::create_dir_all(&temp).unwrap();
+        git(&temp, &["init", "--quiet", "--bare", "remote.git"]);
+        git(&temp, &["clone", "--quiet", "remote.git", "other"]);
+        let other = temp.join("other");
+        git(
+            &other,
+            &["commit", "--quiet", "--allow-empty", "-m", "init"],
+        );
+        git(&other, &["push", "--quiet", "origin", "HEAD"]);
+        git(&temp, &["clone", "--quiet", "remote.git", "local"]);
+        git(&other, &["commit", "--quiet", "--allow-empty", "-m", "new"]);
+        git(&other, &["push", "--quiet", "origin", "HEAD"]);
+        (temp.join("local"), other, temp)
+    }
+
+    fn options() -> Options {
+        Options {
+            remote: Vec::new(),
+            all_remotes: false,
+            prune: false,
+            rebase: false,
+            autostash: false,
+        }
+    }
+
+    #[test]
+    fn fast_forwarded() {
+        let (local, other, temp) = setup("fast-forwarded");
+        let before = head(&local);
+
+        let report = update("local", &local, &options(), Events::default()).unwrap();
+        assert!(matches!(
+            report.head,
+            Head::FastForwarded(from, to) if from == before && to == head(&other)
+        ));
+        assert_eq!(head(&local), head(&other));
+
+        _ = std::fs::remove_dir_all(&temp);
+    }
+
+    #[test]
+    fn diverged() {
+        let (local, _, temp) = setup("diverged");
+        git(
+            &local,
+            &["commit", "--quiet", "--allow-empty", "-m", "local"],
+        );
+        let before = head(&local);
+
+        let report = update("local", &local, &options(), Events::default()).unwrap();
+        assert!(matches!(
+            report.head,
+            Head::Diverged {
+                ahead: 1,
+                behind: 1
+            }
+        ));
+        assert_eq!(head(&local), before);
+
+        _ = std::fs::remove_dir_all(&temp);
+    }
+}
@@ -0,0 +1,120 @@
+//! Archived and read-only repositories on forges, cached for `list --check-upstream`
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    forge::{self, RepoState},
+    paths,
+    profile::Profile,
+};
+
+/// States older than this are fetched again
+const MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    #[serde(flatten)]
+    state: RepoState,
+    checked_at: SystemTime,
+}
+
+/// States of repositories by their names like `github.com/owner/name`
+pub struct Cache {
+    file: PathBuf,
+    entries: BTreeMap<String, Entry>,
+    changed: bool,
+    /// Hosts whose API failed, not to fail for each repository
+    failed_hosts: HashSet<String>,
+}
+
+impl Cache {
+    pub fn load() -> Result<Self> {
+        let file = paths::cache_dir()?.join("upstream.json");
+        let entries = match std::fs::read_to_string(&file) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_default(),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(err) => return Err(err.into()),
+        };
+        Ok(Self {
+            file,
+            entries,
+            changed: false,
+            failed_hosts: HashSet::new(),
+        })
+    }
+
+    /// The state of the repository `name`, fetched if it is not cached, stale or `refresh` is set
+    pub fn get(
+        &mut self,
+        config: &git2::Config,
+        profile: &Profile,
+        name: &str,
+        refresh: bool,
+    ) -> Result<RepoState> {
+        if let Some(entry) = self.entries.get(name) {
+            let fresh = entry
+                .checked_at
+                .elapsed()
+                .is_ok_and(|elapsed| elapsed < MAX_AGE);
+            if fresh && !refresh {
+                return Ok(entry.state);
+            }
+        }
+        let (host, path) = name
+            .split_once('/')
+            .with_context(|| format!("`{name}` has no host"))?;
+        let path = path.strip_suffix(".git").unwrap_or(path);
+        let state = forge::repo_state(config, profile, host, path)?;
+        self.entries.insert(
+            name.to_string(),
+            Entry {
+                state,
+                checked_at: SystemTime::now(),
+            },
+        );
+        self.changed = true;
+        Ok(state)
+    }
+
+    /// The label of the repository `name`, warning about a failure once per host
+    pub fn label(
+        &mut self,
+        config: &git2::Config,
+        profile: &Profile,
+        name: &str,
+        refresh: bool,
+    ) -> Option<&'static str> {
+        let host = name.split('/').next().unwrap_or_default();
+        if self.failed_hosts.contains(host) {
+            return None;
+        }
+        match self.get(config, profile, name, refresh) {
+            Ok(state) => state.label(),
+            // not pushed to the forge yet
+            Err(err) if forge::is_status(&err, &[404]) => None,
+            Err(err) => {
+                warn!("failed to check upstreams on {host}: {err:#}");
+                self.failed_hosts.insert(host.to_string());
+                None
+            }
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if !self.changed {
+            return Ok(());
+        }
+        if let Some(parent) = self.file.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.file, serde_json::to_string(&self.entries)?)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,196 @@
+//! Tokens encrypted with a passphrase, for systems where plaintext tokens in git config are
+//! undesirable. The key is derived with Argon2 and the tokens are sealed with
+//! ChaCha20-Poly1305.
+
+use std::{collections::BTreeMap, path::PathBuf, sync::OnceLock};
+
+use anyhow::{ensure, Context, Result};
+#[cfg(feature = "vault")]
+use chacha20poly1305::{
+    aead::{rand_core::RngCore, Aead, KeyInit, OsRng},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::paths;
+
+#[cfg(feature = "vault")]
+const VERSION: u32 = 1;
+
+const DISABLED: &str = "build with the `vault` feature to use the token vault";
+
+/// Tokens keyed by host, or by `<host>/<owner>` for tokens limited to an owner
+pub type Tokens = BTreeMap<String, String>;
+
+#[derive(Serialize, Deserialize)]
+struct Sealed {
+    version: u32,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// `grm.vault`, defaulting to `tokens.json` under the config directory
+pub fn get_vault_path(config: &git2::Config) -> Result<PathBuf> {
+    config
+        .get_path(concat!(env!("CARGO_PKG_NAME"), ".vault"))
+        .or_else(|_| Ok(paths::config_dir()?.join("tokens.json")))
+}
+
+pub fn load(config: &git2::Config, passphrase: &str) -> Result<Tokens> {
+    let path = get_vault_path(config)?;
+    let sealed = match std::fs::read_to_string(&path) {
+        Ok(sealed) => sealed,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Tokens::new()),
+        Err(err) => return Err(err.into()),
+    };
+    open(&serde_json::from_str(&sealed)?, passphrase)
+}
+
+pub fn save(config: &git2::Config, tokens: &Tokens, passphrase: &str) -> Result<()> {
+    let path = get_vault_path(config)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&path, serde_json::to_string(&seal(tokens, passphrase)?)?)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+    }
+    Ok(())
+}
+
+/// Open the vault if it exists and a passphrase is available, at most once per process
+///
+/// Threads asking at the same time wait for the first one, so the passphrase is prompted for
+/// only once.
+pub fn unlock(config: &git2::Config) -> Option<&'static Tokens> {
+    static TOKENS: OnceLock<Option<Tokens>> = OnceLock::new();
+    TOKENS.get_or_init(|| open_default(config)).as_ref()
+}
+
+fn open_default(config: &git2::Config) -> Option<Tokens> {
+    if !get_vault_path(config).ok()?.exists() {
+        return None;
+    }
+    let passphrase = match passphrase(false) {
+        Ok(passphrase) => passphrase,
+        Err(err) => {
+            warn!("{}", t!(VaultSkipped, format!("{err:#}")));
+            return None;
+        }
+    };
+    match load(config, &passphrase) {
+        Ok(tokens) => Some(tokens),
+        Err(err) => {
+            warn!("{}", t!(VaultSkipped, format!("{err:#}")));
+            None
+        }
+    }
+}
+
+/// Read `GRM_PASSPHRASE`, or prompt for the passphrase on the terminal
+pub fn passphrase(confirm: bool) -> Result<String> {
+    ensure!(cfg!(feature = "vault"), DISABLED);
+    if let Ok(passphrase) = std::env::var("GRM_PASSPHRASE") {
+        return Ok(passphrase);
+    }
+    let passphrase = rpassword::prompt_password(t!(VaultPassphrase))
+        .with_context(|| t!(PassphraseNeedsTerminal))?;
+    if confirm {
+        let again = rpassword::prompt_password(t!(ConfirmPassphrase))?;
+        ensure!(passphrase == again, t!(PassphrasesDiffer));
+    }
+    Ok(passphrase)
+}
+
+#[cfg(feature = "vault")]
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Key> {
+    let mut key = Key::default();
+    argon2::Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|err| anyhow::anyhow!("failed to derive a key: {err}"))?;
+    Ok(key)
+}
+
+#[cfg(feature = "vault")]
+fn seal(tokens: &Tokens, passphrase: &str) -> Result<Sealed> {
+    let mut salt = [0; 16];
+    OsRng.fill_bytes(&mut salt);
+    let mut nonce = [0; 12];
+    OsRng.fill_bytes(&mut nonce);
+    let cipher = ChaCha20Poly1305::new(&derive_key(passphrase, &salt)?);
+    let ciphertext = cipher
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            serde_json::to_vec(tokens)?.as_slice(),
+        )
+        .map_err(|_| anyhow::anyhow!("failed to encrypt tokens"))?;
+    Ok(Sealed {
+        version: VERSION,
+        salt: to_hex(&salt),
+        nonce: to_hex(&nonce),
+        ciphertext: to_hex(&ciphertext),
+    })
+}
+
+#[cfg(feature = "vault")]
+fn open(sealed: &Sealed, passphrase: &str) -> Result<Tokens> {
+    ensure!(
+        sealed.version == VERSION,
+        "unsupported vault version {}",
+        sealed.version
+    );
+    let nonce = from_hex(&sealed.nonce)?;
+    ensure!(nonce.len() == 12, "malformed nonce");
+    let cipher = ChaCha20Poly1305::new(&derive_key(passphrase, &from_hex(&sealed.salt)?)?);
+    let plaintext = cipher
+        .decrypt(
+            Nonce::from_slice(&nonce),
+            from_hex(&sealed.ciphertext)?.as_slice(),
+        )
+        .map_err(|_| anyhow::anyhow!("wrong passphrase or corrupted vault"))?;
+    Ok(serde_json::from_slice(&plaintext)?)
+}
+
+#[cfg(not(feature = "vault"))]
+fn seal(_tokens: &Tokens, _passphrase: &str) -> Result<Sealed> {
+    anyhow::bail!(DISABLED)
+}
+
+#[cfg(not(feature = "vault"))]
+fn open(_sealed: &Sealed, _passphrase: &str) -> Result<Tokens> {
+    anyhow::bail!(DISABLED)
+}
+
+#[cfg(feature = "vault")]
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(feature = "vault")]
+fn from_hex(s: &str) -> Result<Vec<u8>> {
+    ensure!(
+        s.len().is_multiple_of(2) && s.is_ascii(),
+        "malformed hex string"
+    );
+    (0..s.len())
+        .step_by(2)
+        .map(|i| Ok(u8::from_str_radix(&s[i..i + 2], 16)?))
+        .collect()
+}
+
+#[cfg(all(test, feature = "vault"))]
+mod test_seal {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let tokens = Tokens::from([("github.com".into(), "secret".into())]);
+        let sealed = seal(&tokens, "pass").unwrap();
+        assert!(!sealed.ciphertext.contains(&to_hex(b"secret")));
+        assert_eq!(open(&sealed, "pass").unwrap(), tokens);
+        assert!(open(&sealed, "wrong").is_err());
+    }
+}
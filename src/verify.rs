@@ -0,0 +1,127 @@
+//! Integrity checks of `grm verify`, a subset of `git fsck` done through libgit2
+
+use std::{collections::HashSet, path::Path};
+
+use anyhow::Result;
+use git2::{ObjectType, Oid, Repository};
+
+/// Something wrong found in a repository
+pub enum Problem {
+    /// An object that cannot be read, or whose content does not match its id
+    Corrupt { oid: Oid, reason: String },
+    /// An object referenced by another object or a reference but not in the database
+    Missing { oid: Oid, referrer: String },
+    /// A reference that cannot be resolved
+    BrokenRef { name: String, reason: String },
+}
+
+impl std::fmt::Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Corrupt { oid, reason } => write!(f, "corrupt object {oid}: {reason}"),
+            Self::Missing { oid, referrer } => {
+                write!(f, "missing object {oid} referenced by {referrer}")
+            }
+            Self::BrokenRef { name, reason } => write!(f, "broken ref {name}: {reason}"),
+        }
+    }
+}
+
+/// Check every object in the database, then everything reachable from the references
+pub fn verify(path: &Path) -> Result<Vec<Problem>> {
+    let repo = Repository::open(path)?;
+    let mut problems = check_objects(&repo)?;
+    problems.extend(check_refs(&repo)?);
+    Ok(problems)
+}
+
+fn check_objects(repo: &Repository) -> Result<Vec<Problem>> {
+    let odb = repo.odb()?;
+    let mut oids = Vec::new();
+    odb.foreach(|oid| {
+        oids.push(*oid);
+        true
+    })?;
+    let mut problems = Vec::new();
+    for oid in oids {
+        let reason = match odb.read(oid) {
+            Ok(object) => match Oid::hash_object(object.kind(), object.data()) {
+                Ok(actual) if actual == oid => continue,
+                Ok(actual) => format!("content hashes to {actual}"),
+                Err(err) => err.message().to_string(),
+            },
+            Err(err) => err.message().to_string(),
+        };
+        problems.push(Problem::Corrupt { oid, reason });
+    }
+    Ok(problems)
+}
+
+fn check_refs(repo: &Repository) -> Result<Vec<Problem>> {
+    let odb = repo.odb()?;
+    // parents of these commits are cut off on purpose
+    let shallow: HashSet<Oid> = std::fs::read_to_string(repo.path().join("shallow"))
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| Oid::from_str(line.trim()).ok())
+        .collect();
+
+    let mut problems = Vec::new();
+    let mut seen = HashSet::new();
+    let mut pending = Vec::new();
+    for reference in repo.references()? {
+        let reference = match reference {
+            Ok(reference) => reference,
+            Err(err) => {
+                problems.push(Problem::BrokenRef {
+                    name: "(unreadable)".into(),
+                    reason: err.message().to_string(),
+                });
+                continue;
+            }
+        };
+        let name = String::from_utf8_lossy(reference.name_bytes()).into_owned();
+        match reference.resolve() {
+            Ok(resolved) => {
+                if let Some(oid) = resolved.target() {
+                    pending.push((oid, name));
+                }
+            }
+            Err(err) => problems.push(Problem::BrokenRef {
+                name,
+                reason: err.message().to_string(),
+            }),
+        }
+    }
+
+    while let Some((oid, referrer)) = pending.pop() {
+        if !seen.insert(oid) {
+            continue;
+        }
+        if !odb.exists(oid) {
+            problems.push(Problem::Missing { oid, referrer });
+            continue;
+        }
+        // corrupt objects are already reported by `check_objects`
+        let Ok(object) = repo.find_object(oid, None) else {
+            continue;
+        };
+        let referrer = format!("{} {oid}", object.kind().map_or("object", |k| k.str()));
+        if let Some(commit) = object.as_commit() {
+            pending.push((commit.tree_id(), referrer.clone()));
+            if !shallow.contains(&oid) {
+                pending.extend(commit.parent_ids().map(|id| (id, referrer.clone())));
+            }
+        } else if let Some(tree) = object.as_tree() {
+            for entry in tree.iter() {
+                // submodules point to commits of other repositories
+                if entry.kind() != Some(ObjectType::Commit) {
+                    pending.push((entry.id(), referrer.clone()));
+                }
+            }
+        } else if let Some(tag) = object.as_tag() {
+            pending.push((tag.target_id(), referrer));
+        }
+    }
+    Ok(problems)
+}
@@ -0,0 +1,85 @@
+//! Multi-package layouts scaffolded by `grm new --workspace`
+
+use std::{io::Write, path::Path, process::Stdio};
+
+use anyhow::{ensure, Context, Result};
+
+use crate::notify;
+
+/// Scaffold `members` in the repository at `path` with `grm.workspaceCommand`, or as a cargo
+/// workspace if it is unset
+pub fn scaffold(config: &git2::Config, path: &Path, members: &[String]) -> Result<()> {
+    for member in members {
+        ensure!(
+            !member.is_empty()
+                && !member.starts_with('.')
+                && !member.contains(['/', '\\'])
+                && !path.join(member).exists(),
+            "cannot create the member `{member}`"
+        );
+    }
+    match config.get_string(concat!(env!("CARGO_PKG_NAME"), ".workspaceCommand")) {
+        Ok(command) => run_command(&command, path, members),
+        Err(_) => cargo(path, members),
+    }
+}
+
+/// Run `command` in the repository with the member names in `GRM_MEMBERS`, one per line
+fn run_command(command: &str, path: &Path, members: &[String]) -> Result<()> {
+    let status = notify::shell(command)
+        .current_dir(path)
+        .env("GRM_PATH", path)
+        .env("GRM_MEMBERS", members.join("\n"))
+        .stdin(Stdio::null())
+        .status()
+        .with_context(|| format!("failed to run `{command}`"))?;
+    ensure!(status.success(), "`{command}` exited with {status}");
+    Ok(())
+}
+
+/// A root manifest listing `members`, each of which is a library crate
+fn cargo(path: &Path, members: &[String]) -> Result<()> {
+    write_new(&path.join("Cargo.toml"), &root_manifest(members))?;
+    write_new(&path.join(".gitignore"), "/target\n")?;
+    for member in members {
+        let dir = path.join(member);
+        std::fs::create_dir_all(dir.join("src"))?;
+        write_new(&dir.join("Cargo.toml"), &member_manifest(member))?;
+        write_new(&dir.join("src/lib.rs"), "")?;
+    }
+    Ok(())
+}
+
+fn root_manifest(members: &[String]) -> String {
+    let members: Vec<_> = members.iter().map(|member| format!("{member:?}")).collect();
+    format!(
+        "[workspace]\nresolver = \"2\"\nmembers = [{}]\n",
+        members.join(", ")
+    )
+}
+
+fn member_manifest(name: &str) -> String {
+    format!(
+        "[package]\nname = {name:?}\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[dependencies]\n"
+    )
+}
+
+/// Write `content` to `file`, failing instead of overwriting an existing file
+fn write_new(file: &Path, content: &str) -> Result<()> {
+    std::fs::File::create_new(file)
+        .and_then(|mut f| f.write_all(content.as_bytes()))
+        .with_context(|| format!("failed to create `{}`", file.display()))
+}
+
+#[cfg(test)]
+mod test_root_manifest {
+    use super::*;
+
+    #[test]
+    fn members() {
+        assert_eq!(
+            root_manifest(&["member-a".into(), "member-b".into()]),
+            "[workspace]\nresolver = \"2\"\nmembers = [\"member-a\", \"member-b\"]\n"
+        );
+    }
+}
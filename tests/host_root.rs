@@ -0,0 +1,78 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// A fresh home directory with a `grm.<host>.root` routing `example.test` to its own root, and
+/// a local repository served as `https://example.test/owner/repo`
+fn setup(name: &str) -> PathBuf {
+    let home = std::env::temp_dir().join(format!("grm-test-{name}-{}", std::process::id()));
+    _ = std::fs::remove_dir_all(&home);
+    let source = home.join("source");
+    std::fs::create_dir_all(&source).unwrap();
+    git(&source, &["init", "--quiet", "repo"]);
+    git(
+        &source.join("repo"),
+        &[
+            "-c",
+            "user.name=grm",
+            "-c",
+            "user.email=grm@example.test",
+            "commit",
+            "--quiet",
+            "--allow-empty",
+            "--message=init",
+        ],
+    );
+    std::fs::write(
+        home.join(".gitconfig"),
+        format!(
+            "[grm]\n\troot = {}\n[grm \"example.test\"]\n\troot = {}\n\
+             [url \"file://{}/\"]\n\tinsteadOf = https://example.test/owner/\n",
+            home.join("root").display(),
+            home.join("host-root").display(),
+            source.display(),
+        ),
+    )
+    .unwrap();
+    home
+}
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+fn grm(home: &Path, args: &[&str]) -> bool {
+    Command::new(env!("CARGO_BIN_EXE_grm"))
+        .args(args)
+        .env("HOME", home)
+        .env_remove("GRM_ROOT")
+        .env_remove("GIT_CONFIG_GLOBAL")
+        .env_remove("XDG_CONFIG_HOME")
+        .status()
+        .unwrap()
+        .success()
+}
+
+#[test]
+fn get_and_remove() {
+    let home = setup("get-and-remove");
+    let path = home.join("host-root/example.test/owner/repo");
+
+    assert!(grm(
+        &home,
+        &["get", "--backend", "git", "https://example.test/owner/repo"]
+    ));
+    assert!(path.join(".git").is_dir());
+    assert!(!home.join("root/example.test").exists());
+
+    assert!(grm(&home, &["remove", "--permanent", "owner/repo"]));
+    assert!(!path.exists());
+
+    _ = std::fs::remove_dir_all(&home);
+}